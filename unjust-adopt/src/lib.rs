@@ -0,0 +1,150 @@
+use console::style;
+use facet::Facet;
+use std::fs;
+use unjust_core::{
+    add_exclude_entry, adopt_justfile, detect_current_repo, ensure_cache_dir, find_local_justfile,
+    remote_url, update_manifest,
+};
+
+/// Arguments for the "adopt" command
+#[derive(Facet, Debug)]
+pub struct AdoptArgs {
+    /// Repo identifier (username/repo), detected from git remotes if omitted
+    #[facet(positional, default)]
+    pub repo: Option<String>,
+
+    /// Delete the local Justfile once it is in the store
+    #[facet(named)]
+    pub remove: bool,
+
+    /// Add the local Justfile to .git/info/exclude
+    #[facet(named)]
+    pub exclude: bool,
+
+    /// Overwrite an existing Justfile in the store
+    #[facet(named, short = 'f')]
+    pub force: bool,
+}
+
+/// Handle the "adopt" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_adopt_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let adopt_args = match facet_args::from_slice::<AdoptArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let current_dir = match std::env::current_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!(
+                "{} Failed to get current directory: {}",
+                style("Error:").red().bold(),
+                e
+            );
+            return 1;
+        }
+    };
+
+    let cache_dir = match ensure_cache_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let local_justfile = match find_local_justfile(&current_dir) {
+        Ok(Some(path)) => path,
+        Ok(None) => {
+            eprintln!(
+                "{} No Justfile found in the current directory",
+                style("Error:").red().bold()
+            );
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    // Use the given repo, or detect it from the git remotes
+    let repo = match adopt_args.repo {
+        Some(repo) => repo,
+        None => match detect_current_repo(&current_dir, &cache_dir) {
+            Ok(Some(repo)) => repo.name,
+            Ok(None) => {
+                eprintln!(
+                    "{} Could not detect the repository, please specify it",
+                    style("Error:").red().bold()
+                );
+                return 1;
+            }
+            Err(e) => {
+                eprintln!("{} {}", style("Error:").red().bold(), e);
+                return 1;
+            }
+        },
+    };
+
+    let stored = match adopt_justfile(&cache_dir, &repo, &local_justfile, adopt_args.force) {
+        Ok(stored) => stored,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            if e.kind() == std::io::ErrorKind::AlreadyExists {
+                eprintln!("Use '{}' to overwrite it", style("--force").green());
+            }
+            return 1;
+        }
+    };
+
+    // Note where it came from, when the current directory has git remotes
+    let origin = remote_url(&current_dir, "origin").ok().flatten();
+    let upstream = detect_current_repo(&current_dir, &cache_dir)
+        .ok()
+        .flatten()
+        .and_then(|r| r.upstream);
+    if let Some(entry) = stored.parent()
+        && let Err(e) = update_manifest(entry, |manifest| {
+            manifest.source_remote = origin.or(manifest.source_remote.take());
+            manifest.upstream = upstream.or(manifest.upstream.take());
+        })
+    {
+        eprintln!("{} {}", style("Error:").red().bold(), e);
+        return 1;
+    }
+
+    println!(
+        "{} Adopted {} as the Justfile for: {}",
+        style("Success:").green().bold(),
+        local_justfile.display(),
+        style(&repo).green()
+    );
+
+    if adopt_args.exclude {
+        match add_exclude_entry(&local_justfile) {
+            Ok(true) => println!("Added {} to .git/info/exclude", local_justfile.display()),
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("{} {}", style("Error:").red().bold(), e);
+                return 1;
+            }
+        }
+    }
+
+    if adopt_args.remove {
+        if let Err(e) = fs::remove_file(&local_justfile) {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+        println!("Removed local copy: {}", local_justfile.display());
+    }
+
+    0
+}
@@ -0,0 +1,143 @@
+use console::style;
+use facet::Facet;
+use std::io;
+use unjust_core::{add_binding, ensure_cache_dir, normalise, read_bindings, remove_binding};
+
+/// Arguments for the "bind" command
+#[derive(Facet, Debug)]
+pub struct BindArgs {
+    /// Repo identifier (username/repo) to bind to
+    #[facet(positional, default)]
+    pub repo: Option<String>,
+
+    /// Directory or path glob to bind (defaults to the current directory)
+    #[facet(positional, default)]
+    pub path: Option<String>,
+}
+
+/// Arguments for the "unbind" command
+#[derive(Facet, Debug)]
+pub struct UnbindArgs {
+    /// Directory or path glob to unbind (defaults to the current directory)
+    #[facet(positional, default)]
+    pub path: Option<String>,
+}
+
+/// Turn a path or glob argument into the absolute pattern stored in a binding
+fn binding_pattern(path: Option<&str>) -> io::Result<String> {
+    let current_dir = std::env::current_dir()?;
+    // Resolved lexically, as a glob or a directory yet to be created has
+    // nothing on disk to canonicalise
+    let pattern = match path {
+        Some(path) => normalise(&current_dir.join(path)),
+        None => current_dir,
+    };
+    Ok(pattern.display().to_string())
+}
+
+/// Handle the "bind" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_bind_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let bind_args = match facet_args::from_slice::<BindArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let cache_dir = match ensure_cache_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    // Without a repo, list the existing bindings
+    let Some(repo) = bind_args.repo.as_deref() else {
+        return match read_bindings(&cache_dir) {
+            Ok(bindings) if bindings.is_empty() => {
+                println!(
+                    "No bindings. Use '{}' to add one.",
+                    style("unjust bind <repo>").green()
+                );
+                0
+            }
+            Ok(bindings) => {
+                for binding in bindings {
+                    println!("{} -> {}", binding.pattern, style(&binding.repo).green());
+                }
+                0
+            }
+            Err(e) => {
+                eprintln!("{} {}", style("Error:").red().bold(), e);
+                1
+            }
+        };
+    };
+
+    let result = binding_pattern(bind_args.path.as_deref())
+        .and_then(|pattern| add_binding(&cache_dir, &pattern, repo).map(|_| pattern));
+
+    match result {
+        Ok(pattern) => {
+            println!(
+                "{} Bound {} to {}",
+                style("Success:").green().bold(),
+                pattern,
+                style(repo).green()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
+
+/// Handle the "unbind" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_unbind_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let unbind_args = match facet_args::from_slice::<UnbindArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let result = ensure_cache_dir().and_then(|cache_dir| {
+        let pattern = binding_pattern(unbind_args.path.as_deref())?;
+        remove_binding(&cache_dir, &pattern).map(|removed| (pattern, removed))
+    });
+
+    match result {
+        Ok((_, Some(binding))) => {
+            println!(
+                "{} Unbound {} from {}",
+                style("Success:").green().bold(),
+                binding.pattern,
+                style(&binding.repo).green()
+            );
+            0
+        }
+        Ok((pattern, None)) => {
+            eprintln!(
+                "{} No binding for: {}",
+                style("Error:").red().bold(),
+                pattern
+            );
+            1
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
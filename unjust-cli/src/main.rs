@@ -1,11 +1,22 @@
 use console::style;
 use std::env;
 use std::process::exit;
+use unjust_adopt::handle_adopt_command;
+use unjust_bind::{handle_bind_command, handle_unbind_command};
 use unjust_core::{ensure_cache_dir, is_first_use};
+use unjust_history::handle_history_command;
 use unjust_init::handle_init_command;
 use unjust_list::handle_list_command;
+use unjust_log::{handle_diff_command, handle_log_command, handle_restore_command};
+use unjust_pin::{handle_pin_command, handle_update_command};
+use unjust_remote::{handle_remote_command, handle_subscribe_command};
+use unjust_remove::handle_remove_command;
+use unjust_rename::handle_rename_command;
+use unjust_serve::handle_serve_command;
+use unjust_status::handle_status_command;
 use unjust_sync::handle_sync_command;
-use unjust_use::handle_use_command;
+use unjust_tag::handle_tag_command;
+use unjust_use::{handle_unlink_command, handle_use_command};
 
 fn main() {
     // Get command line arguments, skipping the program name
@@ -38,10 +49,38 @@ fn main() {
             let exit_code = handle_use_command(command_args);
             exit(exit_code);
         }
+        "unlink" => {
+            let exit_code = handle_unlink_command(command_args);
+            exit(exit_code);
+        }
+        "pin" => {
+            let exit_code = handle_pin_command(command_args);
+            exit(exit_code);
+        }
+        "update" => {
+            let exit_code = handle_update_command(command_args);
+            exit(exit_code);
+        }
         "sync" => {
             let exit_code = handle_sync_command(command_args);
             exit(exit_code);
         }
+        "status" => {
+            let exit_code = handle_status_command(command_args);
+            exit(exit_code);
+        }
+        "remote" => {
+            let exit_code = handle_remote_command(command_args);
+            exit(exit_code);
+        }
+        "subscribe" => {
+            let exit_code = handle_subscribe_command(command_args);
+            exit(exit_code);
+        }
+        "serve" => {
+            let exit_code = handle_serve_command(command_args);
+            exit(exit_code);
+        }
         "init" => {
             let exit_code = handle_init_command(command_args);
             exit(exit_code);
@@ -50,6 +89,46 @@ fn main() {
             let exit_code = handle_list_command(command_args);
             exit(exit_code);
         }
+        "remove" => {
+            let exit_code = handle_remove_command(command_args);
+            exit(exit_code);
+        }
+        "rename" => {
+            let exit_code = handle_rename_command(command_args);
+            exit(exit_code);
+        }
+        "adopt" => {
+            let exit_code = handle_adopt_command(command_args);
+            exit(exit_code);
+        }
+        "bind" => {
+            let exit_code = handle_bind_command(command_args);
+            exit(exit_code);
+        }
+        "unbind" => {
+            let exit_code = handle_unbind_command(command_args);
+            exit(exit_code);
+        }
+        "tag" => {
+            let exit_code = handle_tag_command(command_args);
+            exit(exit_code);
+        }
+        "history" => {
+            let exit_code = handle_history_command(command_args);
+            exit(exit_code);
+        }
+        "log" => {
+            let exit_code = handle_log_command(command_args);
+            exit(exit_code);
+        }
+        "diff" => {
+            let exit_code = handle_diff_command(command_args);
+            exit(exit_code);
+        }
+        "restore" => {
+            let exit_code = handle_restore_command(command_args);
+            exit(exit_code);
+        }
         _ => {
             eprintln!(
                 "{} Unknown command: {}",
@@ -70,16 +149,74 @@ fn print_usage(program: &str) {
     );
     eprintln!("\n{}:", style("Commands").yellow().bold());
     eprintln!(
-        "  {} [--separate-upstream-justfile] [--force|-f] <repo>",
+        "  {} [--separate-upstream-justfile] [--force|-f] [--link|--copy] [--recipe|-r recipe] [repo]",
         style("use").green()
     );
     eprintln!("      Use a Justfile from remote storage");
-    eprintln!("  {} [--force-push] [repo]", style("sync").green());
+    eprintln!("  {} [--force|-f]", style("unlink").green());
+    eprintln!("      Remove a Justfile placed by 'use --link'");
+    eprintln!("  {} [--remove] [--rev rev] [repo]", style("pin").green());
+    eprintln!("      Keep using a revision of a Justfile in this checkout");
+    eprintln!("  {} [repo]", style("update").green());
+    eprintln!("      Move this checkout's pins on to the latest revisions");
+    eprintln!(
+        "  {} [--force-push] [--dry-run] [--remote name] [--jobs|-j count] [repo]",
+        style("sync").green()
+    );
     eprintln!("      Sync Justfiles with remote storage");
-    eprintln!("  {} [-t template] [name]", style("init").green());
+    eprintln!("  {} [--porcelain] [repo]", style("status").green());
+    eprintln!("      Show how stored Justfiles compare with remote storage");
+    eprintln!(
+        "  {} [add|remove|priority|list] [name] [kind|position] [location]",
+        style("remote").green()
+    );
+    eprintln!("      Configure the remote storage to sync with (lists remotes without args)");
+    eprintln!(
+        "  {} [username/repo | name kind location]",
+        style("subscribe").green()
+    );
+    eprintln!("      Sync someone else's Justfiles read-only (lists subscriptions without args)");
+    eprintln!(
+        "  {} [--bind address] [--port port] <dir>",
+        style("serve").green()
+    );
+    eprintln!("      Serve a storage directory over HTTP for 'http' remotes");
+    eprintln!("  {} [-t template|tag] [name]", style("init").green());
     eprintln!("      Initialize a new Justfile for the current repo");
-    eprintln!("  {} [--paths|-p]", style("list").green());
+    eprintln!(
+        "  {} [--paths|-p] [--recipes|-r] [--tag tag] [--sort name|recent]",
+        style("list").green()
+    );
     eprintln!("      List available Justfiles");
+    eprintln!(
+        "  {} [--undo] <repo> | --empty-trash",
+        style("remove").green()
+    );
+    eprintln!("      Remove a Justfile from the store (or restore it)");
+    eprintln!("  {} <from> <to>", style("rename").green());
+    eprintln!("      Move a Justfile to a different repo id");
+    eprintln!(
+        "  {} [--remove] [--exclude] [--force|-f] [repo]",
+        style("adopt").green()
+    );
+    eprintln!("      Import the current directory's Justfile into the store");
+    eprintln!("  {} [repo] [path|glob]", style("bind").green());
+    eprintln!("      Use a repo's Justfile for a directory (lists bindings without args)");
+    eprintln!("  {} [path|glob]", style("unbind").green());
+    eprintln!("      Remove a directory binding");
+    eprintln!("  {} [add|remove|list] [tag] [repo]", style("tag").green());
+    eprintln!("      Tag a Justfile to group it with others (lists tags without args)");
+    eprintln!(
+        "  {} [--failed] [--stats] [--limit|-n count] [repo]",
+        style("history").green()
+    );
+    eprintln!("      Show recent uses of stored Justfiles");
+    eprintln!("  {} [repo]", style("log").green());
+    eprintln!("      Show the revisions of a stored Justfile");
+    eprintln!("  {} [repo] [rev]", style("diff").green());
+    eprintln!("      Compare a stored Justfile with a revision (defaults to the last change)");
+    eprintln!("  {} <repo> <rev>", style("restore").green());
+    eprintln!("      Roll a stored Justfile back to a revision");
 }
 
 // Command argument structs using facet
@@ -142,32 +142,53 @@ fn test_cli_use_command() -> std::io::Result<()> {
 }
 
 #[test]
-fn test_cli_init_command() {
-    // Test basic init
-    let mut cmd = Command::cargo_bin("unjust").unwrap();
-    cmd.arg("init")
-        .assert()
-        .success()
-        .stdout(predicate::str::contains("Would initialize new Justfile"));
+fn test_cli_init_command() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    create_test_repo(cache_dir, "template", "# Template justfile")?;
 
-    // Test init with name
-    let mut cmd = Command::cargo_bin("unjust").unwrap();
-    cmd.arg("init")
-        .arg("custom")
-        .assert()
-        .success()
-        .stdout(predicate::str::contains(
-            "Would initialize new Justfile with name: custom",
-        ));
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            // Test basic init
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("init")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Would initialize new Justfile"));
 
-    // Test init with template
-    let mut cmd = Command::cargo_bin("unjust").unwrap();
-    cmd.arg("init")
-        .arg("-t")
-        .arg("template")
-        .assert()
-        .success()
-        .stdout(predicate::str::contains("Would use template: template"));
+            // Test init with name
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("init")
+                .arg("custom")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                    "Would initialize new Justfile with name: custom",
+                ));
+
+            // Test init with template
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("init")
+                .arg("-t")
+                .arg("template")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Would use template: template"));
+
+            // Test init with a template that doesn't exist
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("init")
+                .arg("-t")
+                .arg("missing")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("No Justfile or tag found"));
+        },
+    );
+
+    Ok(())
 }
 
 #[test]
@@ -181,29 +202,1607 @@ fn test_cli_sync_command() {
         "UNJUST_CACHE_DIR",
         Some(cache_dir.to_str().unwrap()),
         || {
-            // Test sync with no arguments
+            // Syncing needs a remote
             let mut cmd = Command::cargo_bin("unjust").unwrap();
             cmd.arg("sync")
                 .assert()
-                .success()
-                .stdout(predicate::str::contains("Would sync all repos"));
+                .failure()
+                .stderr(predicate::str::contains("No remote configured"));
 
-            // Test sync with repo argument
             let mut cmd = Command::cargo_bin("unjust").unwrap();
-            cmd.arg("sync")
-                .arg("test/repo")
+            cmd.args(["remote", "add", "origin", "ftp", "example.com"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("Unknown storage kind: ftp"));
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["remote", "add", "origin", "git", "/srv/justfiles.git"])
+                .assert()
+                .success();
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("remote")
                 .assert()
                 .success()
-                .stdout(predicate::str::contains("Would sync repo: test/repo"));
+                .stdout(predicate::str::contains("origin (git) /srv/justfiles.git"));
 
-            // Test sync with --force-push flag
             let mut cmd = Command::cargo_bin("unjust").unwrap();
-            cmd.arg("sync").arg("--force-push").assert().success();
+            cmd.args(["remote", "remove", "origin"]).assert().success();
         },
     );
 }
 
-// This test simulates a complete workflow using the CLI
+// Run unjust against a particular cache directory
+fn unjust(cache_dir: &Path, args: &[&str]) -> assert_cmd::assert::Assert {
+    Command::cargo_bin("unjust")
+        .unwrap()
+        .env("UNJUST_CACHE_DIR", cache_dir)
+        .args(args)
+        .assert()
+}
+
+#[test]
+fn test_cli_sync_with_git_remote() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let remote = temp_dir.path().join("remote.git");
+    fs::create_dir_all(&remote)?;
+    let status = std::process::Command::new("git")
+        .args(["init", "--quiet", "--bare"])
+        .current_dir(&remote)
+        .status()?;
+    assert!(status.success());
+    let remote = remote.to_str().unwrap();
+
+    // Two machines sharing one remote
+    let laptop = temp_dir.path().join("laptop");
+    let desktop = temp_dir.path().join("desktop");
+    create_test_repo(&laptop, "user/shared", "build:\n    cargo build\n")?;
+    fs::create_dir_all(&desktop)?;
+    for cache_dir in [&laptop, &desktop] {
+        unjust(cache_dir, &["remote", "add", "origin", "git", remote]).success();
+    }
+    let on_laptop = laptop.join("user/shared/Justfile");
+    let on_desktop = desktop.join("user/shared/Justfile");
+
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("push +user/shared").unwrap());
+    unjust(&desktop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("pull +user/shared").unwrap());
+    assert_eq!(
+        fs::read_to_string(&on_desktop)?,
+        "build:\n    cargo build\n"
+    );
+    unjust(&desktop, &["status", "--porcelain"])
+        .success()
+        .stdout("clean\tuser/shared\torigin\n");
+
+    // Separate edits to different lines are merged
+    fs::write(&on_desktop, "# Shared\nbuild:\n    cargo build\n")?;
+    fs::write(&on_laptop, "build:\n    cargo build --release\n")?;
+    unjust(&desktop, &["sync"]).success();
+    unjust(&laptop, &["sync", "--dry-run"])
+        .success()
+        .stdout(predicate::str::is_match("merge +user/shared").unwrap());
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("merge +user/shared").unwrap());
+    unjust(&desktop, &["sync"]).success();
+    let merged = "# Shared\nbuild:\n    cargo build --release\n";
+    assert_eq!(fs::read_to_string(&on_laptop)?, merged);
+    assert_eq!(fs::read_to_string(&on_desktop)?, merged);
+
+    // Edits to the same line conflict until one side is forced
+    fs::write(&on_desktop, "# Shared\nbuild:\n    make\n")?;
+    fs::write(&on_laptop, "# Shared\nbuild:\n    ninja\n")?;
+    unjust(&desktop, &["sync"]).success();
+    unjust(&laptop, &["sync"])
+        .failure()
+        .stderr(predicate::str::contains("Conflict:"));
+    assert_eq!(
+        fs::read_to_string(&on_laptop)?,
+        "# Shared\nbuild:\n    ninja\n"
+    );
+    unjust(&laptop, &["sync", "--force-push"]).success();
+    unjust(&desktop, &["sync"]).success();
+    assert_eq!(
+        fs::read_to_string(&on_desktop)?,
+        "# Shared\nbuild:\n    ninja\n"
+    );
+
+    // Removals are propagated
+    unjust(&laptop, &["remove", "user/shared"]).success();
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::contains(
+            "Replayed queued remove user/shared",
+        ));
+    unjust(&desktop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("delete-local +user/shared").unwrap());
+    assert!(!on_desktop.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_sync_with_directory_remote() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let shared = temp_dir.path().join("shared drive");
+    let laptop = temp_dir.path().join("laptop");
+    let desktop = temp_dir.path().join("desktop");
+    create_test_repo(&laptop, "user/shared", "build:\n")?;
+    fs::create_dir_all(&desktop)?;
+
+    // The directory has to exist, in case it's a drive that isn't mounted
+    unjust(
+        &laptop,
+        &["remote", "add", "drive", "dir", shared.to_str().unwrap()],
+    )
+    .success();
+    unjust(&laptop, &["sync"])
+        .failure()
+        .stderr(predicate::str::contains("Storage directory not found"));
+
+    fs::create_dir_all(&shared)?;
+    unjust(
+        &desktop,
+        &["remote", "add", "drive", "dir", shared.to_str().unwrap()],
+    )
+    .success();
+    unjust(&laptop, &["sync"]).success();
+    assert_eq!(
+        fs::read_to_string(shared.join("user/shared/Justfile"))?,
+        "build:\n"
+    );
+    assert!(shared.join(".unjust-index").exists());
+
+    unjust(&desktop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("pull +user/shared").unwrap());
+    assert_eq!(
+        fs::read_to_string(desktop.join("user/shared/Justfile"))?,
+        "build:\n"
+    );
+
+    // Syncing a clean entry leaves it as it was
+    let manifest = desktop.join("user/shared/.unjust.toml");
+    let before = fs::read_to_string(&manifest)?;
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    unjust(&desktop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("up-to-date +user/shared").unwrap());
+    assert_eq!(fs::read_to_string(&manifest)?, before);
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_tags_sync_between_machines() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let shared = temp_dir.path().join("shared");
+    let laptop = temp_dir.path().join("laptop");
+    let desktop = temp_dir.path().join("desktop");
+    create_test_repo(&laptop, "user/shared", "build:\n")?;
+    fs::create_dir_all(&shared)?;
+    fs::create_dir_all(&desktop)?;
+    for machine in [&laptop, &desktop] {
+        unjust(
+            machine,
+            &["remote", "add", "drive", "dir", shared.to_str().unwrap()],
+        )
+        .success();
+    }
+    unjust(&laptop, &["sync"]).success();
+    unjust(&desktop, &["sync"]).success();
+
+    // A tag is a change to sync like any other
+    unjust(&laptop, &["tag", "add", "rust", "user/shared"]).success();
+    unjust(&laptop, &["status", "--porcelain"])
+        .success()
+        .stdout(predicate::str::contains("modified\tuser/shared"));
+    unjust(&laptop, &["sync"]).success();
+    unjust(&desktop, &["sync"]).success();
+    unjust(&desktop, &["list", "--tag", "rust"])
+        .success()
+        .stdout(predicate::str::contains("user/shared"));
+
+    // Tags added on both sides are merged
+    unjust(&laptop, &["tag", "add", "laptop", "user/shared"]).success();
+    unjust(&desktop, &["tag", "add", "desktop", "user/shared"]).success();
+    unjust(&laptop, &["sync"]).success();
+    unjust(&desktop, &["sync"]).success();
+    unjust(&desktop, &["tag"])
+        .success()
+        .stdout(predicate::str::contains("laptop: user/shared"))
+        .stdout(predicate::str::contains("desktop: user/shared"))
+        .stdout(predicate::str::contains("rust: user/shared"));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_sync_with_remotes_in_priority_order() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let personal = temp_dir.path().join("personal");
+    let team = temp_dir.path().join("team");
+    let laptop = temp_dir.path().join("laptop");
+    create_test_repo(&personal, "team/api", "# Personal\n")?;
+    create_test_repo(&team, "team/api", "# Team\n")?;
+    create_test_repo(&team, "team/tools", "# Tools\n")?;
+    create_test_repo(&laptop, "user/mine", "# Mine\n")?;
+    for (name, dir) in [("personal", &personal), ("team", &team)] {
+        unjust(
+            &laptop,
+            &["remote", "add", name, "dir", dir.to_str().unwrap()],
+        )
+        .success();
+    }
+
+    // A dry run plans each remote as the ones before it would leave things
+    unjust(&laptop, &["sync", "--dry-run"])
+        .success()
+        .stdout(predicate::str::is_match("pull +team/api").unwrap())
+        .stdout(predicate::function(|out: &str| {
+            out.matches("team/api").count() == 1
+        }));
+    assert!(!laptop.join("team/api").exists());
+
+    // The first remote provides entries both have, and gets new ones
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("pull +team/api").unwrap())
+        .stdout(predicate::str::is_match("push +user/mine").unwrap())
+        .stdout(predicate::str::is_match("pull +team/tools").unwrap());
+    assert_eq!(
+        fs::read_to_string(laptop.join("team/api/Justfile"))?,
+        "# Personal\n"
+    );
+    assert!(personal.join("user/mine/Justfile").exists());
+    assert!(!team.join("user/mine").exists());
+    unjust(&laptop, &["list"])
+        .success()
+        .stdout(predicate::str::contains("team/api [personal]"))
+        .stdout(predicate::str::contains("team/tools [team]"))
+        .stdout(predicate::str::contains("user/mine [personal]"));
+
+    // Moving the team first hands its copy over
+    unjust(&laptop, &["remote", "priority", "team", "1"])
+        .success()
+        .stdout(predicate::str::contains("1. team"));
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("pull +team/api").unwrap());
+    assert_eq!(
+        fs::read_to_string(laptop.join("team/api/Justfile"))?,
+        "# Team\n"
+    );
+    assert!(personal.join("team/api/Justfile").exists());
+    unjust(&laptop, &["status", "--porcelain", "team/api"])
+        .success()
+        .stdout("clean\tteam/api\tteam\n");
+    unjust(&laptop, &["sync", "--remote", "personal", "--dry-run"])
+        .success()
+        .stdout(predicate::str::contains("team/api").not());
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_subscribe_command() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let personal = temp_dir.path().join("personal");
+    let colleague = temp_dir.path().join("colleague");
+    let laptop = temp_dir.path().join("laptop");
+    create_test_repo(&colleague, "alice/tools", "build:\n    make\n")?;
+    create_test_repo(&laptop, "user/mine", "# Mine\n")?;
+    fs::create_dir_all(&personal)?;
+
+    unjust(&laptop, &["subscribe"])
+        .success()
+        .stdout(predicate::str::contains("No subscriptions"));
+    unjust(&laptop, &["subscribe", "alice/justfiles"]).success();
+    unjust(&laptop, &["remote"])
+        .success()
+        .stdout(predicate::str::contains(
+            "alice (git, read-only) https://github.com/alice/justfiles.git",
+        ));
+    unjust(&laptop, &["remote", "remove", "alice"]).success();
+
+    let colleague_dir = colleague.to_str().unwrap();
+    unjust(&laptop, &["subscribe", "colleague", "dir", colleague_dir])
+        .success()
+        .stdout(predicate::str::contains("Subscribed to colleague"));
+    unjust(
+        &laptop,
+        &[
+            "remote",
+            "add",
+            "personal",
+            "dir",
+            personal.to_str().unwrap(),
+        ],
+    )
+    .success();
+
+    // New entries go to the first remote that takes them
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::contains("read-only"))
+        .stdout(predicate::str::is_match("pull +alice/tools").unwrap());
+    assert!(personal.join("user/mine/Justfile").exists());
+    assert!(!colleague.join("user/mine").exists());
+
+    // Local edits stay local
+    let on_laptop = laptop.join("alice/tools/Justfile");
+    fs::write(&on_laptop, "# Tweaked\nbuild:\n    make\n")?;
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("override +alice/tools").unwrap());
+    assert_eq!(
+        fs::read_to_string(colleague.join("alice/tools/Justfile"))?,
+        "build:\n    make\n"
+    );
+    unjust(&laptop, &["status"])
+        .success()
+        .stdout(predicate::str::contains("personal override"))
+        .stdout(predicate::str::contains("Everything is in sync"));
+
+    // The colleague's later changes are merged into the override. Reading
+    // their directory never writes an index into it, so it's rebuilt from
+    // the entries each time
+    assert!(!colleague.join(".unjust-index").exists());
+    fs::write(
+        colleague.join("alice/tools/Justfile"),
+        "build:\n    make all\n",
+    )?;
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("merge +alice/tools").unwrap());
+    assert_eq!(
+        fs::read_to_string(&on_laptop)?,
+        "# Tweaked\nbuild:\n    make all\n"
+    );
+    assert_eq!(
+        fs::read_to_string(colleague.join("alice/tools/Justfile"))?,
+        "build:\n    make all\n"
+    );
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("override +alice/tools").unwrap());
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_pin_and_update() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    let work_dir = TempDir::new()?;
+    let checkout = work_dir.path();
+    create_test_repo(cache_dir, "user/repo", "build:\n    echo v1\n")?;
+
+    let pin = |args: &[&str]| {
+        Command::cargo_bin("unjust")
+            .unwrap()
+            .env("UNJUST_CACHE_DIR", cache_dir)
+            .current_dir(checkout)
+            .args(args)
+            .assert()
+    };
+
+    pin(&["update"])
+        .success()
+        .stdout(predicate::str::contains("Nothing is pinned here"));
+    pin(&["pin", "user/repo"])
+        .success()
+        .stdout(predicate::str::contains("Pinned user/repo to revision 1"));
+
+    // The stored Justfile moves on, but this checkout keeps revision 1
+    fs::write(
+        cache_dir.join("user/repo/Justfile"),
+        "build:\n    echo v2\n",
+    )?;
+    pin(&["use", "--copy", "user/repo"])
+        .success()
+        .stdout(predicate::str::contains("Using revision 1 pinned"))
+        .stderr(predicate::str::contains("has changed since it was pinned"));
+    assert_eq!(
+        fs::read_to_string(checkout.join("Justfile"))?,
+        "build:\n    echo v1\n"
+    );
+    fs::remove_file(checkout.join("Justfile"))?;
+
+    pin(&["update"]).success().stdout(predicate::str::contains(
+        "Updated user/repo from revision 1 to 2",
+    ));
+    pin(&["use", "--copy", "user/repo"])
+        .success()
+        .stderr(predicate::str::contains("has changed").not());
+    assert_eq!(
+        fs::read_to_string(checkout.join("Justfile"))?,
+        "build:\n    echo v2\n"
+    );
+
+    pin(&["pin", "--remove", "user/repo"])
+        .success()
+        .stdout(predicate::str::contains("Unpinned user/repo"));
+    pin(&["pin", "--rev", "7", "user/repo"])
+        .failure()
+        .stderr(predicate::str::contains("No revision 7 of user/repo"));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_sync_settles_queued_operations() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let registry = temp_dir.path().join("registry");
+    let away = temp_dir.path().join("unmounted");
+    let laptop = temp_dir.path().join("laptop");
+    let desktop = temp_dir.path().join("desktop");
+    fs::create_dir_all(&registry)?;
+    create_test_repo(&laptop, "user/shared", "build:\n")?;
+    create_test_repo(&laptop, "user/old", "# Old\n")?;
+    for cache_dir in [&laptop, &desktop] {
+        unjust(
+            cache_dir,
+            &["remote", "add", "team", "dir", registry.to_str().unwrap()],
+        )
+        .success();
+    }
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::contains(
+            "Queued operations now on the remote: 2",
+        ));
+    unjust(&desktop, &["sync"]).success();
+    fs::write(desktop.join("user/shared/Justfile"), "desktop:\n")?;
+    unjust(&desktop, &["sync"]).success();
+
+    // Offline, changes are queued rather than lost
+    fs::rename(&registry, &away)?;
+    unjust(&laptop, &["rename", "user/old", "user/new"]).success();
+    fs::write(laptop.join("user/shared/Justfile"), "laptop:\n")?;
+    unjust(&laptop, &["sync"])
+        .failure()
+        .stderr(predicate::str::contains("Could not sync with team"))
+        .stdout(predicate::str::contains(
+            "Operations still queued for the next sync: 2",
+        ));
+    unjust(&laptop, &["status"])
+        .success()
+        .stdout(predicate::str::contains("  rename user/old to user/new"))
+        .stdout(predicate::str::contains("  edit user/shared"));
+
+    // Back online, the rename is replayed and the edit now conflicts
+    fs::rename(&away, &registry)?;
+    unjust(&laptop, &["sync"])
+        .failure()
+        .stdout(predicate::str::contains(
+            "Replayed queued rename user/old to user/new",
+        ))
+        .stdout(predicate::str::contains(
+            "Queued operations now on the remote: 1",
+        ))
+        .stderr(predicate::str::contains(
+            "queued edit user/shared conflicts with team",
+        ));
+    assert!(registry.join("user/new/Justfile").exists());
+    assert!(!registry.join("user/old").exists());
+    unjust(&laptop, &["status"])
+        .success()
+        .stdout(predicate::str::contains("  edit user/shared"))
+        .stdout(predicate::str::contains("rename").not());
+
+    // Resolving the conflict settles the queue
+    unjust(&laptop, &["sync", "--force-push"])
+        .success()
+        .stdout(predicate::str::contains(
+            "Queued operations now on the remote: 1",
+        ));
+    unjust(&laptop, &["status"])
+        .success()
+        .stdout(predicate::str::contains("queued").not());
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_clears_lock_left_by_crashed_process() -> std::io::Result<()> {
+    let Ok(host) = fs::read_to_string("/proc/sys/kernel/hostname") else {
+        return Ok(());
+    };
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    let mut crashed = std::process::Command::new("true").spawn()?;
+    crashed.wait()?;
+    fs::write(
+        cache_dir.join(".lock"),
+        format!("{} {}\n", crashed.id(), host.trim()),
+    )?;
+
+    unjust(
+        cache_dir,
+        &["remote", "add", "origin", "dir", "/srv/justfiles"],
+    )
+    .success();
+    assert!(!cache_dir.join(".lock").exists());
+    assert!(fs::read_to_string(cache_dir.join(".remotes"))?.contains("origin"));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_sync_many_entries_in_parallel() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let registry = temp_dir.path().join("registry");
+    let laptop = temp_dir.path().join("laptop");
+    let desktop = temp_dir.path().join("desktop");
+    fs::create_dir_all(&registry)?;
+    let repos: Vec<String> = (0..40).map(|i| format!("user/repo{i:02}")).collect();
+    for repo in &repos {
+        create_test_repo(&laptop, repo, &format!("# {repo}\n"))?;
+    }
+    for cache_dir in [&laptop, &desktop] {
+        unjust(
+            cache_dir,
+            &["remote", "add", "team", "dir", registry.to_str().unwrap()],
+        )
+        .success();
+    }
+
+    // The report lists every entry in order, whichever finished first
+    let pushed: String = repos
+        .iter()
+        .map(|r| format!("push          {r}  (new)\n"))
+        .collect();
+    unjust(&laptop, &["sync", "--jobs", "4"])
+        .success()
+        .stdout(predicate::str::contains(pushed))
+        .stdout(predicate::str::contains("0 to pull, 40 to push"));
+    let pulled: String = repos
+        .iter()
+        .map(|r| format!("pull          {r}  (remote-updated)\n"))
+        .collect();
+    unjust(&desktop, &["sync", "-j", "16"])
+        .success()
+        .stdout(predicate::str::contains(pulled));
+    for repo in &repos {
+        assert_eq!(
+            fs::read_to_string(desktop.join(repo).join("Justfile"))?,
+            format!("# {repo}\n")
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_sync_removes_entries_with_nested_entries() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let registry = temp_dir.path().join("registry");
+    let laptop = temp_dir.path().join("laptop");
+    let desktop = temp_dir.path().join("desktop");
+    fs::create_dir_all(&registry)?;
+    create_test_repo(&laptop, "user/repo", "# root\n")?;
+    create_test_repo(&laptop, "user/repo/tools", "# tools\n")?;
+    create_test_repo(&laptop, "user/repo/.subpaths/pkg", "# pkg\n")?;
+    for cache_dir in [&laptop, &desktop] {
+        unjust(
+            cache_dir,
+            &["remote", "add", "team", "dir", registry.to_str().unwrap()],
+        )
+        .success();
+    }
+    unjust(&laptop, &["sync"]).success();
+    unjust(&desktop, &["sync", "-j", "8"]).success();
+
+    // Removing the root entry leaves the entries inside its directory, here
+    // and on every machine it syncs to
+    unjust(&laptop, &["remove", "user/repo"]).success();
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::contains("Replayed queued remove user/repo"));
+    unjust(&desktop, &["sync", "-j", "8"])
+        .success()
+        .stdout(predicate::str::contains("1 to delete"));
+    for cache_dir in [&laptop, &desktop] {
+        unjust(cache_dir, &["list"])
+            .success()
+            .stdout(predicate::str::contains("user/repo/tools"))
+            .stdout(predicate::str::contains("user/repo//pkg"));
+    }
+    assert!(registry.join("user/repo/tools/Justfile").exists());
+    assert!(registry.join("user/repo/.subpaths/pkg/Justfile").exists());
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::contains("0 to delete"));
+
+    Ok(())
+}
+
+/// A running `unjust serve`, stopped when dropped
+struct Server(std::process::Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[test]
+fn test_cli_sync_with_http_remote() -> std::io::Result<()> {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let temp_dir = setup_test_env();
+    let registry = temp_dir.path().join("registry");
+    let laptop = temp_dir.path().join("laptop");
+    let desktop = temp_dir.path().join("desktop");
+    create_test_repo(&laptop, "user/shared", "build:\n")?;
+    fs::create_dir_all(&desktop)?;
+    fs::create_dir_all(&registry)?;
+
+    unjust(&laptop, &["serve"])
+        .failure()
+        .stderr(predicate::str::contains("No storage directory given"));
+
+    let mut server = Server(
+        std::process::Command::new(assert_cmd::cargo::cargo_bin("unjust"))
+            .args(["serve", "--port", "0", registry.to_str().unwrap()])
+            .env("UNJUST_CACHE_DIR", &laptop)
+            .stdout(Stdio::piped())
+            .spawn()?,
+    );
+    let mut line = String::new();
+    BufReader::new(server.0.stdout.take().unwrap()).read_line(&mut line)?;
+    let url = line.trim().split(" at ").last().unwrap().to_string();
+    assert!(url.starts_with("http://127.0.0.1:"), "{line}");
+
+    for cache_dir in [&laptop, &desktop] {
+        unjust(cache_dir, &["remote", "add", "team", "http", &url]).success();
+    }
+    unjust(&laptop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("push +user/shared").unwrap());
+    assert_eq!(
+        fs::read_to_string(registry.join("user/shared/Justfile"))?,
+        "build:\n"
+    );
+
+    unjust(&desktop, &["sync"])
+        .success()
+        .stdout(predicate::str::is_match("pull +user/shared").unwrap());
+    fs::write(desktop.join("user/shared/Justfile"), "build:\ntest:\n")?;
+    unjust(&desktop, &["sync"]).success();
+
+    unjust(&laptop, &["sync"]).success();
+    assert_eq!(
+        fs::read_to_string(laptop.join("user/shared/Justfile"))?,
+        "build:\ntest:\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_remove_command() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+
+    create_test_repo(cache_dir, "user/repo", "# Test justfile")?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            // Remove the repo
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("remove")
+                .arg("user/repo")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Removed Justfile for: user/repo"));
+            assert!(!cache_dir.join("user/repo/Justfile").exists());
+
+            // The removal is queued for the next sync
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["sync", "--dry-run"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                    "Removed since the last sync: user/repo",
+                ));
+
+            // Removing again fails
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("remove")
+                .arg("user/repo")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("not found"));
+
+            // Undo brings it back
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("remove")
+                .arg("--undo")
+                .arg("user/repo")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Restored Justfile for: user/repo"));
+            assert!(cache_dir.join("user/repo/Justfile").exists());
+
+            // Emptying the trash makes a removal final
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("remove").arg("user/repo").assert().success();
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("remove")
+                .arg("--empty-trash")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Emptied the trash"));
+            assert!(!cache_dir.join(".trash").exists());
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("remove")
+                .arg("--undo")
+                .arg("user/repo")
+                .assert()
+                .failure();
+        },
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_rename_command() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+
+    create_test_repo(cache_dir, "user/repo", "# Test justfile")?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            // Missing target
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("rename")
+                .arg("user/repo")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("must be specified"));
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("rename")
+                .arg("user/repo")
+                .arg("org/repo")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Renamed user/repo to org/repo"));
+            assert!(cache_dir.join("org/repo/Justfile").exists());
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["sync", "--dry-run"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                    "Renamed since the last sync: user/repo to org/repo",
+                ));
+        },
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_adopt_command() -> std::io::Result<()> {
+    use std::process::Command as ProcessCommand;
+
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    let work_dir = TempDir::new()?;
+    let repo_path = work_dir.path();
+
+    if ProcessCommand::new("git")
+        .args(["--version"])
+        .status()
+        .is_err()
+    {
+        println!("Skipping git-dependent test because git command failed");
+        return Ok(());
+    }
+
+    ProcessCommand::new("git")
+        .args(["init", "-q"])
+        .current_dir(repo_path)
+        .status()?;
+    ProcessCommand::new("git")
+        .args(["remote", "add", "origin", "git@github.com:user/project.git"])
+        .current_dir(repo_path)
+        .status()?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            // Nothing to adopt yet
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path)
+                .arg("adopt")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("No Justfile found"));
+
+            fs::write(repo_path.join("justfile"), "# Local justfile")?;
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path)
+                .arg("adopt")
+                .arg("--exclude")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Justfile for: user/project"));
+            assert_eq!(
+                fs::read_to_string(cache_dir.join("user/project/justfile"))?,
+                "# Local justfile"
+            );
+            let exclude = fs::read_to_string(repo_path.join(".git/info/exclude"))?;
+            assert!(exclude.lines().any(|line| line == "/justfile"));
+            let manifest = fs::read_to_string(cache_dir.join("user/project/.unjust.toml"))?;
+            assert!(manifest.contains("source_remote = \"git@github.com:user/project.git\""));
+            assert!(manifest.contains("content_hash = \"fnv1a64:"));
+
+            // Adopting again needs --force
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path)
+                .arg("adopt")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("already exists"));
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path)
+                .arg("adopt")
+                .arg("--force")
+                .arg("--remove")
+                .arg("other/name")
+                .assert()
+                .success();
+            assert!(cache_dir.join("other/name/justfile").exists());
+            assert!(!repo_path.join("justfile").exists());
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_cli_use_link_and_unlink() -> std::io::Result<()> {
+    use std::process::Command as ProcessCommand;
+
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    let work_dir = TempDir::new()?;
+    let repo_path = work_dir.path();
+
+    if ProcessCommand::new("git")
+        .args(["--version"])
+        .status()
+        .is_err()
+    {
+        println!("Skipping git-dependent test because git command failed");
+        return Ok(());
+    }
+
+    ProcessCommand::new("git")
+        .args(["init", "-q"])
+        .current_dir(repo_path)
+        .status()?;
+    fs::create_dir_all(repo_path.join("src"))?;
+
+    create_test_repo(cache_dir, "test/repo", "# Test justfile")?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            // Linking from a subdirectory places the Justfile at the repo root
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path.join("src"))
+                .arg("use")
+                .arg("--copy")
+                .arg("test/repo")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Linked Justfile for test/repo"));
+            assert_eq!(
+                fs::read_to_string(repo_path.join("Justfile"))?,
+                "# Test justfile"
+            );
+            let exclude = fs::read_to_string(repo_path.join(".git/info/exclude"))?;
+            assert!(exclude.lines().any(|line| line == "/Justfile"));
+
+            // An edited copy is kept unless forced
+            fs::write(repo_path.join("Justfile"), "# edited")?;
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path)
+                .arg("use")
+                .arg("--copy")
+                .arg("test/repo")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("local changes"))
+                .stderr(predicate::str::contains("--force"));
+            assert_eq!(fs::read_to_string(repo_path.join("Justfile"))?, "# edited");
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path)
+                .arg("unlink")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("local changes"));
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path)
+                .arg("unlink")
+                .arg("--force")
+                .assert()
+                .success();
+            assert!(!repo_path.join("Justfile").exists());
+            let exclude = fs::read_to_string(repo_path.join(".git/info/exclude"))?;
+            assert!(!exclude.lines().any(|line| line == "/Justfile"));
+
+            // Nothing left to unlink
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path)
+                .arg("unlink")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("No linked Justfile"));
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_cli_bind_and_unbind() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    let work_dir = TempDir::new()?;
+    let project = work_dir.path().join("project");
+    fs::create_dir_all(project.join("nested"))?;
+
+    create_test_repo(cache_dir, "test/repo", "# Test justfile")?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(work_dir.path())
+                .arg("bind")
+                .arg("test/repo")
+                .arg("project")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Bound"));
+
+            // Listing shows the binding
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("bind")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("project -> test/repo"));
+
+            // Bindings apply in subdirectories without naming the repo
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(project.join("nested"))
+                .arg("use")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Using Justfile from"));
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(&project)
+                .arg("unbind")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Unbound"));
+
+            // `..` is resolved before the binding is stored
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(project.join("nested"))
+                .arg("bind")
+                .arg("test/repo")
+                .arg("../nested/..")
+                .assert()
+                .success();
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("bind")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("..").not());
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(&project).arg("unbind").assert().success();
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(&project)
+                .arg("unbind")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("No binding for"));
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(project.join("nested"))
+                .arg("use")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("could not be detected"));
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_cli_monorepo_subpaths() -> std::io::Result<()> {
+    use std::process::Command as ProcessCommand;
+
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    let work_dir = TempDir::new()?;
+    let repo_path = work_dir.path();
+
+    if ProcessCommand::new("git")
+        .args(["--version"])
+        .status()
+        .is_err()
+    {
+        println!("Skipping git-dependent test because git command failed");
+        return Ok(());
+    }
+
+    ProcessCommand::new("git")
+        .args(["init", "-q"])
+        .current_dir(repo_path)
+        .status()?;
+    ProcessCommand::new("git")
+        .args(["remote", "add", "origin", "https://github.com/user/mono"])
+        .current_dir(repo_path)
+        .status()?;
+    fs::create_dir_all(repo_path.join("packages/api/src"))?;
+    fs::create_dir_all(repo_path.join("packages/web"))?;
+
+    create_test_repo(cache_dir, "user/mono", "# Root justfile")?;
+    create_test_repo(cache_dir, "user/mono/.subpaths/packages/api", "# API")?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("list")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("user/mono//packages/api"));
+
+            // The deepest subpath Justfile wins
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path.join("packages/api/src"))
+                .arg("use")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("packages/api/Justfile"));
+
+            // Elsewhere the repo root Justfile is used
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path.join("packages/web"))
+                .arg("use")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("packages/api").not())
+                .stdout(predicate::str::contains("user/mono/Justfile"));
+
+            // Subpath ids can be named explicitly too
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("use")
+                .arg("user/mono//packages/api")
+                .assert()
+                .success();
+
+            // A subpath Justfile is linked into its package directory
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path.join("packages/api/src"))
+                .arg("use")
+                .arg("--copy")
+                .assert()
+                .success();
+            assert_eq!(
+                fs::read_to_string(repo_path.join("packages/api/Justfile"))?,
+                "# API"
+            );
+            assert!(!repo_path.join("Justfile").exists());
+
+            // And unlinked from there
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(repo_path.join("packages/api/src"))
+                .arg("unlink")
+                .assert()
+                .success();
+            assert!(!repo_path.join("packages/api/Justfile").exists());
+
+            Ok(())
+        },
+    )
+}
+
+// This test simulates a complete workflow using the CLI
+#[test]
+fn test_cli_layered_justfiles() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+
+    create_test_repo(
+        cache_dir,
+        "company/base",
+        "fmt:\n    cargo fmt\n\nlint:\n    echo base\n",
+    )?;
+    create_test_repo(
+        cache_dir,
+        "user/project",
+        "# unjust-base: company/base\nlint:\n    echo project\n",
+    )?;
+    create_test_repo(cache_dir, "user/broken", "# unjust-base: missing/base\n")?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("list")
+                .arg("--recipes")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("fmt (company/base)"))
+                .stdout(predicate::str::contains("lint (user/project)"))
+                .stderr(predicate::str::contains("missing/base"));
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("use")
+                .arg("user/project")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(".unjust-composed.just"));
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("use")
+                .arg("user/broken")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("Base Justfile not found"));
+        },
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_multi_file_justfiles() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    let work_dir = TempDir::new()?;
+    let project = work_dir.path().join("project");
+    fs::create_dir_all(project.join("ci"))?;
+    fs::write(
+        project.join("Justfile"),
+        "import 'common.just'\nmod ci\n\nbuild: shared\n",
+    )?;
+    fs::write(project.join("common.just"), "shared:\n    echo shared\n")?;
+    fs::write(project.join("ci/mod.just"), "check:\n    echo ci\n")?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            // Imported files are stored along with the Justfile
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(&project)
+                .arg("adopt")
+                .arg("user/project")
+                .assert()
+                .success();
+            assert!(cache_dir.join("user/project/common.just").exists());
+            assert!(cache_dir.join("user/project/ci/mod.just").exists());
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["sync", "--dry-run", "user/project"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("common.just"))
+                .stdout(predicate::str::contains("mod.just"));
+
+            // Linking places a Justfile that imports the stored one
+            let target = work_dir.path().join("checkout");
+            fs::create_dir_all(&target)?;
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(&target)
+                .arg("use")
+                .arg("--link")
+                .arg("user/project")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("imports the stored Justfile"));
+            assert!(
+                fs::read_to_string(target.join("Justfile"))?
+                    .contains(cache_dir.join("user/project/Justfile").to_str().unwrap())
+            );
+
+            // Imports must stay inside the Justfile's directory
+            fs::write(work_dir.path().join("outside.just"), "")?;
+            fs::write(project.join("Justfile"), "import '../outside.just'\n")?;
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(&project)
+                .arg("adopt")
+                .arg("--force")
+                .arg("user/project")
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("outside"));
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_cli_tag_command() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    create_test_repo(cache_dir, "user/rusty", "# Rust justfile")?;
+    create_test_repo(cache_dir, "user/snake", "# Python justfile")?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["tag", "add", "rust", "user/rusty"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Tagged user/rusty with rust"));
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["tag", "add", "template", "user/snake"])
+                .assert()
+                .success();
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["list", "--tag", "rust"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("user/rusty"))
+                .stdout(predicate::str::contains("user/snake").not());
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("tag")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("rust: user/rusty"))
+                .stdout(predicate::str::contains("template: user/snake"));
+
+            // Tagged Justfiles can be picked as templates
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["init", "-t", "rust"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Would use template: user/rusty"));
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["init", "-t", "template"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Would use template: user/snake"));
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("init")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Would create a basic Justfile"));
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["tag", "remove", "rust", "user/rusty"])
+                .assert()
+                .success();
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["list", "--tag", "rust"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("No Justfiles tagged rust"));
+        },
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_use_with_unwritable_manifest() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+
+    create_test_repo(cache_dir, "user/repo", "# Test justfile")?;
+    fs::write(
+        cache_dir.join("user/repo/.unjust.toml"),
+        "last_used = soon\n",
+    )?;
+
+    // Failing to record the use doesn't stop the Justfile being used
+    unjust(cache_dir, &["use", "user/repo"])
+        .success()
+        .stdout(predicate::str::contains("Using Justfile from"))
+        .stderr(predicate::str::contains("Could not record last use"));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_history_command() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    let work_dir = TempDir::new()?;
+
+    create_test_repo(
+        cache_dir,
+        "user/tools",
+        "ok:\n    @echo fine\n\nbroken:\n    @exit 3\n",
+    )?;
+    create_test_repo(cache_dir, "user/older", "# Older justfile")?;
+    fs::write(cache_dir.join("user/older/.unjust.toml"), "last_used = 1\n")?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("history")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("No usage recorded"));
+
+            // Recipes are run with just and their exit code passed through
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(work_dir.path())
+                .args(["use", "-r", "ok", "user/tools"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("fine"));
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.current_dir(work_dir.path())
+                .args(["use", "--recipe", "broken", "user/tools"])
+                .assert()
+                .code(3);
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("history")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("user/tools ok"))
+                .stdout(predicate::str::contains("user/tools broken"))
+                .stdout(predicate::str::contains("exit 3"));
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["history", "--failed"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("broken"))
+                .stdout(predicate::str::contains("user/tools ok").not());
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["history", "--stats"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                    "user/tools broken: 1 runs, 1 failed",
+                ));
+
+            // The most recently used Justfile comes first
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            let output = cmd.args(["list", "--sort", "recent"]).output().unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let tools = stdout.find("user/tools").unwrap();
+            let older = stdout.find("user/older").unwrap();
+            assert!(tools < older);
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_cli_log_diff_and_restore() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    create_test_repo(cache_dir, "user/shared", "build:\n    cargo build\n")?;
+    let stored = cache_dir.join("user/shared/Justfile");
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            unjust_core::snapshot(
+                &unjust_core::Justfile::new("user/shared".to_string(), stored.clone()),
+                "Created",
+            )?;
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["log", "user/shared"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Created"))
+                .stdout(predicate::str::contains("Working copy").not());
+
+            // A bad edit made directly in the store
+            fs::write(&stored, "build:\n    cargo biuld\n")?;
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["diff", "user/shared"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("-    cargo build"))
+                .stdout(predicate::str::contains("+    cargo biuld"));
+
+            // Looking at the history doesn't record the edit
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["log", "user/shared"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Working copy"));
+            assert!(!cache_dir.join("user/shared/.revisions/2").exists());
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["restore", "user/shared", "1"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains(
+                    "Restored user/shared to revision 1",
+                ));
+            assert_eq!(fs::read_to_string(&stored)?, "build:\n    cargo build\n");
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["restore", "user/shared", "42"])
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("No revision 42"));
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_cli_status_command() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    create_test_repo(cache_dir, "user/clean", "build:\n")?;
+    create_test_repo(cache_dir, "user/edited", "build:\n")?;
+    create_test_repo(cache_dir, "user/fresh", "build:\n")?;
+    let hash = unjust_core::content_hash(&cache_dir.join("user/clean/Justfile"))?;
+
+    // As left by a sync, after which user/edited changed locally, user/gone
+    // was deleted locally and user/upstream appeared on the remote
+    fs::write(cache_dir.join(".remotes"), "origin\tdir\t/srv/justfiles\n")?;
+    fs::create_dir_all(cache_dir.join(".sync-states"))?;
+    fs::write(
+        cache_dir.join(".sync-states/origin"),
+        format!(
+            "user/clean\t{hash}\t{hash}\nuser/edited\told\told\n\
+             user/gone\told\told\nuser/upstream\t-\tnew\n"
+        ),
+    )?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["status", "--porcelain"])
+                .assert()
+                .success()
+                .stdout(
+                    "clean\tuser/clean\torigin\n\
+                     modified\tuser/edited\torigin\n\
+                     new\tuser/fresh\torigin\n\
+                     deleted\tuser/gone\torigin\n\
+                     remote-updated\tuser/upstream\torigin\n",
+                );
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.arg("status")
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("locally modified"))
+                .stdout(predicate::str::contains("4 of 5 entries differ"));
+
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["status", "--porcelain", "user/clean"])
+                .assert()
+                .success()
+                .stdout("clean\tuser/clean\torigin\n");
+
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn test_cli_sync_dry_run() -> std::io::Result<()> {
+    let temp_dir = setup_test_env();
+    let cache_dir = temp_dir.path();
+    create_test_repo(cache_dir, "user/clean", "build:\n")?;
+    create_test_repo(cache_dir, "user/edited", "build:\n")?;
+    create_test_repo(cache_dir, "user/both", "build:\n")?;
+    let hash = unjust_core::content_hash(&cache_dir.join("user/clean/Justfile"))?;
+    let state = format!(
+        "user/both\told\tother\nuser/clean\t{hash}\t{hash}\nuser/edited\told\told\n\
+         user/gone\told\told\n"
+    );
+    fs::create_dir_all(cache_dir.join(".sync-states"))?;
+    fs::write(cache_dir.join(".sync-states/origin"), &state)?;
+
+    // The remote's index is all a dry run reads from it
+    let remote_dir = TempDir::new()?;
+    fs::write(
+        remote_dir.path().join(".unjust-index"),
+        format!(
+            "# revision 4\nuser/both\tother\nuser/clean\t{hash}\nuser/edited\told\n\
+             user/gone\told\nuser/upstream\tnew\n"
+        ),
+    )?;
+    fs::write(
+        cache_dir.join(".remotes"),
+        format!("origin\tdir\t{}\n", remote_dir.path().display()),
+    )?;
+
+    with_var(
+        "UNJUST_CACHE_DIR",
+        Some(cache_dir.to_str().unwrap()),
+        || {
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["sync", "--dry-run"])
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("Dry run"))
+                .stdout(predicate::str::is_match("conflict +user/both").unwrap())
+                .stdout(predicate::str::is_match("up-to-date +user/clean").unwrap())
+                .stdout(predicate::str::is_match("push +user/edited").unwrap())
+                .stdout(predicate::str::is_match("delete-remote +user/gone").unwrap())
+                .stdout(predicate::str::is_match("pull +user/upstream").unwrap())
+                .stdout(predicate::str::contains(
+                    "1 to pull, 1 to push, 0 to merge, 1 to delete, 1 in conflict",
+                ));
+
+            // Force pushing resolves the conflict in favour of the store
+            let mut cmd = Command::cargo_bin("unjust").unwrap();
+            cmd.args(["sync", "--dry-run", "--force-push", "user/both"])
+                .assert()
+                .success()
+                .stdout(predicate::str::is_match("push +user/both").unwrap());
+
+            // Nothing was touched
+            assert_eq!(
+                fs::read_to_string(cache_dir.join(".sync-states/origin"))?,
+                state
+            );
+            assert_eq!(
+                fs::read_to_string(cache_dir.join("user/edited/Justfile"))?,
+                "build:\n"
+            );
+
+            Ok(())
+        },
+    )
+}
+
 #[test]
 fn test_end_to_end_workflow() -> std::io::Result<()> {
     use std::process::Command as ProcessCommand;
@@ -0,0 +1,66 @@
+//! Atomic file writes for unjust
+//!
+//! Files are written to a temporary file beside their target and renamed
+//! into place, so a crash or a concurrent reader never sees one half
+//! written. The temporary name is hidden, so it's never mistaken for part
+//! of an entry.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counter keeping temporary names apart between threads
+static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+/// Get a temporary path beside `path` that no other writer will pick
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let count = NEXT_TEMP.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{name}.{}-{count}.tmp", std::process::id()))
+}
+
+/// Replace the contents of a file in one step
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let temp = temp_path(path);
+    let written = File::create(&temp).and_then(|mut file| {
+        file.write_all(contents.as_ref())?;
+        file.sync_all()
+    });
+    match written.and_then(|()| fs::rename(&temp, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+/// Copy a file over another in one step
+pub fn copy_atomic(from: &Path, to: &Path) -> io::Result<()> {
+    write_atomic(to, fs::read(from)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_atomic_replaces_without_leftovers() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("Justfile");
+
+        write_atomic(&path, "build:\n")?;
+        write_atomic(&path, "test:\n")?;
+        copy_atomic(&path, &temp_dir.path().join("copy"))?;
+        assert_eq!(fs::read_to_string(&path)?, "test:\n");
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 2);
+
+        // A failed write leaves nothing behind
+        assert!(write_atomic(&temp_dir.path().join("missing/file"), "").is_err());
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 2);
+
+        Ok(())
+    }
+}
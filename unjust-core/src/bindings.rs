@@ -0,0 +1,221 @@
+//! Directory bindings for unjust
+//!
+//! Some checkouts don't map cleanly to a remote (monorepo subdirectories,
+//! scratch directories), so a directory or a path glob can be bound to a
+//! repository id. Bindings are consulted before remote-based detection.
+
+use crate::atomic::write_atomic;
+use crate::lock::StoreLock;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Name of the bindings file inside the cache directory
+const BINDINGS_FILE: &str = ".bindings";
+
+/// A directory (or path glob) bound to a repository id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    /// Absolute directory path, or a glob over absolute paths
+    pub pattern: String,
+
+    /// Repository id the matching directories use
+    pub repo: String,
+}
+
+impl Binding {
+    /// Whether the pattern contains glob syntax rather than a plain path
+    pub fn is_glob(&self) -> bool {
+        self.pattern.contains(['*', '?'])
+    }
+
+    /// Check whether this binding applies to exactly `dir`
+    fn matches(&self, dir: &str) -> bool {
+        if self.is_glob() {
+            glob_match(&self.pattern, dir)
+        } else {
+            self.pattern.trim_end_matches('/') == dir.trim_end_matches('/')
+        }
+    }
+}
+
+/// Match a path against a glob pattern
+///
+/// `*` and `?` match within a single path segment, `**` matches across
+/// segments.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    fn matches(p: &[u8], s: &[u8]) -> bool {
+        match p {
+            [] => s.is_empty(),
+            [b'*', b'*', rest @ ..] => {
+                let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+                (0..=s.len()).any(|i| matches(rest, &s[i..]))
+            }
+            [b'*', rest @ ..] => {
+                let segment = s.iter().position(|&c| c == b'/').unwrap_or(s.len());
+                (0..=segment).any(|i| matches(rest, &s[i..]))
+            }
+            [b'?', rest @ ..] => matches!(s, [c, ..] if *c != b'/') && matches(rest, &s[1..]),
+            [c, rest @ ..] => s.first() == Some(c) && matches(rest, &s[1..]),
+        }
+    }
+
+    matches(pattern.as_bytes(), path.as_bytes())
+}
+
+/// Read all bindings from the store
+pub fn read_bindings(cache_dir: &Path) -> io::Result<Vec<Binding>> {
+    let content = match fs::read_to_string(cache_dir.join(BINDINGS_FILE)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let (pattern, repo) = line.split_once('\t')?;
+            Some(Binding {
+                pattern: pattern.to_string(),
+                repo: repo.to_string(),
+            })
+        })
+        .collect())
+}
+
+fn write_bindings(cache_dir: &Path, bindings: &[Binding]) -> io::Result<()> {
+    let content: String = bindings
+        .iter()
+        .map(|b| format!("{}\t{}\n", b.pattern, b.repo))
+        .collect();
+    write_atomic(&cache_dir.join(BINDINGS_FILE), content)
+}
+
+/// Bind a directory or path glob to a repository id
+///
+/// An existing binding for the same pattern is replaced.
+pub fn add_binding(cache_dir: &Path, pattern: &str, repo: &str) -> io::Result<()> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    if !Path::new(pattern).is_absolute() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Binding must be an absolute path or glob: {pattern}"),
+        ));
+    }
+
+    let mut bindings = read_bindings(cache_dir)?;
+    bindings.retain(|b| b.pattern != pattern);
+    bindings.push(Binding {
+        pattern: pattern.to_string(),
+        repo: repo.to_string(),
+    });
+    write_bindings(cache_dir, &bindings)
+}
+
+/// Remove the binding for a directory or path glob
+///
+/// Returns the removed binding, if there was one.
+pub fn remove_binding(cache_dir: &Path, pattern: &str) -> io::Result<Option<Binding>> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let mut bindings = read_bindings(cache_dir)?;
+    let Some(index) = bindings.iter().position(|b| b.pattern == pattern) else {
+        return Ok(None);
+    };
+
+    let removed = bindings.remove(index);
+    write_bindings(cache_dir, &bindings)?;
+    Ok(Some(removed))
+}
+
+/// Find the binding that applies to a directory
+///
+/// Bindings apply to subdirectories too, and the deepest bound directory
+/// wins. At the same depth an exact path beats a glob, and between globs
+/// the most recently added one wins.
+pub fn resolve_binding(cache_dir: &Path, dir: &Path) -> io::Result<Option<Binding>> {
+    let bindings = read_bindings(cache_dir)?;
+    if bindings.is_empty() {
+        return Ok(None);
+    }
+
+    for ancestor in dir.ancestors() {
+        let path = ancestor.to_string_lossy();
+        let exact = bindings.iter().find(|b| !b.is_glob() && b.matches(&path));
+        let glob = || {
+            bindings
+                .iter()
+                .rev()
+                .find(|b| b.is_glob() && b.matches(&path))
+        };
+        if let Some(binding) = exact.or_else(glob) {
+            return Ok(Some(binding.clone()));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("/work/*", "/work/api"));
+        assert!(!glob_match("/work/*", "/work/api/src"));
+        assert!(glob_match("/work/**/scratch", "/work/a/b/scratch"));
+        assert!(glob_match("/work/**/scratch", "/work/scratch"));
+        assert!(glob_match("/tmp/scratch-?", "/tmp/scratch-1"));
+        assert!(!glob_match("/tmp/scratch-?", "/tmp/scratch-10"));
+    }
+
+    #[test]
+    fn test_add_replace_and_remove() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+
+        assert!(add_binding(cache_dir, "relative/dir", "user/repo").is_err());
+
+        add_binding(cache_dir, "/work/api", "user/repo")?;
+        add_binding(cache_dir, "/work/api", "org/api")?;
+        assert_eq!(
+            read_bindings(cache_dir)?,
+            vec![Binding {
+                pattern: "/work/api".to_string(),
+                repo: "org/api".to_string()
+            }]
+        );
+
+        assert!(remove_binding(cache_dir, "/work/api")?.is_some());
+        assert!(remove_binding(cache_dir, "/work/api")?.is_none());
+        assert!(read_bindings(cache_dir)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_binding() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+
+        add_binding(cache_dir, "/work/mono", "org/mono")?;
+        add_binding(cache_dir, "/work/mono/packages/*", "org/package")?;
+        add_binding(cache_dir, "/work/mono/packages/api", "org/api")?;
+
+        let resolve =
+            |dir: &str| resolve_binding(cache_dir, Path::new(dir)).map(|b| b.map(|b| b.repo));
+        assert_eq!(
+            resolve("/work/mono/packages/api/src")?.as_deref(),
+            Some("org/api")
+        );
+        assert_eq!(
+            resolve("/work/mono/packages/web")?.as_deref(),
+            Some("org/package")
+        );
+        assert_eq!(resolve("/work/mono/docs")?.as_deref(), Some("org/mono"));
+        assert_eq!(resolve("/elsewhere")?, None);
+
+        Ok(())
+    }
+}
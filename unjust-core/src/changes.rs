@@ -0,0 +1,362 @@
+//! Operation log for unjust
+//!
+//! Adds, edits, removals and renames made in the store are appended to a
+//! log, so they can be made offline. Sync replays the removals and renames
+//! against each remote in log order, then compares the store's contents
+//! with it, which takes care of adds and edits. An operation leaves the log
+//! once every remote that matters has caught up with it. Operations that
+//! conflict with changes on a remote stay queued until the conflict is
+//! resolved.
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::atomic::write_atomic;
+use crate::justfile::{Justfile, justfile_in_dir};
+use crate::lock::StoreLock;
+use crate::manifest::content_hash;
+use crate::remotes::read_remotes;
+use crate::status::{entry_origins, read_sync_state};
+use crate::store::entry_dir;
+
+/// Name of the operation log inside the cache directory
+const CHANGES_FILE: &str = ".pending-changes";
+
+/// A change made to the store that has not yet been synced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// An entry was added
+    Added {
+        /// Repository id of the new entry
+        repo: String,
+    },
+
+    /// An entry's content was changed
+    Edited {
+        /// Repository id of the edited entry
+        repo: String,
+    },
+
+    /// An entry was removed
+    Removed {
+        /// Repository id of the removed entry
+        repo: String,
+    },
+
+    /// An entry was renamed (or moved between repository ids)
+    Renamed {
+        /// Previous repository id
+        from: String,
+        /// New repository id
+        to: String,
+    },
+}
+
+impl Change {
+    /// Repository ids of the entries the change touches
+    pub fn repos(&self) -> Vec<&str> {
+        match self {
+            Change::Added { repo } | Change::Edited { repo } | Change::Removed { repo } => {
+                vec![repo]
+            }
+            Change::Renamed { from, to } => vec![from, to],
+        }
+    }
+
+    /// Serialise the change as a single tab-separated line
+    fn to_line(&self) -> String {
+        match self {
+            Change::Added { repo } => format!("add\t{repo}"),
+            Change::Edited { repo } => format!("edit\t{repo}"),
+            Change::Removed { repo } => format!("remove\t{repo}"),
+            Change::Renamed { from, to } => format!("rename\t{from}\t{to}"),
+        }
+    }
+
+    /// Parse a change from a line written by `to_line`
+    fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.split('\t');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("add"), Some(repo), None) => Some(Change::Added {
+                repo: repo.to_string(),
+            }),
+            (Some("edit"), Some(repo), None) => Some(Change::Edited {
+                repo: repo.to_string(),
+            }),
+            (Some("remove"), Some(repo), None) => Some(Change::Removed {
+                repo: repo.to_string(),
+            }),
+            (Some("rename"), Some(from), Some(to)) => Some(Change::Renamed {
+                from: from.to_string(),
+                to: to.to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { repo } => write!(f, "add {repo}"),
+            Change::Edited { repo } => write!(f, "edit {repo}"),
+            Change::Removed { repo } => write!(f, "remove {repo}"),
+            Change::Renamed { from, to } => write!(f, "rename {from} to {to}"),
+        }
+    }
+}
+
+fn changes_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join(CHANGES_FILE)
+}
+
+/// Append a change to the pending changes file
+pub fn record_change(cache_dir: &Path, change: &Change) -> io::Result<()> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(changes_path(cache_dir))?;
+    // One write per line, so lines appended at once don't interleave
+    file.write_all((change.to_line() + "\n").as_bytes())
+}
+
+/// Read all pending changes, oldest first
+///
+/// Lines that can't be parsed are skipped.
+pub fn pending_changes(cache_dir: &Path) -> io::Result<Vec<Change>> {
+    let path = changes_path(cache_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(path)?;
+    Ok(content.lines().filter_map(Change::from_line).collect())
+}
+
+fn write_changes(cache_dir: &Path, changes: &[Change]) -> io::Result<()> {
+    let content: String = changes.iter().map(|c| c.to_line() + "\n").collect();
+    write_atomic(&changes_path(cache_dir), content)
+}
+
+/// Drop the most recent pending occurrence of `change`, if any
+pub fn discard_change(cache_dir: &Path, change: &Change) -> io::Result<()> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let mut changes = pending_changes(cache_dir)?;
+    let Some(index) = changes.iter().rposition(|c| c == change) else {
+        return Ok(());
+    };
+    changes.remove(index);
+    write_changes(cache_dir, &changes)
+}
+
+/// Log the adds and edits in the store that aren't logged yet, such as
+/// Justfiles edited by hand, returning the newly queued changes
+///
+/// An entry counts as changed when its content differs from what was last
+/// synced with the remote providing it. Entries with a change already
+/// queued, and overrides of read-only remotes, are left alone.
+pub fn queue_local_changes(cache_dir: &Path, justfiles: &[Justfile]) -> io::Result<Vec<Change>> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let queued = pending_changes(cache_dir)?;
+    let remotes = read_remotes(cache_dir)?;
+
+    let mut changes = Vec::new();
+    for justfile in justfiles {
+        let repo = &justfile.repo_name;
+        if queued.iter().any(|c| c.repos().contains(&repo.as_str())) {
+            continue;
+        }
+        let change = match &justfile.remote {
+            None => Change::Added { repo: repo.clone() },
+            Some(remote) if remotes.iter().any(|r| &r.name == remote && r.read_only) => continue,
+            Some(remote) => {
+                let base = read_sync_state(cache_dir, remote)?
+                    .remove(repo)
+                    .and_then(|r| r.base);
+                if base == Some(content_hash(&justfile.path)?) {
+                    continue;
+                }
+                Change::Edited { repo: repo.clone() }
+            }
+        };
+        record_change(cache_dir, &change)?;
+        changes.push(change);
+    }
+    Ok(changes)
+}
+
+/// Whether the remotes have caught up with a queued change
+///
+/// That's when every entry it touches either exists locally and was last
+/// synced with this content, or exists neither locally nor on a remote. A
+/// change to an entry from a read-only remote is kept as an override, so
+/// there's nothing to send.
+pub fn change_settled(cache_dir: &Path, change: &Change) -> io::Result<bool> {
+    let origins = entry_origins(cache_dir)?;
+    let remotes = read_remotes(cache_dir)?;
+
+    for repo in change.repos() {
+        let local = match justfile_in_dir(&entry_dir(cache_dir, repo)?)? {
+            Some(path) => Some(content_hash(&path)?),
+            None => None,
+        };
+        let settled = match (local, origins.get(repo)) {
+            (None, None) => true,
+            (None, Some(_)) | (Some(_), None) => false,
+            (Some(_), Some(origin)) if remotes.iter().any(|r| &r.name == origin && r.read_only) => {
+                true
+            }
+            (Some(hash), Some(origin)) => {
+                let base = read_sync_state(cache_dir, origin)?
+                    .remove(repo)
+                    .and_then(|r| r.base);
+                base == Some(hash)
+            }
+        };
+        if !settled {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Drop the queued changes the remotes have caught up with, returning them
+pub fn settle_changes(cache_dir: &Path) -> io::Result<Vec<Change>> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let mut settled = Vec::new();
+    let mut pending = Vec::new();
+    for change in pending_changes(cache_dir)? {
+        if change_settled(cache_dir, &change)? {
+            settled.push(change);
+        } else {
+            pending.push(change);
+        }
+    }
+    if !settled.is_empty() {
+        write_changes(cache_dir, &pending)?;
+    }
+    Ok(settled)
+}
+
+/// Forget all pending changes (after they have been synced)
+pub fn clear_changes(cache_dir: &Path) -> io::Result<()> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let path = changes_path(cache_dir);
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_change_line_roundtrip() {
+        let changes = [
+            Change::Added {
+                repo: "user/repo".to_string(),
+            },
+            Change::Edited {
+                repo: "user/repo".to_string(),
+            },
+            Change::Removed {
+                repo: "user/repo".to_string(),
+            },
+            Change::Renamed {
+                from: "old/repo".to_string(),
+                to: "new/repo".to_string(),
+            },
+        ];
+
+        for change in changes {
+            assert_eq!(Change::from_line(&change.to_line()), Some(change));
+        }
+    }
+
+    #[test]
+    fn test_record_and_clear_changes() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+
+        assert!(pending_changes(cache_dir)?.is_empty());
+
+        let removed = Change::Removed {
+            repo: "user/repo".to_string(),
+        };
+        record_change(cache_dir, &removed)?;
+        assert_eq!(pending_changes(cache_dir)?, vec![removed]);
+
+        clear_changes(cache_dir)?;
+        assert!(pending_changes(cache_dir)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_queue_and_settle_changes() -> io::Result<()> {
+        use crate::remotes::{Remote, add_remote};
+        use crate::status::{SyncRecord, SyncState, write_sync_state};
+
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+        let dir = cache_dir.join("user/repo");
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("Justfile"), "build:\n")?;
+        let justfile = |remote: Option<&str>| {
+            let mut justfile = Justfile::new("user/repo".to_string(), dir.join("Justfile"));
+            justfile.remote = remote.map(str::to_string);
+            justfile
+        };
+
+        // Never synced, so it's queued as an add, once
+        let added = Change::Added {
+            repo: "user/repo".to_string(),
+        };
+        assert_eq!(
+            queue_local_changes(cache_dir, &[justfile(None)])?,
+            vec![added.clone()]
+        );
+        assert!(queue_local_changes(cache_dir, &[justfile(None)])?.is_empty());
+        assert!(!change_settled(cache_dir, &added)?);
+
+        // Once synced the add settles
+        add_remote(
+            cache_dir,
+            Remote {
+                name: "origin".to_string(),
+                kind: "dir".to_string(),
+                location: "/srv/justfiles".to_string(),
+                read_only: false,
+            },
+        )?;
+        let hash = content_hash(&dir.join("Justfile"))?;
+        let record = SyncRecord {
+            base: Some(hash.clone()),
+            remote: Some(hash),
+        };
+        let state = SyncState::from([("user/repo".to_string(), record)]);
+        write_sync_state(cache_dir, "origin", &state)?;
+        assert_eq!(settle_changes(cache_dir)?, [added]);
+        assert!(pending_changes(cache_dir)?.is_empty());
+
+        // An edit by hand is picked up, and stays queued until synced
+        fs::write(dir.join("Justfile"), "test:\n")?;
+        let queued = queue_local_changes(cache_dir, &[justfile(Some("origin"))])?;
+        assert_eq!(
+            queued,
+            [Change::Edited {
+                repo: "user/repo".to_string(),
+            }]
+        );
+        assert!(settle_changes(cache_dir)?.is_empty());
+        assert_eq!(pending_changes(cache_dir)?, queued);
+
+        Ok(())
+    }
+}
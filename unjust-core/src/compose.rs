@@ -0,0 +1,339 @@
+//! Layered Justfile composition for unjust
+//!
+//! A stored Justfile can build on another store entry by declaring it as its
+//! base with a directive comment:
+//!
+//! ```just
+//! # unjust-base: company/base
+//! ```
+//!
+//! At use time unjust generates a Justfile that imports the base (and the
+//! base's base, and so on), with recipes in upper layers overriding those
+//! below. The generated files live next to each layer's Justfile so relative
+//! paths inside it keep working.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::atomic::write_atomic;
+use crate::includes::justfile_files;
+use crate::justfile::{Justfile, justfile_in_dir};
+use crate::store::entry_dir;
+
+/// Directive comment declaring a Justfile's base
+const BASE_DIRECTIVE: &str = "# unjust-base:";
+
+/// Generated Justfile that is run for a composed entry
+pub const COMPOSED_FILE: &str = ".unjust-composed.just";
+
+/// Generated file for an entry used as a lower layer of another's composition
+pub(crate) const LAYER_FILE: &str = ".unjust-layer.just";
+
+/// Settings that let upper layers redefine recipes and variables from lower ones
+const COMPOSE_SETTINGS: [&str; 2] = [
+    "set allow-duplicate-recipes",
+    "set allow-duplicate-variables",
+];
+
+/// Get the base a Justfile's content declares, if any
+pub fn parse_base(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let base = line.trim().strip_prefix(BASE_DIRECTIVE)?.trim();
+        (!base.is_empty()).then(|| base.to_string())
+    })
+}
+
+/// Get the recipe names defined in a Justfile's content, in order
+///
+/// This is a lightweight scan rather than a full parse: it picks out
+/// unindented `name ...:` lines and skips comments, settings, imports,
+/// aliases, attributes and assignments.
+pub fn parse_recipes(content: &str) -> Vec<String> {
+    const KEYWORDS: [&str; 6] = ["set", "import", "mod", "alias", "export", "unexport"];
+
+    content
+        .lines()
+        .filter(|line| !line.starts_with([' ', '\t', '#', '[']))
+        .filter_map(|line| {
+            let line = line.trim_start_matches('@');
+            let end = line
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(line.len());
+            let (name, rest) = line.split_at(end);
+            if name.is_empty() || KEYWORDS.contains(&name) {
+                return None;
+            }
+
+            // A recipe header has a `:` that isn't part of `:=`
+            let colon = rest.find(':')?;
+            let is_assignment = rest[colon..].starts_with(":=");
+            let before_colon = &rest[..colon];
+            (!is_assignment && !before_colon.contains(":=")).then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Get the layers making up a stored Justfile, lowest base first
+///
+/// The last layer is the Justfile itself. Fails if a base is missing from
+/// the store or the bases form a cycle.
+pub fn justfile_layers(cache_dir: &Path, justfile: &Justfile) -> io::Result<Vec<Justfile>> {
+    resolve_layers(justfile, |base| stored_layer(cache_dir, base))
+}
+
+/// Get a store entry's current Justfile, to use as a layer
+pub(crate) fn stored_layer(cache_dir: &Path, repo: &str) -> io::Result<Option<Justfile>> {
+    let path = justfile_in_dir(&entry_dir(cache_dir, repo)?)?;
+    Ok(path.map(|path| Justfile::new(repo.to_string(), path)))
+}
+
+/// Get the layers making up a Justfile, lowest base first, with
+/// `find_base` giving the Justfile to use for each base
+pub(crate) fn resolve_layers(
+    justfile: &Justfile,
+    mut find_base: impl FnMut(&str) -> io::Result<Option<Justfile>>,
+) -> io::Result<Vec<Justfile>> {
+    let mut layers = vec![justfile.clone()];
+    let mut seen = HashSet::from([justfile.repo_name.clone()]);
+
+    while let Some(base) = parse_base(&layers[layers.len() - 1].read_content()?) {
+        let requiring = &layers[layers.len() - 1].repo_name;
+        if !seen.insert(base.clone()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Cycle in Justfile bases: {requiring} builds on {base}"),
+            ));
+        }
+
+        let layer = find_base(&base)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Base Justfile not found for repo: {base} (required by {requiring})"),
+            )
+        })?;
+        layers.push(layer);
+    }
+
+    layers.reverse();
+    Ok(layers)
+}
+
+/// Quote a path for a just `import` statement
+pub(crate) fn import_line(path: &Path) -> String {
+    format!(
+        "import '{}'",
+        path.display().to_string().replace('\'', "\\'")
+    )
+}
+
+fn generated_path(justfile: &Justfile, name: &str) -> io::Result<PathBuf> {
+    let dir = justfile.path.parent().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Not a file path: {}", justfile.path.display()),
+        )
+    })?;
+    Ok(dir.join(name))
+}
+
+/// Build the Justfile to run for a stored Justfile, composing its bases
+///
+/// Returns the Justfile's own path when it has no base, and otherwise the
+/// path of the generated composed Justfile. Fails if a layer imports a file
+/// that isn't in its entry.
+pub fn compose_justfile(cache_dir: &Path, justfile: &Justfile) -> io::Result<PathBuf> {
+    compose_layers(&justfile_layers(cache_dir, justfile)?)
+}
+
+/// Build the Justfile to run for a list of layers, lowest base first
+///
+/// Works like [`compose_justfile`], for layers that weren't all taken from
+/// the store's current entries.
+pub fn compose_layers(layers: &[Justfile]) -> io::Result<PathBuf> {
+    let justfile = layers
+        .last()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No layers to compose"))?;
+    for layer in layers {
+        justfile_files(&layer.path)?;
+    }
+    if layers.len() == 1 {
+        return Ok(justfile.path.clone());
+    }
+
+    let contents = layers
+        .iter()
+        .map(Justfile::read_content)
+        .collect::<io::Result<Vec<_>>>()?;
+
+    // Settings are global in just, so only the top file sets them, and only
+    // if no layer already does
+    let settings: Vec<&str> = COMPOSE_SETTINGS
+        .into_iter()
+        .filter(|setting| {
+            !contents
+                .iter()
+                .any(|c| c.lines().any(|l| l.trim() == *setting))
+        })
+        .collect();
+
+    let mut below: Option<PathBuf> = None;
+    for (index, (layer, content)) in layers.iter().zip(&contents).enumerate() {
+        let is_top = index == layers.len() - 1;
+        let path = generated_path(layer, if is_top { COMPOSED_FILE } else { LAYER_FILE })?;
+
+        let mut generated = format!(
+            "# Generated by unjust from {}, do not edit\n",
+            layer.repo_name
+        );
+        if is_top {
+            for setting in &settings {
+                generated.push_str(setting);
+                generated.push('\n');
+            }
+        }
+        if let Some(below) = &below {
+            generated.push_str(&import_line(below));
+            generated.push('\n');
+        }
+        generated.push('\n');
+        generated.push_str(content);
+
+        write_atomic(&path, generated)?;
+        below = Some(path);
+    }
+
+    Ok(below.unwrap_or_else(|| justfile.path.clone()))
+}
+
+/// Get each recipe a stored Justfile provides along with the layer (repo
+/// id) it comes from, sorted by recipe name
+///
+/// When several layers define a recipe the uppermost one wins, matching the
+/// composed Justfile.
+pub fn recipe_layers(cache_dir: &Path, justfile: &Justfile) -> io::Result<Vec<(String, String)>> {
+    let mut recipes = BTreeMap::new();
+    for layer in justfile_layers(cache_dir, justfile)? {
+        for recipe in parse_recipes(&layer.read_content()?) {
+            recipes.insert(recipe, layer.repo_name.clone());
+        }
+    }
+    Ok(recipes.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn store_entry(cache_dir: &Path, repo: &str, content: &str) -> io::Result<Justfile> {
+        let dir = cache_dir.join(repo);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("Justfile");
+        fs::write(&path, content)?;
+        Ok(Justfile::new(repo.to_string(), path))
+    }
+
+    #[test]
+    fn test_parse_base() {
+        assert_eq!(
+            parse_base("# unjust-base: company/base\nbuild:\n"),
+            Some("company/base".to_string())
+        );
+        assert_eq!(parse_base("# unjust-base:\n"), None);
+        assert_eq!(parse_base("build:\n    echo hi\n"), None);
+    }
+
+    #[test]
+    fn test_parse_recipes() {
+        let content = "\
+set shell := ['bash', '-c']
+import 'other.just'
+alias b := build
+version := \"1.0\"
+
+# Build it
+[private]
+build target='all': lint
+    echo {{target}}
+
+@lint:
+    cargo clippy
+
+test *args:
+    cargo test {{args}}
+";
+        assert_eq!(parse_recipes(content), ["build", "lint", "test"]);
+    }
+
+    #[test]
+    fn test_compose_and_recipe_layers() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+
+        store_entry(cache_dir, "company/root", "fmt:\n    cargo fmt\n")?;
+        store_entry(
+            cache_dir,
+            "company/base",
+            "# unjust-base: company/root\nlint:\n    echo base\n",
+        )?;
+        let overlay = store_entry(
+            cache_dir,
+            "user/repo",
+            "# unjust-base: company/base\nlint:\n    echo overlay\n\nbuild:\n    cargo build\n",
+        )?;
+
+        let layers: Vec<_> = justfile_layers(cache_dir, &overlay)?
+            .into_iter()
+            .map(|j| j.repo_name)
+            .collect();
+        assert_eq!(layers, ["company/root", "company/base", "user/repo"]);
+
+        let composed = compose_justfile(cache_dir, &overlay)?;
+        assert_eq!(composed, cache_dir.join("user/repo").join(COMPOSED_FILE));
+        let content = fs::read_to_string(&composed)?;
+        assert!(content.contains("set allow-duplicate-recipes"));
+        assert!(content.contains(&import_line(
+            &cache_dir.join("company/base").join(LAYER_FILE)
+        )));
+        let base_layer = fs::read_to_string(cache_dir.join("company/base").join(LAYER_FILE))?;
+        assert!(!base_layer.contains("set allow-duplicate-recipes"));
+
+        assert_eq!(
+            recipe_layers(cache_dir, &overlay)?,
+            [
+                ("build".to_string(), "user/repo".to_string()),
+                ("fmt".to_string(), "company/root".to_string()),
+                ("lint".to_string(), "user/repo".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compose_without_base() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let justfile = store_entry(temp_dir.path(), "user/repo", "build:\n")?;
+        assert_eq!(compose_justfile(temp_dir.path(), &justfile)?, justfile.path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_and_cyclic_bases() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+
+        let orphan = store_entry(cache_dir, "user/orphan", "# unjust-base: gone/base\n")?;
+        let err = justfile_layers(cache_dir, &orphan).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        store_entry(cache_dir, "a/a", "# unjust-base: b/b\n")?;
+        let b = store_entry(cache_dir, "b/b", "# unjust-base: a/a\n")?;
+        let err = justfile_layers(cache_dir, &b).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+//! Git exclude entries for unjust
+//!
+//! Files unjust leaves in a working tree are listed in `.git/info/exclude`
+//! so they are never committed, without touching the shared `.gitignore`.
+//! unjust only ever edits its own marked block in that file, so entries
+//! added by hand are left alone.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::git::find_git_dirs;
+
+/// First line of the block unjust manages in the exclude file
+const BLOCK_START: &str = "# BEGIN unjust (managed automatically, do not edit)";
+
+/// Last line of the block unjust manages in the exclude file
+const BLOCK_END: &str = "# END unjust";
+
+/// Locate the `info/exclude` file for the git repository containing `dir`
+pub fn exclude_file_path(dir: &Path) -> io::Result<PathBuf> {
+    Ok(find_git_dirs(dir)?.common_dir.join("info").join("exclude"))
+}
+
+/// Build the root-anchored exclude pattern for a file in a working tree,
+/// along with the exclude file it belongs in
+fn exclude_pattern(file: &Path) -> io::Result<(PathBuf, String)> {
+    let Some(name) = file.file_name() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Not a file path: {}", file.display()),
+        ));
+    };
+    let dir = file
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    let dirs = find_git_dirs(dir)?;
+    let path = dirs.common_dir.join("info").join("exclude");
+    Ok((path, format!("/{}{}", dirs.prefix, name.to_string_lossy())))
+}
+
+/// Split an exclude file into the lines before, inside and after the unjust
+/// block
+fn split_block(content: &str) -> (Vec<&str>, Vec<&str>, Vec<&str>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.iter().position(|l| *l == BLOCK_START);
+    let end = start.and_then(|s| {
+        lines[s..]
+            .iter()
+            .position(|l| *l == BLOCK_END)
+            .map(|e| s + e)
+    });
+
+    match (start, end) {
+        (Some(start), Some(end)) => (
+            lines[..start].to_vec(),
+            lines[start + 1..end].to_vec(),
+            lines[end + 1..].to_vec(),
+        ),
+        _ => (lines, Vec::new(), Vec::new()),
+    }
+}
+
+/// Rebuild an exclude file from its parts, dropping the block when empty
+fn join_block(before: &[&str], block: &[&str], after: &[&str]) -> String {
+    let mut lines: Vec<&str> = before.to_vec();
+    if !block.is_empty() {
+        lines.push(BLOCK_START);
+        lines.extend(block);
+        lines.push(BLOCK_END);
+    }
+    lines.extend(after);
+
+    let mut content = lines.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    content
+}
+
+fn read_exclude_file(path: &Path) -> io::Result<String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Add a file in a working tree to the unjust block of its repository's
+/// exclude file, if it isn't listed already
+///
+/// The file is anchored to the working tree root so only that exact path is
+/// excluded. Returns `true` if the entry was added.
+pub fn add_exclude_entry(file: &Path) -> io::Result<bool> {
+    let (path, pattern) = exclude_pattern(file)?;
+    let existing = read_exclude_file(&path)?;
+    let (before, mut block, after) = split_block(&existing);
+
+    if block.contains(&pattern.as_str()) {
+        return Ok(false);
+    }
+    block.push(&pattern);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, join_block(&before, &block, &after))?;
+
+    Ok(true)
+}
+
+/// Remove a file from the unjust block of its repository's exclude file
+///
+/// The block itself is removed once it is empty. Returns `true` if an entry
+/// was removed.
+pub fn remove_exclude_entry(file: &Path) -> io::Result<bool> {
+    let (path, pattern) = exclude_pattern(file)?;
+    let existing = read_exclude_file(&path)?;
+    let (before, mut block, after) = split_block(&existing);
+
+    let count = block.len();
+    block.retain(|line| *line != pattern);
+    if block.len() == count {
+        return Ok(false);
+    }
+
+    fs::write(&path, join_block(&before, &block, &after))?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    /// Run git in `dir` for a test, failing the test if it doesn't succeed
+    fn git(dir: &Path, args: &[&str]) -> io::Result<()> {
+        let status = Command::new("git")
+            .args([
+                "-c",
+                "user.name=unjust",
+                "-c",
+                "user.email=unjust@example.com",
+            ])
+            .args(args)
+            .current_dir(dir)
+            .status()?;
+        assert!(status.success(), "git {args:?} failed");
+        Ok(())
+    }
+
+    /// Whether git is installed, as these tests need a real repository
+    fn has_git() -> bool {
+        Command::new("git").arg("--version").output().is_ok()
+    }
+
+    #[test]
+    fn test_add_and_remove_entries() -> io::Result<()> {
+        if !has_git() {
+            return Ok(());
+        }
+        let temp_dir = TempDir::new()?;
+        let repo = temp_dir.path();
+        git(repo, &["init", "-q"])?;
+        let exclude = repo.join(".git/info/exclude");
+        fs::create_dir_all(exclude.parent().unwrap())?;
+        fs::write(&exclude, "# user comment\n*.log\n")?;
+        fs::create_dir_all(repo.join("sub"))?;
+
+        assert!(add_exclude_entry(&repo.join("Justfile"))?);
+        assert!(add_exclude_entry(&repo.join("sub/.justfile"))?);
+        assert!(!add_exclude_entry(&repo.join("Justfile"))?);
+        assert_eq!(
+            fs::read_to_string(&exclude)?,
+            format!(
+                "# user comment\n*.log\n{BLOCK_START}\n/Justfile\n/sub/.justfile\n{BLOCK_END}\n"
+            )
+        );
+
+        assert!(remove_exclude_entry(&repo.join("Justfile"))?);
+        assert!(!remove_exclude_entry(&repo.join("Justfile"))?);
+        assert!(remove_exclude_entry(&repo.join("sub/.justfile"))?);
+        assert_eq!(fs::read_to_string(&exclude)?, "# user comment\n*.log\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hand_written_entries_are_kept() -> io::Result<()> {
+        if !has_git() {
+            return Ok(());
+        }
+        let temp_dir = TempDir::new()?;
+        let repo = temp_dir.path();
+        git(repo, &["init", "-q"])?;
+        let exclude = repo.join(".git/info/exclude");
+        fs::create_dir_all(exclude.parent().unwrap())?;
+        fs::write(&exclude, "/Justfile\n")?;
+
+        assert!(add_exclude_entry(&repo.join("Justfile"))?);
+        assert!(remove_exclude_entry(&repo.join("Justfile"))?);
+        assert_eq!(fs::read_to_string(&exclude)?, "/Justfile\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_linked_worktree_uses_common_dir() -> io::Result<()> {
+        if !has_git() {
+            return Ok(());
+        }
+        let temp_dir = TempDir::new()?;
+        let main = temp_dir.path().join("main");
+        let linked = temp_dir.path().join("linked");
+        fs::create_dir_all(&main)?;
+        git(&main, &["init", "-q"])?;
+        git(&main, &["commit", "-q", "--allow-empty", "-m", "Initial"])?;
+        git(&main, &["worktree", "add", "-q", linked.to_str().unwrap()])?;
+
+        let dirs = find_git_dirs(&linked)?;
+        assert_eq!(dirs.worktree.canonicalize()?, linked.canonicalize()?);
+        assert_ne!(
+            dirs.git_dir.canonicalize()?,
+            dirs.common_dir.canonicalize()?
+        );
+        assert_eq!(
+            dirs.common_dir.canonicalize()?,
+            main.join(".git").canonicalize()?
+        );
+
+        add_exclude_entry(&linked.join("Justfile"))?;
+        let content = fs::read_to_string(main.join(".git/info/exclude"))?;
+        assert!(content.lines().any(|l| l == "/Justfile"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_submodule_uses_its_own_git_dir() -> io::Result<()> {
+        if !has_git() {
+            return Ok(());
+        }
+        let temp_dir = TempDir::new()?;
+        let parent = temp_dir.path();
+        let submodule = parent.join("vendor/lib");
+        let module_dir = parent.join(".git/modules/vendor/lib");
+        git(parent, &["init", "-q"])?;
+        fs::create_dir_all(&submodule)?;
+        fs::create_dir_all(module_dir.parent().unwrap())?;
+        git(
+            &submodule,
+            &[
+                "init",
+                "-q",
+                "--separate-git-dir",
+                module_dir.to_str().unwrap(),
+            ],
+        )?;
+
+        assert_eq!(
+            exclude_file_path(&submodule)?.canonicalize()?,
+            module_dir.join("info/exclude").canonicalize()?
+        );
+
+        add_exclude_entry(&submodule.join("Justfile"))?;
+        let content = fs::read_to_string(module_dir.join("info/exclude"))?;
+        assert!(content.lines().any(|l| l == "/Justfile"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_outside_git_repo() {
+        let temp_dir = TempDir::new().unwrap();
+        let err = add_exclude_entry(&temp_dir.path().join("Justfile")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,99 @@
+//! Git helpers for unjust
+//!
+//! unjust shells out to the `git` binary rather than linking a git library.
+//! Repository layout is always asked of `git rev-parse`, so `GIT_DIR`,
+//! `GIT_COMMON_DIR`, `core.worktree`, linked worktrees and submodules are
+//! all resolved the way git itself resolves them.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Run a git command in `dir` and return its trimmed stdout
+///
+/// Returns `None` if git is not installed or the command fails (for example
+/// because `dir` is not inside a git repository).
+pub(crate) fn git_output(dir: &Path, args: &[&str]) -> io::Result<Option<String>> {
+    let output = match Command::new("git").args(args).current_dir(dir).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+/// Get the URL of a git remote for the repository containing `dir`, if it
+/// has that remote
+pub fn remote_url(dir: &Path, remote: &str) -> io::Result<Option<String>> {
+    git_output(dir, &["remote", "get-url", remote])
+}
+
+/// Get the root of the git working tree containing `dir`, if any
+pub fn worktree_root(dir: &Path) -> io::Result<Option<PathBuf>> {
+    Ok(git_output(dir, &["rev-parse", "--show-toplevel"])?.map(PathBuf::from))
+}
+
+/// Locations of a git working tree and the directories git keeps for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitDirs {
+    /// Root of the working tree
+    pub worktree: PathBuf,
+
+    /// The git dir for this working tree
+    ///
+    /// For linked worktrees and submodules `.git` is a file pointing here.
+    pub git_dir: PathBuf,
+
+    /// The git dir shared by all worktrees, which holds `info/exclude`
+    pub common_dir: PathBuf,
+
+    /// Path of `dir` relative to the working tree root, ending in `/`
+    /// unless it's the root itself
+    pub prefix: String,
+}
+
+/// Find the git directories for the working tree containing `dir`
+///
+/// Fails with `NotFound` if `dir` isn't in a working tree or git isn't
+/// installed.
+pub fn find_git_dirs(dir: &Path) -> io::Result<GitDirs> {
+    let args = [
+        "rev-parse",
+        "--show-toplevel",
+        "--git-dir",
+        "--git-common-dir",
+        "--show-prefix",
+    ];
+    let output = git_output(dir, &args)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Not a git repository: {}", dir.display()),
+        )
+    })?;
+
+    // An empty prefix prints as an empty line, which trimming drops
+    let mut lines = output.lines();
+    let mut next = || lines.next().unwrap_or_default();
+    let (worktree, git_dir, common_dir, prefix) = (next(), next(), next(), next());
+    if worktree.is_empty() || git_dir.is_empty() || common_dir.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unexpected output from git rev-parse: {output}"),
+        ));
+    }
+
+    // Git dirs are given relative to `dir` unless git gives absolute ones
+    Ok(GitDirs {
+        worktree: PathBuf::from(worktree),
+        git_dir: dir.join(git_dir),
+        common_dir: dir.join(common_dir),
+        prefix: prefix.to_string(),
+    })
+}
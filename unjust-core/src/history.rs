@@ -0,0 +1,148 @@
+//! Usage history for unjust
+//!
+//! Every `use` is appended to a history file in the cache directory, so it's
+//! possible to see which Justfiles and recipes get used, and which fail.
+
+use crate::lock::StoreLock;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the history file inside the cache directory
+const HISTORY_FILE: &str = ".history";
+
+/// One recorded use of a stored Justfile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageRecord {
+    /// When it was used, in seconds since the Unix epoch
+    pub time: u64,
+
+    /// Repository id of the Justfile
+    pub repo: String,
+
+    /// Recipe that was run, if any
+    pub recipe: Option<String>,
+
+    /// Exit code of the run
+    pub exit_code: i32,
+
+    /// How long the run took, in milliseconds
+    pub duration_ms: u64,
+
+    /// Directory it was used from
+    pub cwd: PathBuf,
+}
+
+impl UsageRecord {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.time,
+            self.repo,
+            self.recipe.as_deref().unwrap_or("-"),
+            self.exit_code,
+            self.duration_ms,
+            self.cwd.display()
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(6, '\t');
+        let time = parts.next()?.parse().ok()?;
+        let repo = parts.next()?.to_string();
+        let recipe = Some(parts.next()?)
+            .filter(|r| *r != "-")
+            .map(str::to_string);
+        let exit_code = parts.next()?.parse().ok()?;
+        let duration_ms = parts.next()?.parse().ok()?;
+        let cwd = PathBuf::from(parts.next()?);
+        Some(Self {
+            time,
+            repo,
+            recipe,
+            exit_code,
+            duration_ms,
+            cwd,
+        })
+    }
+
+    /// Whether the run failed
+    pub fn failed(&self) -> bool {
+        self.exit_code != 0
+    }
+}
+
+/// Append a use to the history
+pub fn record_usage(cache_dir: &Path, record: &UsageRecord) -> io::Result<()> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(cache_dir.join(HISTORY_FILE))?;
+    writeln!(file, "{}", record.to_line())
+}
+
+/// Read the history, oldest first
+pub fn read_history(cache_dir: &Path) -> io::Result<Vec<UsageRecord>> {
+    match fs::read_to_string(cache_dir.join(HISTORY_FILE)) {
+        Ok(content) => Ok(content.lines().filter_map(UsageRecord::from_line).collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Describe how long ago a time was, like `5m ago`
+pub fn format_age(time: u64, now: u64) -> String {
+    let elapsed = now.saturating_sub(time);
+    match elapsed {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", elapsed / 60),
+        3600..86400 => format!("{}h ago", elapsed / 3600),
+        _ => format!("{}d ago", elapsed / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_read_history() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+        assert!(read_history(cache_dir)?.is_empty());
+
+        let ok = UsageRecord {
+            time: 1_700_000_000,
+            repo: "user/repo".to_string(),
+            recipe: Some("build".to_string()),
+            exit_code: 0,
+            duration_ms: 1200,
+            cwd: PathBuf::from("/work/with\ttab"),
+        };
+        let failed = UsageRecord {
+            recipe: None,
+            exit_code: 2,
+            ..ok.clone()
+        };
+        record_usage(cache_dir, &ok)?;
+        record_usage(cache_dir, &failed)?;
+
+        let history = read_history(cache_dir)?;
+        assert_eq!(history, [ok, failed]);
+        assert!(!history[0].failed());
+        assert!(history[1].failed());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(100, 130), "just now");
+        assert_eq!(format_age(100, 400), "5m ago");
+        assert_eq!(format_age(0, 7200), "2h ago");
+        assert_eq!(format_age(0, 3 * 86400), "3d ago");
+        assert_eq!(format_age(200, 100), "just now");
+    }
+}
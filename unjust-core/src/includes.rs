@@ -0,0 +1,316 @@
+//! Multi-file store entries for unjust
+//!
+//! A stored Justfile can pull in other files with `import` and `mod`
+//! statements. Those files live in the same entry directory and travel with
+//! the Justfile, so every path they name has to resolve inside the entry.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::atomic::copy_atomic;
+use crate::justfile::is_justfile_name;
+
+/// A file a Justfile pulls in with `import` or `mod`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Include {
+    /// `import 'path'`, or `import? 'path'` when optional
+    Import {
+        /// Path relative to the including file
+        path: String,
+        /// Whether a missing file is allowed
+        optional: bool,
+    },
+    /// `mod name`, `mod name 'path'`, or `mod? ...` when optional
+    Module {
+        /// Module name
+        name: String,
+        /// Explicit path relative to the including file, if given
+        path: Option<String>,
+        /// Whether a missing module is allowed
+        optional: bool,
+    },
+}
+
+impl Include {
+    fn optional(&self) -> bool {
+        match self {
+            Include::Import { optional, .. } | Include::Module { optional, .. } => *optional,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Include::Import { path, .. } => format!("import '{path}'"),
+            Include::Module {
+                name,
+                path: Some(path),
+                ..
+            } => format!("mod {name} '{path}'"),
+            Include::Module { name, .. } => format!("mod {name}"),
+        }
+    }
+}
+
+/// Parse a quoted string literal at the start of `s`
+fn parse_quoted(s: &str) -> Option<String> {
+    let quote = s.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let end = s[1..].find(quote)?;
+    Some(s[1..1 + end].to_string())
+}
+
+/// Get the `import` and `mod` statements in a Justfile's content, in order
+pub fn parse_includes(content: &str) -> Vec<Include> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with([' ', '\t']))
+        .filter_map(|line| {
+            let (keyword, rest) = line.split_once(char::is_whitespace)?;
+            let rest = rest.trim();
+            match keyword {
+                "import" | "import?" => Some(Include::Import {
+                    path: parse_quoted(rest)?,
+                    optional: keyword == "import?",
+                }),
+                "mod" | "mod?" => {
+                    let (name, path) = match rest.split_once(char::is_whitespace) {
+                        Some((name, path)) => (name, parse_quoted(path.trim())),
+                        None => (rest, None),
+                    };
+                    Some(Include::Module {
+                        name: name.to_string(),
+                        path,
+                        optional: keyword == "mod?",
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Normalise `..` and `.` components without touching the filesystem
+pub fn normalise(path: &Path) -> PathBuf {
+    let mut normalised = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalised.pop();
+            }
+            other => normalised.push(other),
+        }
+    }
+    normalised
+}
+
+/// Find the module source file in a module directory, as just would
+fn module_file_in(dir: &Path) -> io::Result<Option<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+    let mod_just = dir.join("mod.just");
+    if mod_just.is_file() {
+        return Ok(Some(mod_just));
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.path().is_file() && entry.file_name().to_str().is_some_and(is_justfile_name) {
+            return Ok(Some(entry.path()));
+        }
+    }
+    Ok(None)
+}
+
+/// Get the paths an include could refer to, relative to the including file's
+/// directory, in the order just tries them
+fn candidates(dir: &Path, include: &Include) -> Vec<PathBuf> {
+    match include {
+        Include::Import { path, .. } => vec![dir.join(path)],
+        Include::Module {
+            path: Some(path), ..
+        } => vec![dir.join(path)],
+        Include::Module { name, .. } => vec![dir.join(format!("{name}.just")), dir.join(name)],
+    }
+}
+
+/// Resolve an include to a file, checking it stays inside `root`
+fn resolve_include(root: &Path, dir: &Path, include: &Include) -> io::Result<Option<PathBuf>> {
+    for candidate in candidates(dir, include) {
+        let candidate = normalise(&candidate);
+        if !candidate.starts_with(root) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} points outside the Justfile's directory",
+                    include.describe()
+                ),
+            ));
+        }
+
+        if candidate.is_file() {
+            return Ok(Some(candidate));
+        }
+        if matches!(include, Include::Module { .. })
+            && let Some(file) = module_file_in(&candidate)?
+        {
+            return Ok(Some(file));
+        }
+    }
+
+    if include.optional() {
+        Ok(None)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Could not find the file for {}", include.describe()),
+        ))
+    }
+}
+
+/// Get every file a Justfile consists of: the Justfile itself followed by
+/// everything it imports or loads as a module, recursively
+///
+/// All of them must resolve inside the Justfile's directory. Fails if a
+/// required file is missing or a path points outside the directory.
+pub fn justfile_files(justfile: &Path) -> io::Result<Vec<PathBuf>> {
+    let root = normalise(justfile.parent().unwrap_or(Path::new(".")));
+    let mut files = vec![normalise(justfile)];
+    let mut seen: HashSet<PathBuf> = files.iter().cloned().collect();
+
+    let mut next = 0;
+    while next < files.len() {
+        let file = files[next].clone();
+        next += 1;
+
+        let dir = file.parent().unwrap_or(&root).to_path_buf();
+        for include in parse_includes(&fs::read_to_string(&file)?) {
+            if let Some(found) = resolve_include(&root, &dir, &include)?
+                && seen.insert(found.clone())
+            {
+                files.push(found);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Copy a Justfile and every file it includes into `target_dir`, keeping
+/// their layout relative to the Justfile
+///
+/// Returns the path of the copied Justfile.
+pub(crate) fn copy_justfile_files(source: &Path, target_dir: &Path) -> io::Result<PathBuf> {
+    let files = justfile_files(source)?;
+    let root = normalise(source.parent().unwrap_or(Path::new(".")));
+
+    let mut copied = Vec::with_capacity(files.len());
+    for file in &files {
+        let relative = file.strip_prefix(&root).unwrap_or(file);
+        let target = target_dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        copy_atomic(file, &target)?;
+        copied.push(target);
+    }
+
+    Ok(copied.swap_remove(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_includes() {
+        let content = "\
+import 'common.just'
+import? \"local.just\"
+mod ci
+mod? deploy 'ops/deploy.just'
+build:
+    import 'not/a/statement'
+";
+        assert_eq!(
+            parse_includes(content),
+            [
+                Include::Import {
+                    path: "common.just".to_string(),
+                    optional: false
+                },
+                Include::Import {
+                    path: "local.just".to_string(),
+                    optional: true
+                },
+                Include::Module {
+                    name: "ci".to_string(),
+                    path: None,
+                    optional: false
+                },
+                Include::Module {
+                    name: "deploy".to_string(),
+                    path: Some("ops/deploy.just".to_string()),
+                    optional: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_justfile_files() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        fs::create_dir_all(dir.join("ci"))?;
+        fs::write(
+            dir.join("Justfile"),
+            "import 'common.just'\nimport? 'missing.just'\nmod ci\n",
+        )?;
+        fs::write(dir.join("common.just"), "import 'shared/more.just'\n")?;
+        fs::create_dir_all(dir.join("shared"))?;
+        fs::write(dir.join("shared/more.just"), "")?;
+        fs::write(dir.join("ci/mod.just"), "")?;
+
+        let files = justfile_files(&dir.join("Justfile"))?;
+        let relative: Vec<_> = files
+            .iter()
+            .map(|f| f.strip_prefix(dir).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            relative,
+            [
+                PathBuf::from("Justfile"),
+                PathBuf::from("common.just"),
+                PathBuf::from("ci/mod.just"),
+                PathBuf::from("shared/more.just"),
+            ]
+        );
+
+        let target = TempDir::new()?;
+        let copied = copy_justfile_files(&dir.join("Justfile"), target.path())?;
+        assert_eq!(copied, target.path().join("Justfile"));
+        assert!(target.path().join("shared/more.just").is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unresolved_includes() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path().join("entry");
+        fs::create_dir_all(&dir)?;
+        fs::write(temp_dir.path().join("outside.just"), "")?;
+
+        fs::write(dir.join("Justfile"), "import '../outside.just'\n")?;
+        let err = justfile_files(&dir.join("Justfile")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::write(dir.join("Justfile"), "mod ci\n")?;
+        let err = justfile_files(&dir.join("Justfile")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        Ok(())
+    }
+}
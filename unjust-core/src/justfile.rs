@@ -12,12 +12,19 @@ pub struct Justfile {
 
     /// Path to the Justfile
     pub path: PathBuf,
+
+    /// Remote it came from, if it has been synced
+    pub remote: Option<String>,
 }
 
 impl Justfile {
     /// Create a new Justfile
     pub fn new(repo_name: String, path: PathBuf) -> Self {
-        Self { repo_name, path }
+        Self {
+            repo_name,
+            path,
+            remote: None,
+        }
     }
 
     /// Check if the Justfile exists
@@ -36,25 +43,77 @@ impl Justfile {
     }
 }
 
+/// Check whether a file name is one just recognises as a Justfile
+///
+/// Like just, this matches `justfile` and `.justfile` in any case.
+pub fn is_justfile_name(name: &str) -> bool {
+    name.eq_ignore_ascii_case("justfile") || name.eq_ignore_ascii_case(".justfile")
+}
+
+/// Sort key giving the precedence of Justfile names when several exist
+///
+/// Visible names beat hidden ones, then the canonical `Justfile` beats
+/// `justfile`, and any other case variants follow in byte order.
+fn name_precedence(name: &str) -> (bool, u8, &str) {
+    let rank = match name.trim_start_matches('.') {
+        "Justfile" => 0,
+        "justfile" => 1,
+        _ => 2,
+    };
+    (name.starts_with('.'), rank, name)
+}
+
+/// Find the Justfile in a project directory, as just would
+///
+/// Returns `None` if there isn't one, and an error if there are several
+/// candidates since just would refuse to pick between them too.
+pub fn find_local_justfile(dir: &Path) -> io::Result<Option<PathBuf>> {
+    let mut found = find_justfiles_in_dir(dir)?;
+
+    match found.len() {
+        0 => Ok(None),
+        1 => Ok(found.pop()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Multiple candidate Justfiles found in {}", dir.display()),
+        )),
+    }
+}
+
 /// Look for Justfiles in a directory
-#[allow(dead_code)]
+///
+/// Every name just recognises is matched, and the result is ordered by
+/// precedence so the first path is the one unjust uses.
 pub fn find_justfiles_in_dir(dir: &Path) -> io::Result<Vec<PathBuf>> {
-    let mut result = Vec::new();
+    let mut found = Vec::new();
 
     if dir.exists() {
         for entry in fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
 
-            if path.is_file() && path.file_name().and_then(|n| n.to_str()) == Some("Justfile") {
-                result.push(path);
+            if path.is_file() && is_justfile_name(&name) {
+                found.push((name, path));
             }
         }
     }
 
+    found.sort_by(|(a, _), (b, _)| name_precedence(a).cmp(&name_precedence(b)));
+    let result = found.into_iter().map(|(_, path)| path).collect();
+
     Ok(result)
 }
 
+/// Get the Justfile a store entry directory uses, if it has one
+///
+/// When several names are present the highest precedence one wins.
+pub fn justfile_in_dir(dir: &Path) -> io::Result<Option<PathBuf>> {
+    Ok(find_justfiles_in_dir(dir)?.into_iter().next())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -73,6 +132,55 @@ mod tests {
         assert_eq!(justfile.path, path);
     }
 
+    #[test]
+    fn test_find_local_justfile() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+
+        assert_eq!(find_local_justfile(dir)?, None);
+
+        fs::write(dir.join(".justfile"), "# hidden")?;
+        assert_eq!(find_local_justfile(dir)?, Some(dir.join(".justfile")));
+
+        fs::write(dir.join("Justfile"), "# visible")?;
+        assert!(find_local_justfile(dir).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_justfile_name() {
+        for name in ["Justfile", "justfile", "JUSTFILE", ".justfile", ".Justfile"] {
+            assert!(is_justfile_name(name), "{name}");
+        }
+        for name in ["Justfile.bak", "justfile.just", "..justfile", "Makefile"] {
+            assert!(!is_justfile_name(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_find_justfiles_in_dir_precedence() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+
+        for name in [".justfile", "JUSTFILE", "justfile", "Justfile"] {
+            fs::write(dir.join(name), name)?;
+        }
+
+        // Case-insensitive filesystems only keep the first of these
+        let found = find_justfiles_in_dir(dir)?;
+        let names: Vec<_> = found
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        if names.len() == 4 {
+            assert_eq!(names, ["Justfile", "justfile", "JUSTFILE", ".justfile"]);
+        }
+        assert_eq!(justfile_in_dir(dir)?, found.first().cloned());
+
+        Ok(())
+    }
+
     #[test]
     fn test_justfile_display_name() {
         let repo_name = "user/project".to_string();
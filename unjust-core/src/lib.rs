@@ -6,18 +6,96 @@
 //! without any CLI-specific code.
 
 use standard_paths::{LocationType, StandardPaths};
+use std::collections::HashSet;
+use std::ffi::OsString;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
+mod atomic;
+mod bindings;
+mod changes;
+mod compose;
+mod exclude;
+mod git;
+mod history;
+mod includes;
 mod justfile;
+mod lock;
+mod manifest;
+mod materialise;
+mod objects;
+mod pins;
+mod plan;
+mod remotes;
 mod repository;
-
-pub use justfile::Justfile;
-pub use repository::Repository;
+mod revisions;
+mod status;
+mod store;
+mod tags;
+
+pub use atomic::{copy_atomic, write_atomic};
+pub use bindings::{
+    Binding, add_binding, glob_match, read_bindings, remove_binding, resolve_binding,
+};
+pub use changes::{
+    Change, change_settled, clear_changes, pending_changes, queue_local_changes, settle_changes,
+};
+pub use compose::{
+    COMPOSED_FILE, compose_justfile, compose_layers, justfile_layers, parse_base, parse_recipes,
+    recipe_layers,
+};
+pub use exclude::{add_exclude_entry, exclude_file_path, remove_exclude_entry};
+pub use git::{GitDirs, find_git_dirs, remote_url, worktree_root};
+pub use history::{UsageRecord, format_age, read_history, record_usage};
+pub use includes::{Include, justfile_files, normalise, parse_includes};
+pub use justfile::{
+    Justfile, find_justfiles_in_dir, find_local_justfile, is_justfile_name, justfile_in_dir,
+};
+pub use lock::{FileLock, StoreLock};
+pub use manifest::{
+    MANIFEST_FILE, Manifest, content_hash, current_hash, manifest_path, now, read_manifest,
+    record_content_change, record_use, update_manifest, write_manifest,
+};
+pub use materialise::{LinkMode, Materialised, link_justfile, materialised_in, unlink_justfile};
+pub use objects::{
+    OBJECTS_DIR, Tree, collect_garbage, object_id, object_path, place_object, read_tree,
+    store_object, store_tree,
+};
+pub use pins::{
+    Pin, checkout_layers, find_pin, pin_entry, pin_outdated, pinned_justfile, read_pins, remove_pin,
+};
+pub use plan::{PlannedSync, SyncAction, plan_entry, plan_sync};
+pub use remotes::{Remote, add_remote, read_remotes, remove_remote, set_remote_priority};
+pub use repository::{Repository, detect_current_repo, parse_remote_url};
+pub use revisions::{
+    Revision, diff_lines, diff_revision, merge_lines, restore_revision, revision_dir, revisions,
+    snapshot,
+};
+pub use status::{
+    EntryState, EntryStatus, SyncRecord, SyncState, compare_hashes, entry_origins, entry_statuses,
+    local_hashes, provided_statuses, read_sync_state, record_remote_hashes, release_entries,
+    remote_statuses, write_sync_state,
+};
+use store::SUBPATH_DIR;
+pub use store::{
+    SUBPATH_SEPARATOR, adopt_justfile, empty_trash, entry_dir, remove_justfile, rename_justfile,
+    restore_removed, split_repo_id,
+};
+pub use tags::{find_template, has_tag, tag_entry, tagged_entries, untag_entry};
+
+/// Environment variable that overrides the cache directory location
+pub const CACHE_DIR_ENV: &str = "UNJUST_CACHE_DIR";
 
 /// Get the cache directory for unjust
+///
+/// The `UNJUST_CACHE_DIR` environment variable takes precedence over the
+/// platform cache location.
 pub fn get_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(CACHE_DIR_ENV).filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+
     let sp = StandardPaths::new("unjust.core", "unjust");
     sp.writable_location(LocationType::AppCacheLocation).ok()
 }
@@ -47,48 +125,144 @@ pub fn ensure_cache_dir() -> io::Result<PathBuf> {
 }
 
 /// Find an appropriate Justfile for the given repository
+///
+/// The store keeps one copy of each entry. When several remotes have it,
+/// sync keeps the copy from the remote first in priority order, and that
+/// is the remote the Justfile is reported to come from.
 pub fn find_justfile(repo_name: &str, _separate_upstream: bool) -> io::Result<Option<Justfile>> {
     let cache_dir = ensure_cache_dir()?;
 
-    // Simple implementation for now - just check if the file exists in cache
-    let justfile_path = cache_dir.join(repo_name).join("Justfile");
+    let entry = entry_dir(&cache_dir, repo_name)?;
+    let Some(path) = justfile_in_dir(&entry)? else {
+        return Ok(None);
+    };
+    let mut justfile = Justfile::new(repo_name.to_string(), path);
+    justfile.remote = entry_origins(&cache_dir)?.remove(repo_name);
+    Ok(Some(justfile))
+}
+
+/// Work out which repository id a directory should use
+///
+/// A binding for the directory takes precedence over its git remotes. In a
+/// fork the upstream's Justfile is used if the store has one, unless
+/// `separate_upstream` is set.
+pub fn resolve_repo_for_dir(dir: &Path, separate_upstream: bool) -> io::Result<Option<String>> {
+    let cache_dir = ensure_cache_dir()?;
+
+    if let Some(binding) = resolve_binding(&cache_dir, dir)? {
+        return Ok(Some(binding.repo));
+    }
+
+    let Some(repo) = detect_current_repo(dir, &cache_dir)? else {
+        return Ok(None);
+    };
 
-    if justfile_path.exists() {
-        Ok(Some(Justfile::new(repo_name.to_string(), justfile_path)))
-    } else {
-        Ok(None)
+    let mut base = repo.name;
+    if let Some(upstream) = repo.upstream.filter(|_| !separate_upstream) {
+        let upstream_dir = entry_dir(&cache_dir, &upstream)?;
+        if justfile_in_dir(&upstream_dir)?.is_some() {
+            base = upstream;
+        }
     }
+
+    // In a subdirectory, prefer the deepest subpath-scoped Justfile
+    if let Some(root) = worktree_root(dir)? {
+        let relative = dir.strip_prefix(&root).unwrap_or(Path::new(""));
+        for subdir in relative.ancestors().filter(|p| !p.as_os_str().is_empty()) {
+            let subpath = path_to_id(subdir);
+            let id = format!("{base}{SUBPATH_SEPARATOR}{subpath}");
+            if justfile_in_dir(&entry_dir(&cache_dir, &id)?)?.is_some() {
+                return Ok(Some(id));
+            }
+        }
+    }
+
+    Ok(Some(base))
 }
 
 /// List all available Justfiles in the cache
+///
+/// Repository ids may be nested (`username/repo`), so the cache is scanned
+/// recursively. Hidden directories are skipped, apart from the one holding
+/// subpath-scoped entries (`username/repo//packages/api`).
 pub fn list_justfiles() -> io::Result<Vec<Justfile>> {
-    let cache_dir = match get_cache_dir() {
+    let dir = match get_cache_dir() {
         Some(dir) if dir.exists() => dir,
         _ => return Ok(Vec::new()),
     };
 
+    let mut justfiles = justfiles_in(&dir)?;
+    let mut origins = entry_origins(&dir)?;
+    for justfile in &mut justfiles {
+        justfile.remote = origins.remove(&justfile.repo_name);
+    }
+    Ok(justfiles)
+}
+
+/// List the Justfiles in a directory laid out like the cache
+///
+/// Remote storage keeps entries in the same layout, so this lists those too.
+pub fn justfiles_in(root: &Path) -> io::Result<Vec<Justfile>> {
     let mut result = Vec::new();
+    collect_justfiles(root, root, &HashSet::new(), &mut result)?;
+    result.sort_by(|a, b| a.repo_name.cmp(&b.repo_name));
 
-    // This is a simplified implementation - we would normally scan subdirectories
-    if let Ok(entries) = fs::read_dir(&cache_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() {
-                let justfile_path = path.join("Justfile");
-                if justfile_path.exists() {
-                    let repo_name = path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("unknown")
-                        .to_string();
-
-                    result.push(Justfile::new(repo_name, justfile_path));
-                }
-            }
+    Ok(result)
+}
+
+/// Collect the entries below `dir`, leaving out the subdirectories named in
+/// `skip`
+fn collect_justfiles(
+    cache_dir: &Path,
+    dir: &Path,
+    skip: &HashSet<OsString>,
+    result: &mut Vec<Justfile>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let is_hidden = name.to_string_lossy().starts_with('.') && name != SUBPATH_DIR;
+        if !path.is_dir() || is_hidden || skip.contains(&name) {
+            continue;
         }
+
+        let Some(justfile_path) = justfile_in_dir(&path)? else {
+            collect_justfiles(cache_dir, &path, &HashSet::new(), result)?;
+            continue;
+        };
+
+        // Directories holding the entry's own modules aren't entries. An
+        // entry that can't be read still lists, so its files are a best guess
+        let root = normalise(&path);
+        let owned = justfile_files(&justfile_path)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|file| {
+                let mut parts = file.strip_prefix(&root).ok()?.components();
+                let first = parts.next()?;
+                parts.next().map(|_| first.as_os_str().to_os_string())
+            })
+            .collect();
+        result.push(Justfile::new(repo_id_for(cache_dir, &path), justfile_path));
+        collect_justfiles(cache_dir, &path, &owned, result)?;
     }
 
-    Ok(result)
+    Ok(())
+}
+
+/// Join the components of a relative path with `/`
+fn path_to_id(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Build an entry id (`username/repo` or `username/repo//subpath`) from a
+/// directory in the cache
+fn repo_id_for(cache_dir: &Path, dir: &Path) -> String {
+    let relative = path_to_id(dir.strip_prefix(cache_dir).unwrap_or(dir));
+    relative.replacen(&format!("/{SUBPATH_DIR}/"), SUBPATH_SEPARATOR, 1)
 }
 
 /// Create a basic Justfile template
@@ -120,26 +294,17 @@ mod tests {
         use super::*;
         use tempfile::TempDir;
 
-        // TODO: Refactor this into a separate module
-        // use unjust_core::{ensure_cache_dir, get_cache_dir, is_first_use};
-
-        /// Helper function to patch the standard paths library for testing
-        /// This is a mock implementation for testing purposes
+        /// Run `f` with the cache directory pointed at a fresh temporary path
+        ///
+        /// The path itself is not created, so it behaves like a first use.
         fn with_temp_cache_dir<F, R>(f: F) -> R
         where
             F: FnOnce(&Path) -> R,
         {
-            // Create a temporary directory for testing
             let temp_dir = TempDir::new().expect("Failed to create temp dir");
-            let temp_path = temp_dir.path();
+            let cache_path = temp_dir.path().join("cache");
 
-            // We would ideally monkey patch get_cache_dir to use our temp dir
-            // For this example, let's assume we've found a way to do that
-            // In practice, you might use mockall or another approach
-
-            // Call the function with our temporary path
-            // Return the result (temp_dir will be cleaned up automatically)
-            f(temp_path)
+            temp_env::with_var(CACHE_DIR_ENV, Some(&cache_path), || f(&cache_path))
         }
 
         #[test]
@@ -152,25 +317,42 @@ mod tests {
                 fs::create_dir_all(dir).expect("Failed to create directory");
 
                 // Now is_first_use should return false
-                // Note: In a real test, we'd ensure get_cache_dir returns dir
-                // assert!(!is_first_use());
+                assert!(!is_first_use());
             });
         }
 
         #[test]
-        #[ignore]
         fn test_ensure_cache_dir() {
             with_temp_cache_dir(|dir| {
                 // The directory shouldn't exist yet
                 assert!(!dir.exists());
 
-                // Call ensure_cache_dir - again, we'd need to patch get_cache_dir
-                // let result = ensure_cache_dir();
-                // assert!(result.is_ok());
+                let result = ensure_cache_dir();
+                assert!(result.is_ok());
 
                 // The directory should now exist
-                // assert!(dir.exists());
+                assert!(dir.exists());
             });
         }
+
+        #[test]
+        fn test_justfiles_in_skips_modules() -> io::Result<()> {
+            let temp_dir = TempDir::new()?;
+            let root = temp_dir.path();
+            let entry = root.join("user/repo");
+            fs::create_dir_all(entry.join("ci"))?;
+            fs::write(entry.join("justfile"), "mod ci\n")?;
+            fs::write(entry.join("ci/justfile"), "test:\n")?;
+            fs::create_dir_all(root.join("user/repo/tools"))?;
+            fs::write(root.join("user/repo/tools/justfile"), "")?;
+
+            let names: Vec<_> = justfiles_in(root)?
+                .into_iter()
+                .map(|j| j.repo_name)
+                .collect();
+            assert_eq!(names, ["user/repo", "user/repo/tools"]);
+
+            Ok(())
+        }
     }
 }
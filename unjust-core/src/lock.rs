@@ -0,0 +1,361 @@
+//! Lock files for unjust
+//!
+//! A lock is a file created exclusively, so only one process can hold it at
+//! a time. This works across machines sharing a directory too, where
+//! advisory `flock` locks may not. The file records the process holding it
+//! and the machine it runs on, with a token telling holders apart. A lock held by a process on this machine
+//! that has exited is stale and broken straight away, and one whose holder
+//! can't be checked is broken once it hasn't been released for a while.
+//!
+//! The store in the cache directory has one lock, taken by every operation
+//! that changes it. It's shared by everything in the process, so operations
+//! that use other operations don't wait for themselves.
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How old a lock has to be before it's taken to be stale, when its holder
+/// can't be checked
+const STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// Name of the store lock inside the cache directory
+const STORE_LOCK_FILE: &str = ".lock";
+
+/// How long to wait for another process to finish with the store
+const STORE_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long to wait between attempts to take a lock
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A held lock, released when dropped
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+    /// What this holder wrote into the lock file, to tell it from others
+    owner: String,
+}
+
+impl FileLock {
+    /// Take the lock at `path`, waiting up to `timeout` for another holder
+    /// to release it
+    pub fn acquire(path: &Path, timeout: Duration) -> io::Result<Self> {
+        let start = SystemTime::now();
+        loop {
+            if let Some(lock) = Self::try_acquire(path)? {
+                return Ok(lock);
+            }
+            if start.elapsed().unwrap_or_default() >= timeout {
+                return Err(timed_out(path));
+            }
+            thread::sleep(RETRY_INTERVAL);
+        }
+    }
+
+    /// Take the lock at `path` if nobody holds it, breaking it if it's stale
+    fn try_acquire(path: &Path) -> io::Result<Option<Self>> {
+        if let Some(lock) = Self::create(path)? {
+            return Ok(Some(lock));
+        }
+        match fs::read_to_string(path) {
+            Ok(seen) if is_stale(path) => {
+                break_stale(path, &seen);
+                Self::create(path)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Create the lock file, unless it exists already
+    fn create(path: &Path) -> io::Result<Option<Self>> {
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let owner = owner_line();
+        if let Err(e) = file.write_all(owner.as_bytes()) {
+            let _ = fs::remove_file(path);
+            return Err(e);
+        }
+        Ok(Some(Self {
+            path: path.to_path_buf(),
+            owner,
+        }))
+    }
+
+    /// Get the path of the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Only our own lock is removed, in case it was broken and taken over
+        if fs::read_to_string(&self.path).is_ok_and(|content| content == self.owner) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn timed_out(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::WouldBlock,
+        format!("Timed out waiting for lock: {}", path.display()),
+    )
+}
+
+/// A name no other holder will pick, for a lock's owner line and for moving
+/// a stale lock aside
+fn unique_token() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("{}-{nanos}", std::process::id())
+}
+
+/// The line a new holder writes into its lock file: its process id, the
+/// machine it runs on and a token telling it from other holders
+fn owner_line() -> String {
+    let host = host_name().unwrap_or_else(|| "-".to_string());
+    format!("{} {host} {}\n", std::process::id(), unique_token())
+}
+
+/// Break a stale lock that read as `seen`
+///
+/// The lock is moved aside first and checked again there, so a waiter that
+/// judged it stale late can't delete a lock someone has just taken. A lock
+/// moved aside by mistake is put back unless another has taken its place.
+fn break_stale(path: &Path, seen: &str) {
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(format!(".stale-{}", unique_token()));
+    let aside = PathBuf::from(aside);
+    if fs::rename(path, &aside).is_err() {
+        // Someone else moved it first
+        return;
+    }
+
+    let same = fs::read_to_string(&aside).is_ok_and(|content| content == seen);
+    if !same {
+        let _ = fs::hard_link(&aside, path);
+    }
+    let _ = fs::remove_file(&aside);
+}
+
+/// Get the name of this machine, where the system makes it available
+fn host_name() -> Option<String> {
+    let name = fs::read_to_string("/proc/sys/kernel/hostname").ok()?;
+    Some(name.trim().to_string()).filter(|n| !n.is_empty())
+}
+
+/// Whether the process holding a lock is still running, if that can be told
+///
+/// Only processes on this machine can be checked, and only where the system
+/// lists running processes in `/proc`.
+fn holder_running(path: &Path) -> Option<bool> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut parts = content.split_whitespace();
+    let pid: u32 = parts.next()?.parse().ok()?;
+    let host = parts.next()?;
+    if Some(host) != host_name().as_deref() || !Path::new("/proc/self").exists() {
+        return None;
+    }
+    Some(Path::new("/proc").join(pid.to_string()).exists())
+}
+
+/// Whether a lock file was left behind by a process that has exited, or
+/// long enough ago to be ignored
+fn is_stale(path: &Path) -> bool {
+    if let Some(running) = holder_running(path) {
+        return !running;
+    }
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| modified.elapsed().unwrap_or_default() >= STALE_AFTER)
+}
+
+/// Store locks held by this process, with how many guards share each
+static HELD: Mutex<BTreeMap<PathBuf, (FileLock, usize)>> = Mutex::new(BTreeMap::new());
+
+/// A held lock on the store in a cache directory, released when the last
+/// guard in the process is dropped
+#[derive(Debug)]
+pub struct StoreLock {
+    path: PathBuf,
+}
+
+impl StoreLock {
+    /// Take the store lock, waiting for other processes to release it
+    ///
+    /// The process's table of held locks is only locked for each attempt,
+    /// so threads taking a lock they already share don't queue behind one
+    /// that's waiting.
+    pub fn acquire(cache_dir: &Path) -> io::Result<Self> {
+        let path = cache_dir.join(STORE_LOCK_FILE);
+        let start = SystemTime::now();
+        loop {
+            {
+                let mut held = HELD.lock().unwrap_or_else(PoisonError::into_inner);
+                if let Some((_, guards)) = held.get_mut(&path) {
+                    *guards += 1;
+                    return Ok(Self { path });
+                }
+                if let Some(lock) = FileLock::try_acquire(&path)? {
+                    held.insert(path.clone(), (lock, 1));
+                    return Ok(Self { path });
+                }
+            }
+            if start.elapsed().unwrap_or_default() >= STORE_LOCK_TIMEOUT {
+                return Err(timed_out(&path));
+            }
+            thread::sleep(RETRY_INTERVAL);
+        }
+    }
+
+    /// Take the lock of the store a path is in, if it's in the cache
+    /// directory
+    ///
+    /// For writers that are given an entry rather than the cache directory.
+    pub(crate) fn acquire_for(path: &Path) -> io::Result<Option<Self>> {
+        match crate::get_cache_dir() {
+            Some(cache_dir) if path.starts_with(&cache_dir) => Self::acquire(&cache_dir).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let mut held = HELD.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((_, guards)) = held.get_mut(&self.path) {
+            *guards -= 1;
+            if *guards == 0 {
+                held.remove(&self.path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("lock");
+
+        let lock = FileLock::acquire(&path, Duration::ZERO)?;
+        assert!(path.exists());
+        let err = FileLock::acquire(&path, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        drop(lock);
+        assert!(!path.exists());
+        FileLock::acquire(&path, Duration::ZERO)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stale_lock_is_broken() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("lock");
+        let file = File::create(&path)?;
+        file.set_modified(SystemTime::now() - STALE_AFTER - Duration::from_secs(1))?;
+
+        let lock = FileLock::acquire(&path, Duration::ZERO)?;
+        assert_eq!(lock.path(), path);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_of_exited_process_is_broken() -> io::Result<()> {
+        let Some(host) = host_name() else {
+            return Ok(());
+        };
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("lock");
+
+        // A running process keeps its lock, however old
+        let file = File::create(&path)?;
+        fs::write(&path, format!("{} {host}\n", std::process::id()))?;
+        file.set_modified(SystemTime::now() - STALE_AFTER - Duration::from_secs(1))?;
+        assert!(!is_stale(&path));
+
+        let mut child = std::process::Command::new("true").spawn()?;
+        let pid = child.id();
+        child.wait()?;
+        fs::write(&path, format!("{pid} {host}\n"))?;
+        assert!(is_stale(&path));
+        FileLock::acquire(&path, Duration::ZERO)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_only_the_judged_lock_is_broken() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("lock");
+
+        // Taken by someone else since it was judged stale, so it's put back
+        fs::write(&path, "1 host fresh\n")?;
+        break_stale(&path, "1 host stale\n");
+        assert_eq!(fs::read_to_string(&path)?, "1 host fresh\n");
+
+        break_stale(&path, "1 host fresh\n");
+        assert!(!path.exists());
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 0);
+
+        // A holder whose lock was taken over leaves the new one alone
+        let lock = FileLock::acquire(&path, Duration::ZERO)?;
+        fs::write(&path, "1 host other\n")?;
+        drop(lock);
+        assert!(path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_lock_is_shared_within_a_process() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join(STORE_LOCK_FILE);
+
+        let outer = StoreLock::acquire(temp_dir.path())?;
+        let inner = StoreLock::acquire(temp_dir.path())?;
+        assert!(path.exists());
+        drop(inner);
+        assert!(path.exists());
+        drop(outer);
+        assert!(!path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_in_the_cache_lock_its_store() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path().join("cache");
+        fs::create_dir_all(cache_dir.join("user/repo"))?;
+
+        temp_env::with_var(crate::CACHE_DIR_ENV, Some(&cache_dir), || {
+            let lock = StoreLock::acquire_for(&cache_dir.join("user/repo/Justfile"))?;
+            assert!(lock.is_some());
+            assert!(cache_dir.join(STORE_LOCK_FILE).exists());
+            drop(lock);
+            assert!(!cache_dir.join(STORE_LOCK_FILE).exists());
+
+            assert!(StoreLock::acquire_for(temp_dir.path())?.is_none());
+            Ok(())
+        })
+    }
+}
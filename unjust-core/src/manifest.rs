@@ -0,0 +1,468 @@
+//! Per-entry metadata for unjust
+//!
+//! Each store entry can carry a small TOML manifest next to its Justfile
+//! recording where it came from and how it has been used. Only the flat
+//! subset of TOML the manifest needs (strings, integers and string arrays)
+//! is read and written.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::atomic::write_atomic;
+use crate::includes::justfile_files;
+use crate::justfile::Justfile;
+use crate::lock::StoreLock;
+use crate::objects::{object_path, read_tree, store_tree};
+use crate::revisions::{snapshot, store_root};
+
+/// Name of the manifest file inside an entry directory
+pub const MANIFEST_FILE: &str = ".unjust.toml";
+
+/// Metadata recorded for a store entry
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    /// Git remote URL the Justfile was taken from
+    pub source_remote: Option<String>,
+
+    /// Upstream repository id (username/repo), for forks
+    pub upstream: Option<String>,
+
+    /// When the entry was created, in seconds since the Unix epoch
+    pub created: Option<u64>,
+
+    /// When the entry's content last changed, in seconds since the Unix epoch
+    pub updated: Option<u64>,
+
+    /// Hash of the Justfile, every file it includes and the tags
+    pub content_hash: Option<String>,
+
+    /// Id of the tree object listing the entry's files, recorded with the
+    /// content hash
+    pub object_id: Option<String>,
+
+    /// When the tree object was recorded, in nanoseconds since the Unix
+    /// epoch
+    pub object_recorded: Option<u64>,
+
+    /// When the entry was last used, in seconds since the Unix epoch
+    pub last_used: Option<u64>,
+
+    /// Free-form tags
+    pub tags: Vec<String>,
+
+    /// Short description of the entry
+    pub description: Option<String>,
+}
+
+/// Get the current time in seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn nanos_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
+
+fn quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    format!("\"{escaped}\"")
+}
+
+/// Parse a basic TOML string at the start of `s`, returning it and the rest
+fn unquote(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &s[i + 2..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                other => value.push(other),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+fn parse_array(s: &str) -> Option<Vec<String>> {
+    let mut rest = s.strip_prefix('[')?.trim_start();
+    let mut items = Vec::new();
+    while !rest.starts_with(']') {
+        let (item, after) = unquote(rest)?;
+        items.push(item);
+        let after = after.trim_start();
+        rest = after.strip_prefix(',').unwrap_or(after).trim_start();
+    }
+    Some(items)
+}
+
+impl Manifest {
+    /// Serialise the manifest as TOML, leaving out unset fields
+    pub fn to_toml(&self) -> String {
+        let mut lines = Vec::new();
+        let strings = [
+            ("source_remote", &self.source_remote),
+            ("upstream", &self.upstream),
+            ("content_hash", &self.content_hash),
+            ("object_id", &self.object_id),
+            ("description", &self.description),
+        ];
+        for (key, value) in strings {
+            if let Some(value) = value {
+                lines.push(format!("{key} = {}", quote(value)));
+            }
+        }
+        let times = [
+            ("created", self.created),
+            ("updated", self.updated),
+            ("last_used", self.last_used),
+            ("object_recorded", self.object_recorded),
+        ];
+        for (key, value) in times {
+            if let Some(value) = value {
+                lines.push(format!("{key} = {value}"));
+            }
+        }
+        if !self.tags.is_empty() {
+            let tags: Vec<String> = self.tags.iter().map(|t| quote(t)).collect();
+            lines.push(format!("tags = [{}]", tags.join(", ")));
+        }
+
+        lines.iter().map(|l| format!("{l}\n")).collect()
+    }
+
+    /// Parse a manifest from TOML
+    ///
+    /// Unknown keys are ignored so newer manifests can still be read.
+    pub fn from_toml(content: &str) -> io::Result<Self> {
+        let mut manifest = Manifest::default();
+
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid manifest line {}: {line}", number + 1),
+                )
+            };
+            let (key, value) = line.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim();
+            let string = || unquote(value).map(|(s, _)| s).ok_or_else(invalid);
+            let time = || value.parse::<u64>().map_err(|_| invalid());
+
+            match key.trim() {
+                "source_remote" => manifest.source_remote = Some(string()?),
+                "upstream" => manifest.upstream = Some(string()?),
+                "content_hash" => manifest.content_hash = Some(string()?),
+                "object_id" => manifest.object_id = Some(string()?),
+                "description" => manifest.description = Some(string()?),
+                "created" => manifest.created = Some(time()?),
+                "updated" => manifest.updated = Some(time()?),
+                "last_used" => manifest.last_used = Some(time()?),
+                "object_recorded" => manifest.object_recorded = Some(time()?),
+                "tags" => manifest.tags = parse_array(value).ok_or_else(invalid)?,
+                _ => {}
+            }
+        }
+
+        Ok(manifest)
+    }
+}
+
+/// Get the path of the manifest for an entry directory
+pub fn manifest_path(entry_dir: &Path) -> PathBuf {
+    entry_dir.join(MANIFEST_FILE)
+}
+
+/// Read the manifest for an entry directory
+///
+/// An entry without a manifest gets an empty one.
+pub fn read_manifest(entry_dir: &Path) -> io::Result<Manifest> {
+    match fs::read_to_string(manifest_path(entry_dir)) {
+        Ok(content) => Manifest::from_toml(&content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Manifest::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write the manifest for an entry directory
+pub fn write_manifest(entry_dir: &Path, manifest: &Manifest) -> io::Result<()> {
+    write_atomic(&manifest_path(entry_dir), manifest.to_toml())
+}
+
+/// Read, change and write back the manifest for an entry directory
+pub fn update_manifest(
+    entry_dir: &Path,
+    change: impl FnOnce(&mut Manifest),
+) -> io::Result<Manifest> {
+    let _lock = StoreLock::acquire_for(entry_dir)?;
+    let mut manifest = read_manifest(entry_dir)?;
+    change(&mut manifest);
+    write_manifest(entry_dir, &manifest)?;
+    Ok(manifest)
+}
+
+/// Hash a Justfile together with every file it includes and its entry's
+/// tags
+///
+/// This is a 64-bit FNV-1a hash over each file's path relative to the
+/// Justfile and its content, then the tags in sorted order. Tags are synced
+/// with the files, so they count as content, while the rest of the manifest
+/// is local bookkeeping. It detects changes, it isn't meant to be collision
+/// resistant.
+pub fn content_hash(justfile: &Path) -> io::Result<String> {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let root = justfile.parent().unwrap_or(Path::new(""));
+    let mut hash = OFFSET;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+
+    for file in justfile_files(justfile)? {
+        let relative = file.strip_prefix(root).unwrap_or(&file);
+        feed(relative.to_string_lossy().as_bytes());
+        feed(&[0]);
+        feed(&fs::read(&file)?);
+        feed(&[0]);
+    }
+
+    let tags: BTreeSet<String> = read_manifest(root)?.tags.into_iter().collect();
+    if !tags.is_empty() {
+        feed(MANIFEST_FILE.as_bytes());
+        feed(&[0]);
+        for tag in tags {
+            feed(tag.as_bytes());
+            feed(&[0]);
+        }
+    }
+
+    Ok(format!("fnv1a64:{hash:016x}"))
+}
+
+/// Get the content hash of a stored Justfile, only reading its files if
+/// they may have changed since it was recorded
+///
+/// The content hash recorded with the entry's tree object is trusted while
+/// every file the tree lists is older than the recording and the tags still
+/// match, so an untouched entry costs a few stats rather than a read.
+/// Otherwise this is [`content_hash`].
+pub fn current_hash(justfile: &Justfile) -> io::Result<String> {
+    match recorded_hash(justfile) {
+        Some(hash) => Ok(hash),
+        None => content_hash(&justfile.path),
+    }
+}
+
+fn recorded_hash(justfile: &Justfile) -> Option<String> {
+    let cache_dir = store_root(justfile)?;
+    let entry = justfile.path.parent()?;
+    let manifest = read_manifest(entry).ok()?;
+    let recorded = manifest.object_recorded?;
+    let tree = read_tree(cache_dir, manifest.object_id.as_deref()?).ok()?;
+
+    let tags: BTreeSet<&String> = manifest.tags.iter().collect();
+    if !tags.iter().copied().eq(tree.tags.iter())
+        || !tree
+            .files
+            .iter()
+            .any(|(path, _)| entry.join(path) == justfile.path)
+    {
+        return None;
+    }
+    for (path, id) in &tree.files {
+        let metadata = fs::metadata(entry.join(path)).ok()?;
+        let modified = nanos_since_epoch(metadata.modified().ok()?);
+        // A whole second most likely means the filesystem only keeps
+        // seconds, so the change could have come up to a second later
+        let changed_by = match modified % 1_000_000_000 {
+            0 => modified.saturating_add(999_999_999),
+            _ => modified,
+        };
+        let size = fs::metadata(object_path(cache_dir, id)).ok()?.len();
+        if changed_by >= recorded || metadata.len() != size {
+            return None;
+        }
+    }
+    manifest.content_hash
+}
+
+/// Record that a stored Justfile's content was created or changed
+///
+/// Sets the creation time if it isn't set yet, refreshes the update time
+/// and content hash, and snapshots the content as a new revision. In the
+/// store the files are also added to the cache's objects, and the tree
+/// listing them is recorded in the manifest.
+pub fn record_content_change(justfile: &Justfile) -> io::Result<Manifest> {
+    let _lock = StoreLock::acquire_for(&justfile.path)?;
+    // Taken before reading, so a change made meanwhile isn't trusted later
+    let recorded = nanos_since_epoch(SystemTime::now());
+    let hash = content_hash(&justfile.path)?;
+    let time = now();
+    let manifest = justfile.manifest()?;
+    let created = manifest.created.is_none();
+    snapshot(justfile, if created { "Created" } else { "Updated" })?;
+    let tree = match store_root(justfile) {
+        Some(cache_dir) => Some(store_tree(
+            cache_dir,
+            entry_of(justfile)?,
+            &justfile_files(&justfile.path)?,
+            &manifest.tags,
+        )?),
+        None => None,
+    };
+    update_manifest(entry_of(justfile)?, |manifest| {
+        manifest.created.get_or_insert(time);
+        manifest.updated = Some(time);
+        manifest.content_hash = Some(hash);
+        manifest.object_recorded = tree.is_some().then_some(recorded);
+        manifest.object_id = tree;
+    })
+}
+
+/// Record that a stored Justfile was just used
+pub fn record_use(justfile: &Justfile) -> io::Result<Manifest> {
+    let _lock = StoreLock::acquire_for(&justfile.path)?;
+    let time = now();
+    update_manifest(entry_of(justfile)?, |manifest| {
+        manifest.last_used = Some(time);
+    })
+}
+
+fn entry_of(justfile: &Justfile) -> io::Result<&Path> {
+    justfile.path.parent().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Not a file path: {}", justfile.path.display()),
+        )
+    })
+}
+
+impl Justfile {
+    /// Read the manifest of the entry this Justfile is stored in
+    pub fn manifest(&self) -> io::Result<Manifest> {
+        read_manifest(entry_of(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::object_id;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_round_trip() -> io::Result<()> {
+        let manifest = Manifest {
+            source_remote: Some("git@github.com:user/repo.git".to_string()),
+            upstream: Some("org/repo".to_string()),
+            created: Some(1_700_000_000),
+            updated: Some(1_700_000_100),
+            content_hash: Some("fnv1a64:0123456789abcdef".to_string()),
+            object_id: Some("0123456789abcdef0123456789abcdef".to_string()),
+            object_recorded: Some(1_700_000_100_000_000_000),
+            last_used: Some(1_700_000_200),
+            tags: vec!["rust".to_string(), "with \"quotes\"".to_string()],
+            description: Some("Build\tand test\n".to_string()),
+        };
+
+        let toml = manifest.to_toml();
+        assert!(toml.contains("tags = [\"rust\", \"with \\\"quotes\\\"\"]\n"));
+        assert_eq!(Manifest::from_toml(&toml)?, manifest);
+        assert_eq!(Manifest::from_toml("")?, Manifest::default());
+        assert!(Manifest::from_toml("created = soon").is_err());
+        assert_eq!(
+            Manifest::from_toml("# comment\nfuture_key = 1\ntags = []\n")?,
+            Manifest::default()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_content_change_and_use() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("Justfile");
+        fs::write(&path, "build:\n")?;
+        let justfile = Justfile::new("user/repo".to_string(), path.clone());
+
+        assert_eq!(justfile.manifest()?, Manifest::default());
+
+        let first = record_content_change(&justfile)?;
+        assert!(first.created.is_some());
+        assert_eq!(first.content_hash, Some(content_hash(&path)?));
+
+        fs::write(&path, "test:\n")?;
+        let second = record_content_change(&justfile)?;
+        assert_eq!(second.created, first.created);
+        assert_ne!(second.content_hash, first.content_hash);
+
+        let used = record_use(&justfile)?;
+        assert!(used.last_used.is_some());
+        assert_eq!(justfile.manifest()?, used);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_current_hash_trusts_untouched_entries() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path().join("user/repo");
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("Justfile");
+        fs::write(&path, "build:\n")?;
+        let justfile = Justfile::new("user/repo".to_string(), path.clone());
+
+        let manifest = record_content_change(&justfile)?;
+        let tree = read_tree(temp_dir.path(), manifest.object_id.as_deref().unwrap())?;
+        assert_eq!(
+            tree.files,
+            vec![(PathBuf::from("Justfile"), object_id(b"build:\n"))]
+        );
+        let recorded = content_hash(&path)?;
+        assert_eq!(current_hash(&justfile)?, recorded);
+
+        // Same size and an old timestamp: the files aren't read again
+        fs::write(&path, "tests:\n")?;
+        let old = UNIX_EPOCH + std::time::Duration::new(1_000, 5);
+        fs::File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(old)?;
+        assert_eq!(current_hash(&justfile)?, recorded);
+
+        // Any other edit is
+        fs::write(&path, "test:\n")?;
+        assert_eq!(current_hash(&justfile)?, content_hash(&path)?);
+        assert_ne!(current_hash(&justfile)?, recorded);
+
+        // And so is a change of tags
+        record_content_change(&justfile)?;
+        update_manifest(&dir, |manifest| manifest.tags = vec!["rust".to_string()])?;
+        assert_eq!(current_hash(&justfile)?, content_hash(&path)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+//! Materialising stored Justfiles into working trees
+//!
+//! Some tools only look for a Justfile in the project directory, so a stored
+//! Justfile can be symlinked or copied into the working tree. Every file
+//! placed this way is recorded in the cache so it can be removed again
+//! without touching a Justfile the user put there themselves.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::atomic::write_atomic;
+use crate::compose::import_line;
+use crate::justfile::{Justfile, find_justfiles_in_dir, is_justfile_name};
+use crate::lock::StoreLock;
+
+/// Name of the file recording materialised Justfiles inside the cache directory
+const MATERIALISED_FILE: &str = ".materialised";
+
+/// How a stored Justfile is placed in a working tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMode {
+    /// A symlink pointing at the stored Justfile
+    Symlink,
+    /// An independent copy of the stored Justfile
+    Copy,
+    /// A small Justfile importing the stored one
+    ///
+    /// just resolves imports relative to the file it reads, so this is how
+    /// Justfiles made of several files (or composed from bases) are placed.
+    Import,
+}
+
+impl LinkMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            LinkMode::Symlink => "symlink",
+            LinkMode::Copy => "copy",
+            LinkMode::Import => "import",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "symlink" => Some(LinkMode::Symlink),
+            "copy" => Some(LinkMode::Copy),
+            "import" => Some(LinkMode::Import),
+            _ => None,
+        }
+    }
+}
+
+/// A Justfile that unjust placed in a working tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Materialised {
+    /// Path of the file in the working tree
+    pub target: PathBuf,
+
+    /// Path of the stored Justfile it came from
+    pub source: PathBuf,
+
+    /// How it was placed
+    pub mode: LinkMode,
+}
+
+impl Materialised {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}",
+            self.target.display(),
+            self.source.display(),
+            self.mode.as_str()
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.split('\t');
+        let target = PathBuf::from(parts.next()?);
+        let source = PathBuf::from(parts.next()?);
+        let mode = LinkMode::parse(parts.next()?)?;
+        Some(Self {
+            target,
+            source,
+            mode,
+        })
+    }
+}
+
+fn read_records(cache_dir: &Path) -> io::Result<Vec<Materialised>> {
+    match fs::read_to_string(cache_dir.join(MATERIALISED_FILE)) {
+        Ok(content) => Ok(content
+            .lines()
+            .filter_map(Materialised::from_line)
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_records(cache_dir: &Path, records: &[Materialised]) -> io::Result<()> {
+    let content: String = records.iter().map(|r| r.to_line() + "\n").collect();
+    write_atomic(&cache_dir.join(MATERIALISED_FILE), content)
+}
+
+/// Find the Justfile unjust materialised in a working tree directory, if any
+pub fn materialised_in(cache_dir: &Path, worktree: &Path) -> io::Result<Option<Materialised>> {
+    Ok(read_records(cache_dir)?
+        .into_iter()
+        .find(|r| r.target.parent() == Some(worktree)))
+}
+
+/// Content of a Justfile placed with [`LinkMode::Import`]
+fn import_stub(source: &Path) -> String {
+    format!(
+        "# Placed by unjust, edit the stored Justfile instead\n{}\n",
+        import_line(source)
+    )
+}
+
+#[cfg(unix)]
+fn symlink_file(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+fn symlink_file(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(source, target)
+}
+
+/// Fail if a placed copy or import stub has been edited since it was placed
+fn check_unchanged(record: &Materialised) -> io::Result<()> {
+    if record.mode == LinkMode::Symlink {
+        return Ok(());
+    }
+    let placed = fs::read(&record.target)?;
+    let stored = match record.mode {
+        LinkMode::Import => import_stub(&record.source).into_bytes(),
+        _ => fs::read(&record.source).unwrap_or_default(),
+    };
+    if placed != stored {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} has local changes", record.target.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Place a stored Justfile in a working tree directory
+///
+/// The file keeps the name it has in the store, or is called `Justfile` if
+/// that isn't a name just looks for. A Justfile previously placed
+/// by unjust is replaced, unless it's a copy with local changes and `force`
+/// isn't set, but any other Justfile in the directory is left alone and
+/// reported as an error. Returns the path of the placed file.
+pub fn link_justfile(
+    cache_dir: &Path,
+    justfile: &Justfile,
+    worktree: &Path,
+    mode: LinkMode,
+    force: bool,
+) -> io::Result<PathBuf> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let previous = materialised_in(cache_dir, worktree)?;
+
+    for existing in find_justfiles_in_dir(worktree)? {
+        if let Some(previous) = previous.as_ref().filter(|p| p.target == existing) {
+            if !force {
+                check_unchanged(previous)?;
+            }
+            fs::remove_file(&existing)?;
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("A Justfile already exists: {}", existing.display()),
+            ));
+        }
+    }
+
+    let name = justfile.path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Not a file path: {}", justfile.path.display()),
+        )
+    })?;
+    let target = if name.to_str().is_some_and(is_justfile_name) {
+        worktree.join(name)
+    } else {
+        worktree.join("Justfile")
+    };
+
+    match mode {
+        LinkMode::Symlink => symlink_file(&justfile.path, &target)?,
+        LinkMode::Copy => {
+            fs::copy(&justfile.path, &target)?;
+        }
+        LinkMode::Import => fs::write(&target, import_stub(&justfile.path))?,
+    }
+
+    let mut records = read_records(cache_dir)?;
+    records.retain(|r| r.target.parent() != Some(worktree));
+    records.push(Materialised {
+        target: target.clone(),
+        source: justfile.path.clone(),
+        mode,
+    });
+    write_records(cache_dir, &records)?;
+
+    Ok(target)
+}
+
+/// Remove the Justfile unjust placed in a working tree directory
+///
+/// A copy that has been edited since it was placed is kept unless `force`
+/// is set, so local changes aren't lost. Returns the removed path.
+pub fn unlink_justfile(cache_dir: &Path, worktree: &Path, force: bool) -> io::Result<PathBuf> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let Some(record) = materialised_in(cache_dir, worktree)? else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No linked Justfile in {}", worktree.display()),
+        ));
+    };
+
+    // Use symlink_metadata so a dangling symlink still counts as present
+    if fs::symlink_metadata(&record.target).is_ok() {
+        if !force {
+            check_unchanged(&record)?;
+        }
+        fs::remove_file(&record.target)?;
+    }
+
+    let mut records = read_records(cache_dir)?;
+    records.retain(|r| r != &record);
+    write_records(cache_dir, &records)?;
+
+    Ok(record.target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup() -> io::Result<(TempDir, PathBuf, PathBuf, Justfile)> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path().join("cache");
+        let worktree = temp_dir.path().join("project");
+        fs::create_dir_all(cache_dir.join("user/repo"))?;
+        fs::create_dir_all(&worktree)?;
+
+        let path = cache_dir.join("user/repo/justfile");
+        fs::write(&path, "# stored")?;
+        let justfile = Justfile::new("user/repo".to_string(), path);
+
+        Ok((temp_dir, cache_dir, worktree, justfile))
+    }
+
+    #[test]
+    fn test_link_and_unlink_symlink() -> io::Result<()> {
+        let (_temp_dir, cache_dir, worktree, justfile) = setup()?;
+
+        let target = link_justfile(&cache_dir, &justfile, &worktree, LinkMode::Symlink, false)?;
+        assert_eq!(target, worktree.join("justfile"));
+        assert_eq!(fs::read_link(&target)?, justfile.path);
+
+        // Linking again replaces our own link
+        link_justfile(&cache_dir, &justfile, &worktree, LinkMode::Symlink, false)?;
+
+        let removed = unlink_justfile(&cache_dir, &worktree, false)?;
+        assert_eq!(removed, target);
+        assert!(fs::symlink_metadata(&target).is_err());
+        assert!(materialised_in(&cache_dir, &worktree)?.is_none());
+        assert!(justfile.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_link_refuses_foreign_justfile() -> io::Result<()> {
+        let (_temp_dir, cache_dir, worktree, justfile) = setup()?;
+        fs::write(worktree.join("Justfile"), "# mine")?;
+
+        let err =
+            link_justfile(&cache_dir, &justfile, &worktree, LinkMode::Copy, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(worktree.join("Justfile"))?, "# mine");
+
+        let err = unlink_justfile(&cache_dir, &worktree, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlink_keeps_edited_copy() -> io::Result<()> {
+        let (_temp_dir, cache_dir, worktree, justfile) = setup()?;
+
+        let target = link_justfile(&cache_dir, &justfile, &worktree, LinkMode::Copy, false)?;
+        fs::write(&target, "# edited")?;
+
+        let err = unlink_justfile(&cache_dir, &worktree, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(target.exists());
+
+        unlink_justfile(&cache_dir, &worktree, true)?;
+        assert!(!target.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relink_keeps_edited_copy() -> io::Result<()> {
+        let (_temp_dir, cache_dir, worktree, justfile) = setup()?;
+
+        let target = link_justfile(&cache_dir, &justfile, &worktree, LinkMode::Copy, false)?;
+        fs::write(&target, "# edited")?;
+
+        let err =
+            link_justfile(&cache_dir, &justfile, &worktree, LinkMode::Symlink, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(fs::read_to_string(&target)?, "# edited");
+        assert_eq!(
+            materialised_in(&cache_dir, &worktree)?.unwrap().mode,
+            LinkMode::Copy
+        );
+
+        link_justfile(&cache_dir, &justfile, &worktree, LinkMode::Symlink, true)?;
+        assert_eq!(fs::read_link(&target)?, justfile.path);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_link_import_stub() -> io::Result<()> {
+        let (_temp_dir, cache_dir, worktree, justfile) = setup()?;
+        let composed = Justfile::new(
+            justfile.repo_name.clone(),
+            cache_dir.join("user/repo/.unjust-composed.just"),
+        );
+
+        let target = link_justfile(&cache_dir, &composed, &worktree, LinkMode::Import, false)?;
+        assert_eq!(target, worktree.join("Justfile"));
+        assert!(fs::read_to_string(&target)?.contains(&import_line(&composed.path)));
+
+        fs::write(&target, "# edited")?;
+        let err = unlink_justfile(&cache_dir, &worktree, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        unlink_justfile(&cache_dir, &worktree, true)?;
+
+        Ok(())
+    }
+}
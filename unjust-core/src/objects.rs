@@ -0,0 +1,294 @@
+//! Content-addressed object storage for unjust
+//!
+//! File contents are kept once in an object directory in the cache, named
+//! by a hash of their bytes, and revisions hard link to them rather than
+//! holding copies. Many entries share near-identical Justfiles and most
+//! snapshots change one file at most, so this keeps history cheap, and two
+//! revision files with the same object are equal without reading either.
+//!
+//! Objects are read-only, as a write through any link would change every
+//! revision sharing it. Live entries stay plain files for the same reason:
+//! they're edited in place. Where a hard link can't be made the content is
+//! copied instead.
+//!
+//! Entries reference their content through a tree object listing each
+//! file's object id and the entry's tags, recorded in the manifest whenever
+//! the content changes. Status trusts the content hash recorded alongside it
+//! while none of the listed files changed since, rather than reading them.
+//!
+//! Objects nothing refers to any more are only deleted when the trash is
+//! emptied, as trashed entries keep their revisions.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::atomic::write_atomic;
+use crate::lock::StoreLock;
+use crate::manifest::{MANIFEST_FILE, read_manifest};
+use crate::revisions::REVISIONS_DIR;
+
+/// Name of the object directory inside the cache directory
+pub const OBJECTS_DIR: &str = ".objects";
+
+/// Get the id of some content: a 128-bit FNV-1a hash of its bytes, in hex
+///
+/// Like the content hash this detects changes rather than resisting
+/// collisions, so stored objects are compared before they're shared.
+pub fn object_id(bytes: &[u8]) -> String {
+    const OFFSET: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+    const PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+    let hash = bytes.iter().fold(OFFSET, |hash, byte| {
+        (hash ^ u128::from(*byte)).wrapping_mul(PRIME)
+    });
+    format!("{hash:032x}")
+}
+
+/// Get the path an object is stored at, fanned out by its first two digits
+pub fn object_path(cache_dir: &Path, id: &str) -> PathBuf {
+    let (fan, rest) = id.split_at(2.min(id.len()));
+    cache_dir.join(OBJECTS_DIR).join(fan).join(rest)
+}
+
+/// Add content to the object store, returning the stored object's path
+///
+/// Returns `None` if a different object already has the same id, so the
+/// content can't be shared.
+pub fn store_object(cache_dir: &Path, bytes: &[u8]) -> io::Result<Option<PathBuf>> {
+    let path = object_path(cache_dir, &object_id(bytes));
+    match fs::read(&path) {
+        Ok(existing) => return Ok((existing == bytes).then_some(path)),
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        Err(_) => {}
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(&path, bytes)?;
+    let mut permissions = fs::metadata(&path)?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&path, permissions)?;
+    Ok(Some(path))
+}
+
+/// Put content at a new path, linking it to the stored object where
+/// possible and writing a copy otherwise
+pub fn place_object(cache_dir: &Path, bytes: &[u8], target: &Path) -> io::Result<()> {
+    if let Some(object) = store_object(cache_dir, bytes)?
+        && fs::hard_link(&object, target).is_ok()
+    {
+        return Ok(());
+    }
+    write_atomic(target, bytes)
+}
+
+/// The files and tags of an entry, as listed by a tree object
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tree {
+    /// Each file's path relative to the entry directory and its object id
+    pub files: Vec<(PathBuf, String)>,
+
+    /// The entry's tags, sorted and without duplicates
+    pub tags: Vec<String>,
+}
+
+impl Tree {
+    fn to_text(&self) -> String {
+        let files = self
+            .files
+            .iter()
+            .map(|(path, id)| format!("file\t{id}\t{}\n", path.to_string_lossy()));
+        let tags = self.tags.iter().map(|tag| format!("tag\t{tag}\n"));
+        files.chain(tags).collect()
+    }
+
+    fn from_text(text: &str) -> Option<Self> {
+        let mut tree = Tree::default();
+        for line in text.lines() {
+            match line.split_once('\t')? {
+                ("file", rest) => {
+                    let (id, path) = rest.split_once('\t')?;
+                    tree.files.push((PathBuf::from(path), id.to_string()));
+                }
+                ("tag", tag) => tree.tags.push(tag.to_string()),
+                _ => return None,
+            }
+        }
+        Some(tree)
+    }
+}
+
+/// Add an entry's files to the object store, along with a tree object
+/// listing them and the entry's tags, returning the tree's id
+pub fn store_tree(
+    cache_dir: &Path,
+    entry_dir: &Path,
+    files: &[PathBuf],
+    tags: &[String],
+) -> io::Result<String> {
+    let mut tree = Tree::default();
+    for file in files {
+        let bytes = fs::read(file)?;
+        store_object(cache_dir, &bytes)?;
+        let relative = file.strip_prefix(entry_dir).unwrap_or(file);
+        tree.files.push((relative.to_path_buf(), object_id(&bytes)));
+    }
+    tree.tags = tags.to_vec();
+    tree.tags.sort();
+    tree.tags.dedup();
+
+    let text = tree.to_text();
+    store_object(cache_dir, text.as_bytes())?;
+    Ok(object_id(text.as_bytes()))
+}
+
+/// Read a tree object
+pub fn read_tree(cache_dir: &Path, id: &str) -> io::Result<Tree> {
+    let text = fs::read_to_string(object_path(cache_dir, id))?;
+    Tree::from_text(&text)
+        .filter(|_| object_id(text.as_bytes()) == id)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Object {id} is not a tree"),
+            )
+        })
+}
+
+/// Delete every object that no revision and no entry's tree refers to
+///
+/// Revisions anywhere in the cache count, including those of trashed
+/// entries. Returns how many objects were deleted.
+pub fn collect_garbage(cache_dir: &Path) -> io::Result<usize> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let mut referenced = HashSet::new();
+    find_references(cache_dir, cache_dir, &mut referenced)?;
+
+    let mut deleted = 0;
+    let objects_dir = cache_dir.join(OBJECTS_DIR);
+    let fans = match fs::read_dir(&objects_dir) {
+        Ok(fans) => fans,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    for fan in fans {
+        let fan = fan?;
+        if !fan.file_type()?.is_dir() {
+            continue;
+        }
+        let prefix = fan.file_name().to_string_lossy().into_owned();
+        for object in fs::read_dir(fan.path())? {
+            let object = object?;
+            let id = format!("{prefix}{}", object.file_name().to_string_lossy());
+            if referenced.contains(&id) {
+                continue;
+            }
+            fs::remove_file(object.path())?;
+            deleted += 1;
+        }
+        // Only goes if nothing is left in it
+        let _ = fs::remove_dir(fan.path());
+    }
+    Ok(deleted)
+}
+
+/// Add the ids of the objects referred to from within a directory
+fn find_references(
+    cache_dir: &Path,
+    dir: &Path,
+    referenced: &mut HashSet<String>,
+) -> io::Result<()> {
+    for item in fs::read_dir(dir)? {
+        let item = item?;
+        let path = item.path();
+        let file_type = item.file_type()?;
+        if file_type.is_dir() {
+            if path == cache_dir.join(OBJECTS_DIR) {
+                continue;
+            }
+            if item.file_name() == REVISIONS_DIR {
+                add_file_ids(&path, referenced)?;
+            } else {
+                find_references(cache_dir, &path, referenced)?;
+            }
+        } else if file_type.is_file() && item.file_name() == MANIFEST_FILE {
+            let Some(id) = read_manifest(dir)?.object_id else {
+                continue;
+            };
+            // A missing or broken tree can't keep anything else
+            if let Ok(tree) = read_tree(cache_dir, &id) {
+                referenced.extend(tree.files.into_iter().map(|(_, id)| id));
+            }
+            referenced.insert(id);
+        }
+    }
+    Ok(())
+}
+
+/// Add the object ids of every file within a directory
+fn add_file_ids(dir: &Path, referenced: &mut HashSet<String>) -> io::Result<()> {
+    for item in fs::read_dir(dir)? {
+        let item = item?;
+        let file_type = item.file_type()?;
+        if file_type.is_dir() {
+            add_file_ids(&item.path(), referenced)?;
+        } else if file_type.is_file() {
+            referenced.insert(object_id(&fs::read(item.path())?));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_objects_are_shared_by_content() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+
+        assert_eq!(object_id(b"build:\n"), object_id(b"build:\n"));
+        assert_ne!(object_id(b"build:\n"), object_id(b"test:\n"));
+        assert_eq!(object_id(b"").len(), 32);
+
+        let first = cache_dir.join("first");
+        let second = cache_dir.join("second");
+        place_object(cache_dir, b"build:\n", &first)?;
+        place_object(cache_dir, b"build:\n", &second)?;
+        place_object(cache_dir, b"test:\n", &cache_dir.join("third"))?;
+        assert_eq!(fs::read(&second)?, b"build:\n");
+
+        let object = object_path(cache_dir, &object_id(b"build:\n"));
+        assert!(fs::metadata(&object)?.permissions().readonly());
+        let other = object_path(cache_dir, &object_id(b"test:\n"));
+        assert_ne!(object, other);
+        assert!(other.exists());
+        assert_eq!(fs::read_dir(object.parent().unwrap())?.count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_colliding_object_is_not_shared() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+
+        // Something else sitting where the object belongs
+        let object = object_path(cache_dir, &object_id(b"build:\n"));
+        fs::create_dir_all(object.parent().unwrap())?;
+        fs::write(&object, "other:\n")?;
+
+        assert_eq!(store_object(cache_dir, b"build:\n")?, None);
+        let target = cache_dir.join("Justfile");
+        place_object(cache_dir, b"build:\n", &target)?;
+        assert_eq!(fs::read(&target)?, b"build:\n");
+        assert_eq!(fs::read(&object)?, b"other:\n");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,337 @@
+//! Pinned revisions for unjust
+//!
+//! Shared Justfiles change underneath the projects using them, so a checkout
+//! can pin an entry to one of its revisions. `use` then runs that revision
+//! until the pin is moved on, along with its bases at the revisions pinned
+//! for them. Pins are kept in the cache directory, keyed by the root of the
+//! checkout they apply to, so they belong to one machine.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::atomic::{copy_atomic, write_atomic};
+use crate::compose::{resolve_layers, stored_layer};
+use crate::includes::justfile_files;
+use crate::justfile::{Justfile, justfile_in_dir};
+use crate::lock::StoreLock;
+use crate::manifest::content_hash;
+use crate::revisions::{revision_dir, revisions, snapshot};
+use crate::status::{entry_origins, read_sync_state};
+use crate::store::entry_dir;
+
+/// Name of the pins file inside the cache directory
+const PINS_FILE: &str = ".pins";
+
+/// Directory inside an entry that pinned revisions are checked out into
+pub(crate) const PINNED_DIR: &str = ".pinned";
+
+/// An entry pinned to a revision for a checkout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pin {
+    /// Root directory of the checkout
+    pub checkout: PathBuf,
+
+    /// Repository id of the pinned entry
+    pub repo: String,
+
+    /// Revision of the entry the checkout uses
+    pub revision: u32,
+
+    /// Content hash of that revision
+    pub hash: String,
+}
+
+impl Pin {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}",
+            self.checkout.display(),
+            self.repo,
+            self.revision,
+            self.hash
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.split('\t');
+        Some(Self {
+            checkout: PathBuf::from(parts.next()?),
+            repo: parts.next()?.to_string(),
+            revision: parts.next()?.parse().ok()?,
+            hash: parts.next()?.to_string(),
+        })
+    }
+}
+
+/// Read every pin
+pub fn read_pins(cache_dir: &Path) -> io::Result<Vec<Pin>> {
+    match fs::read_to_string(cache_dir.join(PINS_FILE)) {
+        Ok(content) => Ok(content.lines().filter_map(Pin::from_line).collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_pins(cache_dir: &Path, pins: &[Pin]) -> io::Result<()> {
+    let content: String = pins.iter().map(|p| p.to_line() + "\n").collect();
+    write_atomic(&cache_dir.join(PINS_FILE), content)
+}
+
+/// Find a checkout's pin of an entry
+pub fn find_pin(cache_dir: &Path, checkout: &Path, repo: &str) -> io::Result<Option<Pin>> {
+    Ok(read_pins(cache_dir)?
+        .into_iter()
+        .find(|p| p.checkout == checkout && p.repo == repo))
+}
+
+/// Pin a checkout's use of an entry to one of its revisions
+///
+/// Without a revision the entry's current content is pinned, taking a
+/// snapshot of it if it doesn't have one yet. Any existing pin is replaced.
+/// Bases the checkout hasn't pinned yet are pinned at their current
+/// content, so the whole of what runs stays fixed.
+pub fn pin_entry(
+    cache_dir: &Path,
+    checkout: &Path,
+    repo: &str,
+    revision: Option<u32>,
+) -> io::Result<Pin> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let pin = pin_one(cache_dir, checkout, repo, revision)?;
+
+    let layers = checkout_layers(cache_dir, checkout, &pinned_justfile(cache_dir, &pin)?)?;
+    for base in &layers[..layers.len() - 1] {
+        if find_pin(cache_dir, checkout, &base.repo_name)?.is_none() {
+            pin_one(cache_dir, checkout, &base.repo_name, None)?;
+        }
+    }
+    Ok(pin)
+}
+
+/// Pin one entry, without its bases
+fn pin_one(
+    cache_dir: &Path,
+    checkout: &Path,
+    repo: &str,
+    revision: Option<u32>,
+) -> io::Result<Pin> {
+    let dir = entry_dir(cache_dir, repo)?;
+    let revision = match revision {
+        Some(number) => revisions(&dir)?
+            .into_iter()
+            .find(|r| r.number == number)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No revision {number} of {repo}"),
+                )
+            })?,
+        None => {
+            let path = justfile_in_dir(&dir)?.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Justfile not found for repo: {repo}"),
+                )
+            })?;
+            let justfile = Justfile::new(repo.to_string(), path);
+            match snapshot(&justfile, "Pinned")? {
+                Some(revision) => revision,
+                // Already snapshotted, as the latest revision
+                None => revisions(&dir)?
+                    .pop()
+                    .ok_or_else(|| io::Error::other(format!("No revisions of {repo} to pin")))?,
+            }
+        }
+    };
+
+    let pin = Pin {
+        checkout: checkout.to_path_buf(),
+        repo: repo.to_string(),
+        revision: revision.number,
+        hash: revision.hash,
+    };
+    let mut pins = read_pins(cache_dir)?;
+    pins.retain(|p| !(p.checkout == pin.checkout && p.repo == pin.repo));
+    pins.push(pin.clone());
+    write_pins(cache_dir, &pins)?;
+    Ok(pin)
+}
+
+/// Remove a checkout's pin of an entry, returning it if there was one
+pub fn remove_pin(cache_dir: &Path, checkout: &Path, repo: &str) -> io::Result<Option<Pin>> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let mut pins = read_pins(cache_dir)?;
+    let Some(index) = pins
+        .iter()
+        .position(|p| p.checkout == checkout && p.repo == repo)
+    else {
+        return Ok(None);
+    };
+    let removed = pins.remove(index);
+    write_pins(cache_dir, &pins)?;
+    Ok(Some(removed))
+}
+
+/// Get the Justfile of a pinned revision
+///
+/// Revisions are kept as they were taken, so the revision's files are
+/// checked out into a directory of their own, where files generated for
+/// them can sit alongside.
+pub fn pinned_justfile(cache_dir: &Path, pin: &Pin) -> io::Result<Justfile> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let entry = entry_dir(cache_dir, &pin.repo)?;
+    let revision = revision_dir(&entry, pin.revision);
+    let source = justfile_in_dir(&revision)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "Pinned revision {} of {} no longer exists",
+                pin.revision, pin.repo
+            ),
+        )
+    })?;
+
+    let dir = entry.join(PINNED_DIR).join(pin.revision.to_string());
+    for file in justfile_files(&source)? {
+        let target = dir.join(file.strip_prefix(&revision).unwrap_or(&file));
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        copy_atomic(&file, &target)?;
+    }
+    let path = dir.join(source.strip_prefix(&revision).unwrap_or(&source));
+    Ok(Justfile::new(pin.repo.clone(), path))
+}
+
+/// Get the layers a checkout runs for a stored Justfile, lowest base first
+///
+/// The Justfile and each of its bases are taken at the revision the
+/// checkout has pinned for them, where there is one.
+pub fn checkout_layers(
+    cache_dir: &Path,
+    checkout: &Path,
+    justfile: &Justfile,
+) -> io::Result<Vec<Justfile>> {
+    let pins = read_pins(cache_dir)?;
+    let pinned = |repo: &str| {
+        pins.iter()
+            .find(|p| p.checkout == checkout && p.repo == repo)
+            .map(|pin| pinned_justfile(cache_dir, pin))
+            .transpose()
+    };
+    let top = match pinned(&justfile.repo_name)? {
+        Some(top) => top,
+        None => justfile.clone(),
+    };
+    resolve_layers(&top, |base| match pinned(base)? {
+        Some(layer) => Ok(Some(layer)),
+        None => stored_layer(cache_dir, base),
+    })
+}
+
+/// Whether an entry has moved on from a pin, in the store or on the remote
+/// it was last synced with
+pub fn pin_outdated(cache_dir: &Path, pin: &Pin) -> io::Result<bool> {
+    let stored = match justfile_in_dir(&entry_dir(cache_dir, &pin.repo)?)? {
+        Some(path) => Some(content_hash(&path)?),
+        None => None,
+    };
+    let remote = match entry_origins(cache_dir)?.get(&pin.repo) {
+        Some(origin) => read_sync_state(cache_dir, origin)?
+            .remove(&pin.repo)
+            .and_then(|r| r.remote),
+        None => None,
+    };
+    Ok([stored, remote]
+        .into_iter()
+        .flatten()
+        .any(|h| h != pin.hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pin_and_update() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+        let checkout = Path::new("/home/user/project");
+        let dir = cache_dir.join("user/repo");
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("Justfile"), "build:\n")?;
+
+        assert_eq!(find_pin(cache_dir, checkout, "user/repo")?, None);
+        let pin = pin_entry(cache_dir, checkout, "user/repo", None)?;
+        assert_eq!(pin.revision, 1);
+        assert_eq!(
+            find_pin(cache_dir, checkout, "user/repo")?,
+            Some(pin.clone())
+        );
+        assert!(!pin_outdated(cache_dir, &pin)?);
+
+        // The pinned revision is used after the entry changes
+        fs::write(dir.join("Justfile"), "test:\n")?;
+        assert!(pin_outdated(cache_dir, &pin)?);
+        let pinned = pinned_justfile(cache_dir, &pin)?;
+        assert_eq!(pinned.read_content()?, "build:\n");
+
+        // Pinning again moves the pin on
+        let updated = pin_entry(cache_dir, checkout, "user/repo", None)?;
+        assert_eq!(updated.revision, 2);
+        assert_eq!(read_pins(cache_dir)?, vec![updated]);
+        assert_eq!(
+            pin_entry(cache_dir, checkout, "user/repo", Some(1))?.hash,
+            pin.hash
+        );
+        assert!(pin_entry(cache_dir, checkout, "user/repo", Some(9)).is_err());
+
+        assert_eq!(
+            remove_pin(cache_dir, checkout, "user/repo")?.map(|p| p.revision),
+            Some(1)
+        );
+        assert_eq!(remove_pin(cache_dir, checkout, "user/repo")?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pin_covers_bases() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+        let checkout = Path::new("/home/user/project");
+        for (repo, content) in [
+            ("company/base", "lint:\n    echo base\n"),
+            ("user/child", "# unjust-base: company/base\nbuild:\n"),
+        ] {
+            fs::create_dir_all(cache_dir.join(repo))?;
+            fs::write(cache_dir.join(repo).join("Justfile"), content)?;
+        }
+
+        let pin = pin_entry(cache_dir, checkout, "user/child", None)?;
+        assert!(find_pin(cache_dir, checkout, "company/base")?.is_some());
+
+        // The base moving on doesn't change what the checkout runs
+        fs::write(
+            cache_dir.join("company/base/Justfile"),
+            "lint:\n    echo new\n",
+        )?;
+        let child = Justfile::new(
+            "user/child".to_string(),
+            cache_dir.join("user/child/Justfile"),
+        );
+        let layers = checkout_layers(cache_dir, checkout, &child)?;
+        assert_eq!(layers[0].read_content()?, "lint:\n    echo base\n");
+        let composed = crate::compose::compose_layers(&layers)?;
+        assert!(!composed.starts_with(revision_dir(&cache_dir.join("user/child"), 1)));
+
+        // Other checkouts use the store as it is
+        let elsewhere = checkout_layers(cache_dir, Path::new("/elsewhere"), &child)?;
+        assert_eq!(elsewhere[0].read_content()?, "lint:\n    echo new\n");
+        assert_eq!(pinned_justfile(cache_dir, &pin)?.path, layers[1].path);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,251 @@
+//! Sync planning
+//!
+//! Works out what a sync has to do for each entry from its status. The plan
+//! is what `sync --dry-run` prints and what a real sync carries out.
+
+use std::io;
+use std::path::Path;
+
+use crate::revisions::revisions;
+use crate::status::{EntryState, EntryStatus};
+use crate::store::entry_dir;
+
+/// What a sync does with one entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SyncAction {
+    /// Nothing, both sides match
+    UpToDate,
+    /// Fetch the remote copy into the store
+    Pull,
+    /// Send the local copy to the remote
+    Push,
+    /// Combine local and remote changes against the last-synced content
+    Merge,
+    /// Remove the entry from the store, as it was removed on the remote
+    DeleteLocal,
+    /// Remove the entry from the remote, as it was removed locally
+    DeleteRemote,
+    /// Both sides changed and can't be combined, so it's left alone
+    Conflict,
+    /// Keep local changes as a personal override, as the remote is read-only
+    Override,
+}
+
+impl SyncAction {
+    /// Stable name for the action, as used in plan output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SyncAction::UpToDate => "up-to-date",
+            SyncAction::Pull => "pull",
+            SyncAction::Push => "push",
+            SyncAction::Merge => "merge",
+            SyncAction::DeleteLocal => "delete-local",
+            SyncAction::DeleteRemote => "delete-remote",
+            SyncAction::Conflict => "conflict",
+            SyncAction::Override => "override",
+        }
+    }
+
+    /// The action to take instead when the remote is read-only
+    ///
+    /// Nothing is sent to the remote, so local changes and removals are
+    /// kept to this store. Remote changes are still pulled or merged in.
+    pub fn read_only(self) -> Self {
+        match self {
+            SyncAction::Push | SyncAction::DeleteRemote => SyncAction::Override,
+            action => action,
+        }
+    }
+}
+
+/// The planned action for one entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedSync {
+    /// The entry's status the plan was made from
+    pub status: EntryStatus,
+
+    /// What the sync will do
+    pub action: SyncAction,
+}
+
+/// Decide what to do with an entry
+///
+/// `can_merge` says whether the last-synced content is still available to
+/// merge against. With `force_push` the local side wins every conflict.
+pub fn plan_entry(status: &EntryStatus, can_merge: bool, force_push: bool) -> SyncAction {
+    let local = status.local.is_some();
+    let remote = status.record.remote.is_some();
+    match status.state {
+        EntryState::Clean => SyncAction::UpToDate,
+        EntryState::Modified | EntryState::New => SyncAction::Push,
+        EntryState::Deleted => SyncAction::DeleteRemote,
+        EntryState::RemoteUpdated if force_push => SyncAction::Push,
+        EntryState::RemoteUpdated if remote => SyncAction::Pull,
+        EntryState::RemoteUpdated => SyncAction::DeleteLocal,
+        EntryState::Conflicted if force_push && local => SyncAction::Push,
+        EntryState::Conflicted if force_push => SyncAction::DeleteRemote,
+        EntryState::Conflicted if local && remote && can_merge => SyncAction::Merge,
+        EntryState::Conflicted => SyncAction::Conflict,
+    }
+}
+
+/// Whether an entry still has a revision with its last-synced content
+fn has_base_revision(cache_dir: &Path, status: &EntryStatus) -> io::Result<bool> {
+    let Some(base) = &status.record.base else {
+        return Ok(false);
+    };
+    let dir = entry_dir(cache_dir, &status.repo)?;
+    Ok(revisions(&dir)?.iter().any(|r| &r.hash == base))
+}
+
+/// Plan a sync of the given entries with a remote
+pub fn plan_sync(
+    cache_dir: &Path,
+    statuses: Vec<EntryStatus>,
+    force_push: bool,
+    read_only: bool,
+) -> io::Result<Vec<PlannedSync>> {
+    statuses
+        .into_iter()
+        .map(|status| {
+            let can_merge =
+                status.state == EntryState::Conflicted && has_base_revision(cache_dir, &status)?;
+            let action = plan_entry(&status, can_merge, force_push);
+            let action = if read_only {
+                action.read_only()
+            } else {
+                action
+            };
+            Ok(PlannedSync { status, action })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::justfile::Justfile;
+    use crate::manifest::content_hash;
+    use crate::revisions::snapshot;
+    use crate::status::SyncRecord;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn status(
+        state: EntryState,
+        local: Option<&str>,
+        base: Option<&str>,
+        remote: Option<&str>,
+    ) -> EntryStatus {
+        EntryStatus {
+            repo: "user/repo".to_string(),
+            state,
+            local: local.map(str::to_string),
+            record: SyncRecord {
+                base: base.map(str::to_string),
+                remote: remote.map(str::to_string),
+            },
+        }
+    }
+
+    #[test]
+    fn test_plan_entry() {
+        use EntryState::*;
+        use SyncAction::*;
+        let cases = [
+            (
+                status(Clean, Some("a"), Some("a"), Some("a")),
+                false,
+                UpToDate,
+            ),
+            (
+                status(Modified, Some("b"), Some("a"), Some("a")),
+                false,
+                Push,
+            ),
+            (status(New, Some("a"), None, None), false, Push),
+            (
+                status(Deleted, None, Some("a"), Some("a")),
+                false,
+                DeleteRemote,
+            ),
+            (
+                status(RemoteUpdated, Some("a"), Some("a"), Some("b")),
+                false,
+                Pull,
+            ),
+            (
+                status(RemoteUpdated, Some("a"), Some("a"), None),
+                false,
+                DeleteLocal,
+            ),
+            (
+                status(Conflicted, Some("b"), Some("a"), Some("c")),
+                true,
+                Merge,
+            ),
+            (
+                status(Conflicted, Some("b"), Some("a"), Some("c")),
+                false,
+                Conflict,
+            ),
+            (
+                status(Conflicted, Some("b"), Some("a"), None),
+                true,
+                Conflict,
+            ),
+        ];
+        for (status, can_merge, expected) in cases {
+            assert_eq!(
+                plan_entry(&status, can_merge, false),
+                expected,
+                "{status:?}"
+            );
+        }
+
+        let conflicted = status(Conflicted, Some("b"), Some("a"), Some("c"));
+        assert_eq!(plan_entry(&conflicted, false, true), Push);
+        let deleted = status(Conflicted, None, Some("a"), Some("c"));
+        assert_eq!(plan_entry(&deleted, false, true), DeleteRemote);
+        let remote = status(RemoteUpdated, Some("a"), Some("a"), Some("b"));
+        assert_eq!(plan_entry(&remote, false, true), Push);
+
+        // Nothing is sent to read-only remotes
+        assert_eq!(Push.read_only(), Override);
+        assert_eq!(DeleteRemote.read_only(), Override);
+        assert_eq!(Pull.read_only(), Pull);
+        assert_eq!(Merge.read_only(), Merge);
+    }
+
+    #[test]
+    fn test_plan_sync_merges_with_base_revision() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+        let dir = cache_dir.join("user/repo");
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("Justfile");
+        fs::write(&path, "build:\n")?;
+        let justfile = Justfile::new("user/repo".to_string(), path.clone());
+        snapshot(&justfile, "Synced")?;
+        let base = content_hash(&path)?;
+        fs::write(&path, "test:\n")?;
+
+        let mergeable = status(
+            EntryState::Conflicted,
+            Some("local"),
+            Some(&base),
+            Some("remote"),
+        );
+        let unknown = status(
+            EntryState::Conflicted,
+            Some("local"),
+            Some("gone"),
+            Some("remote"),
+        );
+        let plan = plan_sync(cache_dir, vec![mergeable, unknown], false, false)?;
+        let actions: Vec<_> = plan.iter().map(|p| p.action).collect();
+        assert_eq!(actions, [SyncAction::Merge, SyncAction::Conflict]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+//! Remote storage configuration for unjust
+//!
+//! Each remote has a name, the kind of storage it is (which picks the sync
+//! backend) and a location whose meaning depends on the kind, such as a git
+//! URL. Remotes are kept in the cache directory in priority order, which is
+//! the order they were added in unless it's changed.
+//!
+//! A remote can be read-only, for subscribing to someone else's collection.
+//! Its entries are synced into the store but nothing is ever sent to it.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::atomic::write_atomic;
+use crate::lock::StoreLock;
+use crate::status::remove_sync_state;
+
+/// Name of the remotes file inside the cache directory
+const REMOTES_FILE: &str = ".remotes";
+
+/// A configured remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    /// Name the remote is referred to by
+    pub name: String,
+
+    /// Kind of storage, which picks the sync backend
+    pub kind: String,
+
+    /// Where the storage is, such as a git URL or a directory
+    pub location: String,
+
+    /// Whether changes are only ever taken from the remote
+    pub read_only: bool,
+}
+
+/// Marker at the end of a read-only remote's line
+const READ_ONLY: &str = "read-only";
+
+impl Remote {
+    fn to_line(&self) -> String {
+        let line = format!("{}\t{}\t{}", self.name, self.kind, self.location);
+        if self.read_only {
+            format!("{line}\t{READ_ONLY}")
+        } else {
+            line
+        }
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(4, '\t');
+        Some(Self {
+            name: parts.next()?.to_string(),
+            kind: parts.next()?.to_string(),
+            location: parts.next()?.to_string(),
+            read_only: parts.next() == Some(READ_ONLY),
+        })
+    }
+}
+
+/// Read the configured remotes, in priority order
+pub fn read_remotes(cache_dir: &Path) -> io::Result<Vec<Remote>> {
+    match fs::read_to_string(cache_dir.join(REMOTES_FILE)) {
+        Ok(content) => Ok(content.lines().filter_map(Remote::from_line).collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_remotes(cache_dir: &Path, remotes: &[Remote]) -> io::Result<()> {
+    let content: String = remotes.iter().map(|r| r.to_line() + "\n").collect();
+    write_atomic(&cache_dir.join(REMOTES_FILE), content)
+}
+
+/// Add a remote
+///
+/// Names are single words, so they can be used as directory names. Remotes
+/// go after any others, except that read-only ones are kept last.
+pub fn add_remote(cache_dir: &Path, remote: Remote) -> io::Result<()> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let valid_name = !remote.name.is_empty()
+        && !remote.name.starts_with('.')
+        && remote
+            .name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid_name {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid remote name: {}", remote.name),
+        ));
+    }
+    if remote.kind.contains(['\t', '\n']) || remote.location.contains(['\t', '\n']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Remote kind and location can't contain tabs or newlines",
+        ));
+    }
+
+    let mut remotes = read_remotes(cache_dir)?;
+    if remotes.iter().any(|r| r.name == remote.name) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Remote already exists: {}", remote.name),
+        ));
+    }
+    let index = if remote.read_only {
+        remotes.len()
+    } else {
+        remotes
+            .iter()
+            .position(|r| r.read_only)
+            .unwrap_or(remotes.len())
+    };
+    remotes.insert(index, remote);
+    write_remotes(cache_dir, &remotes)
+}
+
+fn position(remotes: &[Remote], name: &str) -> io::Result<usize> {
+    remotes
+        .iter()
+        .position(|r| r.name == name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("No remote named {name}")))
+}
+
+/// Remove a remote, returning it
+///
+/// The entries it provided stay in the store as local ones.
+pub fn remove_remote(cache_dir: &Path, name: &str) -> io::Result<Remote> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let mut remotes = read_remotes(cache_dir)?;
+    let removed = remotes.remove(position(&remotes, name)?);
+    write_remotes(cache_dir, &remotes)?;
+    remove_sync_state(cache_dir, name)?;
+    Ok(removed)
+}
+
+/// Move a remote to a position in the priority order, counting from 1
+///
+/// Positions past the end move it to the end.
+pub fn set_remote_priority(cache_dir: &Path, name: &str, priority: usize) -> io::Result<()> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    if priority == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Remote priorities count from 1",
+        ));
+    }
+    let mut remotes = read_remotes(cache_dir)?;
+    let remote = remotes.remove(position(&remotes, name)?);
+    remotes.insert((priority - 1).min(remotes.len()), remote);
+    write_remotes(cache_dir, &remotes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_add_and_remove_remotes() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+        assert!(read_remotes(cache_dir)?.is_empty());
+
+        let origin = Remote {
+            name: "origin".to_string(),
+            kind: "git".to_string(),
+            location: "git@example.com:user/justfiles.git".to_string(),
+            read_only: false,
+        };
+        let team = Remote {
+            name: "team".to_string(),
+            kind: "git".to_string(),
+            location: "/srv/team justfiles.git".to_string(),
+            read_only: true,
+        };
+        add_remote(cache_dir, team.clone())?;
+        add_remote(cache_dir, origin.clone())?;
+        assert_eq!(read_remotes(cache_dir)?, [origin.clone(), team.clone()]);
+
+        let err = add_remote(cache_dir, origin.clone()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        let bad = Remote {
+            name: "../up".to_string(),
+            ..origin.clone()
+        };
+        assert_eq!(
+            add_remote(cache_dir, bad).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+
+        set_remote_priority(cache_dir, "team", 1)?;
+        assert_eq!(read_remotes(cache_dir)?, [team.clone(), origin.clone()]);
+        set_remote_priority(cache_dir, "team", 5)?;
+        assert_eq!(read_remotes(cache_dir)?, [origin.clone(), team.clone()]);
+        assert!(set_remote_priority(cache_dir, "team", 0).is_err());
+        assert!(set_remote_priority(cache_dir, "missing", 1).is_err());
+
+        assert_eq!(remove_remote(cache_dir, "origin")?, origin);
+        assert_eq!(read_remotes(cache_dir)?, [team]);
+        assert_eq!(
+            remove_remote(cache_dir, "origin").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        Ok(())
+    }
+}
@@ -3,6 +3,9 @@
 use std::io;
 use std::path::{Path, PathBuf};
 
+use crate::git::remote_url;
+use crate::justfile::justfile_in_dir;
+
 /// Information about a repository
 #[derive(Debug, Clone)]
 pub struct Repository {
@@ -41,8 +44,14 @@ impl Repository {
     }
 
     /// Get the path to the Justfile in this repository
+    ///
+    /// An existing Justfile keeps whatever name it was stored under; when
+    /// there is none yet this is where a new `Justfile` would go.
     pub fn justfile_path(&self) -> PathBuf {
-        self.path.join("Justfile")
+        justfile_in_dir(&self.path)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| self.path.join("Justfile"))
     }
 
     /// Check if this repository has a Justfile
@@ -51,14 +60,48 @@ impl Repository {
     }
 }
 
-/// Detect the repository for the current directory
-#[allow(dead_code)]
-pub fn detect_current_repo() -> io::Result<Option<Repository>> {
-    // This is a simplified implementation - in a real implementation,
-    // we would use git commands to detect the current repo
+/// Detect the repository for a working directory from its git remotes
+///
+/// The `origin` remote gives the repository name and an `upstream` remote,
+/// if present, is recorded as the upstream. Returns `None` when `dir` is not
+/// in a git repository or has no recognisable `origin` remote.
+pub fn detect_current_repo(dir: &Path, cache_dir: &Path) -> io::Result<Option<Repository>> {
+    let Some(name) = remote_repo_name(dir, "origin")? else {
+        return Ok(None);
+    };
+
+    let path = cache_dir.join(&name);
+    let repo = match remote_repo_name(dir, "upstream")? {
+        Some(upstream) if upstream != name => Repository::with_upstream(name, path, upstream),
+        _ => Repository::new(name, path),
+    };
+
+    Ok(Some(repo))
+}
 
-    // For now, just return None to indicate we couldn't detect a repo
-    Ok(None)
+/// Get the `username/repo` name for a git remote, if it exists
+fn remote_repo_name(dir: &Path, remote: &str) -> io::Result<Option<String>> {
+    let url = remote_url(dir, remote)?;
+    Ok(url.as_deref().and_then(parse_remote_url))
+}
+
+/// Extract `username/repo` from a git remote URL
+///
+/// Handles scp-like (`git@host:user/repo.git`) and URL (`https://host/user/repo`)
+/// forms, keeping the last two path segments.
+pub fn parse_remote_url(url: &str) -> Option<String> {
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => url.split_once(':')?.1,
+    };
+
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let mut segments = path.rsplit('/').filter(|s| !s.is_empty());
+    let repo = segments.next()?;
+    let user = segments.next()?;
+
+    Some(format!("{user}/{repo}"))
 }
 
 /// Parse a repository name into a Repository object
@@ -111,4 +154,36 @@ mod tests {
 
         assert_eq!(repo.justfile_path(), path.join("Justfile"));
     }
+
+    #[test]
+    fn test_repository_justfile_path_preserves_name() -> io::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let path = temp_dir.path().to_path_buf();
+        std::fs::write(path.join(".justfile"), "# hidden")?;
+
+        let repo = Repository::new("user/repo".to_string(), path.clone());
+
+        assert!(repo.has_justfile());
+        assert_eq!(repo.justfile_path(), path.join(".justfile"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_remote_url() {
+        let cases = [
+            ("git@github.com:user/repo.git", Some("user/repo")),
+            ("https://github.com/user/repo", Some("user/repo")),
+            ("https://github.com/user/repo.git/", Some("user/repo")),
+            (
+                "ssh://git@example.com:2222/group/user/repo.git",
+                Some("user/repo"),
+            ),
+            ("/local/path/repo", None),
+            ("https://github.com/repo", None),
+        ];
+
+        for (url, expected) in cases {
+            assert_eq!(parse_remote_url(url).as_deref(), expected, "{url}");
+        }
+    }
 }
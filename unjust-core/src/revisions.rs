@@ -0,0 +1,561 @@
+//! Revision history for store entries
+//!
+//! Whenever an entry's content changes a snapshot of its files is kept in a
+//! hidden directory inside the entry, so a bad edit can be inspected and
+//! rolled back. Snapshots travel with the entry when it is renamed or
+//! removed.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::atomic::{copy_atomic, write_atomic};
+use crate::includes::justfile_files;
+use crate::justfile::{Justfile, is_justfile_name, justfile_in_dir};
+use crate::lock::StoreLock;
+use crate::manifest::{MANIFEST_FILE, content_hash, manifest_path, now, record_content_change};
+use crate::objects::place_object;
+use crate::pins::PINNED_DIR;
+use crate::store::entry_dir;
+
+/// Name of the directory holding an entry's revisions
+pub(crate) const REVISIONS_DIR: &str = ".revisions";
+
+/// Name of the revision log inside the revisions directory
+const LOG_FILE: &str = "log";
+
+/// A snapshot of a store entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    /// Revision number, counting up from 1
+    pub number: u32,
+
+    /// When it was taken, in seconds since the Unix epoch
+    pub time: u64,
+
+    /// Content hash of the entry at this revision
+    pub hash: String,
+
+    /// What changed
+    pub message: String,
+}
+
+impl Revision {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}",
+            self.number, self.time, self.hash, self.message
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(4, '\t');
+        Some(Self {
+            number: parts.next()?.parse().ok()?,
+            time: parts.next()?.parse().ok()?,
+            hash: parts.next()?.to_string(),
+            message: parts.next().unwrap_or_default().to_string(),
+        })
+    }
+}
+
+fn entry_of(justfile: &Justfile) -> &Path {
+    justfile.path.parent().unwrap_or(Path::new(""))
+}
+
+/// Get the cache directory a stored Justfile's entry is in, if it's laid
+/// out like the store
+pub(crate) fn store_root(justfile: &Justfile) -> Option<&Path> {
+    let entry = entry_of(justfile);
+    let relative = entry_dir(Path::new(""), &justfile.repo_name).ok()?;
+    if relative.as_os_str().is_empty() || !entry.ends_with(&relative) {
+        return None;
+    }
+    relative.components().try_fold(entry, |dir, _| dir.parent())
+}
+
+/// Get the directory holding the files of one of an entry's revisions
+pub fn revision_dir(entry_dir: &Path, number: u32) -> PathBuf {
+    entry_dir.join(REVISIONS_DIR).join(number.to_string())
+}
+
+/// Read an entry's revisions, oldest first
+pub fn revisions(entry_dir: &Path) -> io::Result<Vec<Revision>> {
+    match fs::read_to_string(entry_dir.join(REVISIONS_DIR).join(LOG_FILE)) {
+        Ok(content) => Ok(content.lines().filter_map(Revision::from_line).collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Look up one of an entry's revisions
+fn find_revision(entry_dir: &Path, number: u32) -> io::Result<Revision> {
+    revisions(entry_dir)?
+        .into_iter()
+        .find(|r| r.number == number)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No revision {number} for {}", entry_dir.display()),
+            )
+        })
+}
+
+/// Snapshot a stored Justfile, the files it includes and its manifest
+///
+/// Nothing is recorded if the content matches the latest revision. Returns
+/// the new revision, if one was taken. In the store, the revision's files
+/// are linked to the cache's objects rather than copied.
+pub fn snapshot(justfile: &Justfile, message: &str) -> io::Result<Option<Revision>> {
+    let _lock = StoreLock::acquire_for(&justfile.path)?;
+    let entry = entry_of(justfile);
+    let hash = content_hash(&justfile.path)?;
+    let existing = revisions(entry)?;
+    if existing.last().is_some_and(|r| r.hash == hash) {
+        return Ok(None);
+    }
+
+    let number = existing.last().map_or(1, |r| r.number + 1);
+    let dir = revision_dir(entry, number);
+    for file in justfile_files(&justfile.path)? {
+        let relative = file.strip_prefix(entry).unwrap_or(&file);
+        let target = dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match store_root(justfile) {
+            Some(cache_dir) => place_object(cache_dir, &fs::read(&file)?, &target)?,
+            None => copy_atomic(&file, &target)?,
+        }
+    }
+    // Tags are part of the content, so the manifest goes along for merges
+    let manifest = manifest_path(entry);
+    if manifest.exists() {
+        copy_atomic(&manifest, &dir.join(MANIFEST_FILE))?;
+    }
+
+    let revision = Revision {
+        number,
+        time: now(),
+        hash,
+        message: message.replace(['\t', '\n'], " "),
+    };
+    let mut log: String = existing.iter().map(|r| r.to_line() + "\n").collect();
+    log.push_str(&revision.to_line());
+    log.push('\n');
+    write_atomic(&entry.join(REVISIONS_DIR).join(LOG_FILE), log)?;
+
+    Ok(Some(revision))
+}
+
+/// Whether a name in an entry is one of unjust's own files rather than part
+/// of the Justfile: the manifest, revisions, pinned checkouts and generated
+/// layers
+fn is_unjust_name(name: &str) -> bool {
+    [MANIFEST_FILE, REVISIONS_DIR, PINNED_DIR].contains(&name)
+        || (name.starts_with(".unjust-") && name.ends_with(".just"))
+}
+
+/// List the files in a revision, relative to the entry
+///
+/// unjust's own files are left out, so a revision lists just the Justfile
+/// and the files it includes.
+fn revision_files(entry_dir: &Path, number: u32) -> io::Result<Vec<PathBuf>> {
+    fn collect(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if is_unjust_name(&entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                collect(root, &path, files)?;
+            } else {
+                files.push(path.strip_prefix(root).unwrap_or(&path).to_path_buf());
+            }
+        }
+        Ok(())
+    }
+
+    let dir = revision_dir(entry_dir, number);
+    let mut files = Vec::new();
+    collect(&dir, &dir, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+/// List a stored Justfile's current files, relative to the entry
+fn current_files(justfile: &Justfile) -> io::Result<Vec<PathBuf>> {
+    let entry = entry_of(justfile);
+    let mut files: Vec<PathBuf> = justfile_files(&justfile.path)?
+        .into_iter()
+        .map(|f| f.strip_prefix(entry).unwrap_or(&f).to_path_buf())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Put a stored Justfile's files back as they were at a revision
+///
+/// The current files are snapshotted first, so the restore can itself be
+/// undone, and the restored state is recorded as a new revision. Returns
+/// the path of the restored Justfile.
+pub fn restore_revision(justfile: &Justfile, number: u32) -> io::Result<PathBuf> {
+    let _lock = StoreLock::acquire_for(&justfile.path)?;
+    let entry = entry_of(justfile).to_path_buf();
+    find_revision(&entry, number)?;
+
+    // Check the revision can be restored before touching the current files
+    let restored = revision_files(&entry, number)?;
+    let has_justfile = restored.iter().any(|file| {
+        file.parent() == Some(Path::new("")) && file.to_str().is_some_and(is_justfile_name)
+    });
+    if !has_justfile {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Revision {number} has no Justfile"),
+        ));
+    }
+    snapshot(justfile, "Before restoring")?;
+
+    for file in current_files(justfile)? {
+        fs::remove_file(entry.join(file))?;
+    }
+    for file in &restored {
+        let target = entry.join(file);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        copy_atomic(&revision_dir(&entry, number).join(file), &target)?;
+    }
+
+    // The snapshot's Justfile may have had a different name
+    let path = justfile_in_dir(&entry)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Revision {number} has no Justfile"),
+        )
+    })?;
+    let restored_justfile = Justfile::new(justfile.repo_name.clone(), path.clone());
+    snapshot(&restored_justfile, &format!("Restored revision {number}"))?;
+    record_content_change(&restored_justfile)?;
+
+    Ok(path)
+}
+
+/// Compare a revision of a stored Justfile with its current files, as a
+/// unified diff
+///
+/// Without a revision number the latest revision that differs from the
+/// current files is used, which shows the most recent change.
+pub fn diff_revision(justfile: &Justfile, number: Option<u32>) -> io::Result<String> {
+    let entry = entry_of(justfile).to_path_buf();
+    let number = match number {
+        Some(number) => find_revision(&entry, number)?.number,
+        None => {
+            let hash = content_hash(&justfile.path)?;
+            let all = revisions(&entry)?;
+            let differing = all.iter().rev().find(|r| r.hash != hash);
+            match differing {
+                Some(revision) => revision.number,
+                None => return Ok(String::new()),
+            }
+        }
+    };
+
+    let old_files = revision_files(&entry, number)?;
+    let new_files = current_files(justfile)?;
+    let mut names: Vec<&PathBuf> = old_files.iter().chain(&new_files).collect();
+    names.sort();
+    names.dedup();
+
+    let mut output = String::new();
+    for name in names {
+        let read = |path: PathBuf| match fs::read_to_string(path) {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(e),
+        };
+        let old = if old_files.contains(name) {
+            read(revision_dir(&entry, number).join(name))?
+        } else {
+            String::new()
+        };
+        let new = if new_files.contains(name) {
+            read(entry.join(name))?
+        } else {
+            String::new()
+        };
+        if old != new {
+            output.push_str(&format!(
+                "--- {} (revision {number})\n+++ {} (current)\n",
+                name.display(),
+                name.display()
+            ));
+            output.push_str(&diff_lines(&old, &new, 3));
+        }
+    }
+
+    Ok(output)
+}
+
+/// Longest common subsequence lengths of every pair of suffixes, filled
+/// from the end
+fn lcs_table(old: &[&str], new: &[&str]) -> Vec<Vec<usize>> {
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    lcs
+}
+
+/// Match each line of `old` to the line of `new` it was kept as, if any
+fn matching_lines(old: &[&str], new: &[&str]) -> Vec<Option<usize>> {
+    let lcs = lcs_table(old, new);
+    let mut matches = vec![None; old.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            matches[i] = Some(j);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+/// Merge two texts that were both changed from a common base, line by line
+///
+/// A change made on only one side is kept, as is the same change made on
+/// both. Returns `None` if the two sides changed the same lines differently.
+pub fn merge_lines(base: &str, local: &str, remote: &str) -> Option<String> {
+    let base: Vec<&str> = base.lines().collect();
+    let local: Vec<&str> = local.lines().collect();
+    let remote: Vec<&str> = remote.lines().collect();
+    let to_local = matching_lines(&base, &local);
+    let to_remote = matching_lines(&base, &remote);
+
+    // Base lines kept on both sides split the texts into chunks, and each
+    // chunk can only have been changed on one side
+    let mut merged = Vec::new();
+    let (mut i, mut l, mut r) = (0, 0, 0);
+    loop {
+        let stable = (i..base.len()).find_map(|k| Some((k, to_local[k]?, to_remote[k]?)));
+        let (k, next_l, next_r) = stable.unwrap_or((base.len(), local.len(), remote.len()));
+        let (ours, theirs) = (&local[l..next_l], &remote[r..next_r]);
+        if ours == &base[i..k] {
+            merged.extend_from_slice(theirs);
+        } else if theirs == &base[i..k] || ours == theirs {
+            merged.extend_from_slice(ours);
+        } else {
+            return None;
+        }
+
+        if k == base.len() {
+            break;
+        }
+        merged.push(base[k]);
+        (i, l, r) = (k + 1, next_l + 1, next_r + 1);
+    }
+
+    Some(merged.iter().map(|line| format!("{line}\n")).collect())
+}
+
+/// Diff two texts line by line, giving unified diff hunks with `context`
+/// lines around each change
+pub fn diff_lines(old: &str, new: &str, context: usize) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let lcs = lcs_table(&old, &new);
+
+    // Walk the table into a list of (tag, old line, new line, text)
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push((' ', i, j, old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', i, j, old[i]));
+            i += 1;
+        } else {
+            ops.push(('+', i, j, new[j]));
+            j += 1;
+        }
+    }
+
+    // Group changes into hunks, merging those whose context overlaps
+    let changed: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != ' ').collect();
+    let mut output = String::new();
+    let mut k = 0;
+    while k < changed.len() {
+        let start = changed[k].saturating_sub(context);
+        let mut end = changed[k];
+        while k < changed.len() && changed[k] <= end + 2 * context + 1 {
+            end = changed[k];
+            k += 1;
+        }
+        let end = (end + context + 1).min(ops.len());
+        let hunk = &ops[start..end];
+
+        let old_count = hunk.iter().filter(|op| op.0 != '+').count();
+        let new_count = hunk.iter().filter(|op| op.0 != '-').count();
+        let old_start = hunk[0].1 + usize::from(old_count > 0);
+        let new_start = hunk[0].2 + usize::from(new_count > 0);
+        output.push_str(&format!(
+            "@@ -{old_start},{old_count} +{new_start},{new_count} @@\n"
+        ));
+        for (tag, _, _, text) in hunk {
+            output.push_str(&format!("{tag}{text}\n"));
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{OBJECTS_DIR, object_id, object_path};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_diff_lines() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        assert_eq!(
+            diff_lines(old, new, 1),
+            "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -10,1 +10,2 @@\n j\n+k\n"
+        );
+        assert_eq!(diff_lines("same\n", "same\n", 3), "");
+        assert_eq!(diff_lines("", "new\n", 3), "@@ -0,0 +1,1 @@\n+new\n");
+    }
+
+    #[test]
+    fn test_merge_lines() {
+        let base = "a\nb\nc\nd\ne\n";
+        assert_eq!(
+            merge_lines(base, "A\nb\nc\nd\ne\n", "a\nb\nc\nd\nE\nf\n").as_deref(),
+            Some("A\nb\nc\nd\nE\nf\n")
+        );
+        assert_eq!(
+            merge_lines(base, "a\nc\nd\ne\n", "a\nc\nd\ne\n").as_deref(),
+            Some("a\nc\nd\ne\n")
+        );
+        assert_eq!(merge_lines(base, base, base).as_deref(), Some(base));
+        assert_eq!(
+            merge_lines(base, "a\nB\nc\nd\ne\n", "a\nX\nc\nd\ne\n"),
+            None
+        );
+        assert_eq!(merge_lines("", "local\n", "remote\n"), None);
+    }
+
+    #[test]
+    fn test_snapshot_diff_and_restore() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let entry = temp_dir.path().join("user/repo");
+        fs::create_dir_all(&entry)?;
+        let path = entry.join("Justfile");
+        let justfile = Justfile::new("user/repo".to_string(), path.clone());
+
+        fs::write(&path, "import 'common.just'\nbuild:\n    cargo build\n")?;
+        fs::write(entry.join("common.just"), "fmt:\n")?;
+        assert_eq!(snapshot(&justfile, "Created")?.unwrap().number, 1);
+        assert!(snapshot(&justfile, "Unchanged")?.is_none());
+        assert!(entry.join(".revisions/1/common.just").exists());
+
+        fs::write(&path, "build:\n    cargo build --release\n")?;
+        snapshot(&justfile, "Broke it")?;
+        assert_eq!(revisions(&entry)?.len(), 2);
+
+        // Without a revision the last change is shown
+        let diff = diff_revision(&justfile, None)?;
+        assert!(diff.contains("-    cargo build\n+    cargo build --release\n"));
+        assert!(diff_revision(&justfile, Some(2))?.is_empty());
+        fs::write(entry.join(".revisions/2/.unjust-composed.just"), "")?;
+        assert!(diff_revision(&justfile, Some(2))?.is_empty());
+
+        restore_revision(&justfile, 1)?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "import 'common.just'\nbuild:\n    cargo build\n"
+        );
+        let log = revisions(&entry)?;
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[2].message, "Restored revision 1");
+        assert_eq!(log[2].hash, log[0].hash);
+
+        assert!(restore_revision(&justfile, 9).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_hidden_justfile() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let entry = temp_dir.path().join("user/hid");
+        fs::create_dir_all(&entry)?;
+        let path = entry.join(".justfile");
+        let justfile = Justfile::new("user/hid".to_string(), path.clone());
+
+        fs::write(&path, "build:\n")?;
+        snapshot(&justfile, "Created")?;
+        fs::write(&path, "test:\n")?;
+        snapshot(&justfile, "Edited")?;
+
+        assert!(diff_revision(&justfile, Some(1))?.contains("-build:\n+test:\n"));
+        restore_revision(&justfile, 1)?;
+        assert_eq!(fs::read_to_string(&path)?, "build:\n");
+
+        // A revision without a Justfile is refused before anything changes
+        fs::remove_file(entry.join(".revisions/2/.justfile"))?;
+        let err = restore_revision(&justfile, 2).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(fs::read_to_string(&path)?, "build:\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshots_share_objects() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+        for repo in ["user/one", "user/two"] {
+            let entry = cache_dir.join(repo);
+            fs::create_dir_all(&entry)?;
+            fs::write(entry.join("Justfile"), "build:\n")?;
+            snapshot(
+                &Justfile::new(repo.to_string(), entry.join("Justfile")),
+                "Created",
+            )?;
+        }
+
+        // One object backs both revisions, which can't be edited through
+        let object = object_path(cache_dir, &object_id(b"build:\n"));
+        assert_eq!(fs::read_dir(cache_dir.join(OBJECTS_DIR))?.count(), 1);
+        assert_eq!(fs::read_dir(object.parent().unwrap())?.count(), 1);
+        let revision = cache_dir.join("user/two/.revisions/1/Justfile");
+        assert!(fs::metadata(&revision)?.permissions().readonly());
+        assert_eq!(fs::read(&revision)?, fs::read(&object)?);
+
+        // Restoring gives back a file that can be edited
+        let justfile = Justfile::new("user/two".to_string(), cache_dir.join("user/two/Justfile"));
+        fs::write(&justfile.path, "test:\n")?;
+        restore_revision(&justfile, 1)?;
+        fs::write(&justfile.path, "lint:\n")?;
+        assert_eq!(fs::read(&object)?, b"build:\n");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,501 @@
+//! Sync status of store entries
+//!
+//! Each entry's content hash is recorded when it is synced, along with the
+//! hash last seen on the remote. Comparing the store against those shows
+//! what a sync would have to do.
+//!
+//! Every remote has its own sync state. An entry belongs to the remote it
+//! was last synced with, and when several remotes have the same entry the
+//! one earliest in the priority order provides it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::atomic::write_atomic;
+use crate::justfile::Justfile;
+use crate::lock::StoreLock;
+use crate::manifest::current_hash;
+use crate::remotes::{Remote, read_remotes};
+
+/// Name of the directory inside the cache holding each remote's sync state
+const SYNC_STATE_DIR: &str = ".sync-states";
+
+/// What is known about an entry as of the last sync
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncRecord {
+    /// Content hash both sides agreed on at the last sync
+    pub base: Option<String>,
+
+    /// Content hash last seen on the remote, if it still has the entry
+    pub remote: Option<String>,
+}
+
+/// Sync state for every entry, keyed by repository id
+pub type SyncState = BTreeMap<String, SyncRecord>;
+
+fn field(value: &Option<String>) -> &str {
+    value.as_deref().unwrap_or("-")
+}
+
+fn parse_field(value: &str) -> Option<String> {
+    (value != "-").then(|| value.to_string())
+}
+
+fn sync_state_path(cache_dir: &Path, remote: &str) -> PathBuf {
+    cache_dir.join(SYNC_STATE_DIR).join(remote)
+}
+
+/// Read the sync state recorded for a remote
+pub fn read_sync_state(cache_dir: &Path, remote: &str) -> io::Result<SyncState> {
+    let content = match fs::read_to_string(sync_state_path(cache_dir, remote)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(SyncState::new()),
+        Err(e) => return Err(e),
+    };
+
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            let repo = parts.next()?.to_string();
+            let base = parse_field(parts.next()?);
+            let remote = parse_field(parts.next()?);
+            Some((repo, SyncRecord { base, remote }))
+        })
+        .collect())
+}
+
+/// Write a remote's sync state, dropping entries neither side has any more
+pub fn write_sync_state(cache_dir: &Path, remote: &str, state: &SyncState) -> io::Result<()> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let content: String = state
+        .iter()
+        .filter(|(_, r)| r.base.is_some() || r.remote.is_some())
+        .map(|(repo, r)| format!("{repo}\t{}\t{}\n", field(&r.base), field(&r.remote)))
+        .collect();
+    let path = sync_state_path(cache_dir, remote);
+    fs::create_dir_all(cache_dir.join(SYNC_STATE_DIR))?;
+    write_atomic(&path, content)
+}
+
+/// Forget a remote's sync state, so the entries it provided become local
+pub(crate) fn remove_sync_state(cache_dir: &Path, remote: &str) -> io::Result<()> {
+    match fs::remove_file(sync_state_path(cache_dir, remote)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Work out which remote each synced entry came from
+///
+/// An entry comes from the remote it was last synced with. Entries that
+/// were never synced aren't included.
+pub fn entry_origins(cache_dir: &Path) -> io::Result<BTreeMap<String, String>> {
+    let mut origins = BTreeMap::new();
+    for remote in read_remotes(cache_dir)? {
+        for (repo, record) in read_sync_state(cache_dir, &remote.name)? {
+            if record.base.is_some() {
+                origins.entry(repo).or_insert_with(|| remote.name.clone());
+            }
+        }
+    }
+    Ok(origins)
+}
+
+/// Stop other remotes providing the entries a remote has now synced
+///
+/// Their records of what's on the remote are kept, so the entries show up
+/// again if this remote stops providing them.
+pub fn release_entries(cache_dir: &Path, remote: &str, state: &SyncState) -> io::Result<()> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    for other in read_remotes(cache_dir)? {
+        if other.name == remote {
+            continue;
+        }
+        let mut other_state = read_sync_state(cache_dir, &other.name)?;
+        let mut released = false;
+        for (repo, record) in other_state.iter_mut() {
+            if record.base.is_some() && state.get(repo).is_some_and(|r| r.base.is_some()) {
+                record.base = None;
+                released = true;
+            }
+        }
+        if released {
+            write_sync_state(cache_dir, &other.name, &other_state)?;
+        }
+    }
+    Ok(())
+}
+
+/// Record what a listing of the remote found
+///
+/// Entries missing from the listing are taken to have been removed there.
+pub fn record_remote_hashes(state: &mut SyncState, listing: &BTreeMap<String, String>) {
+    for record in state.values_mut() {
+        record.remote = None;
+    }
+    for (repo, hash) in listing {
+        state.entry(repo.clone()).or_default().remote = Some(hash.clone());
+    }
+}
+
+/// How an entry in the store compares with the last-synced remote state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntryState {
+    /// Same as the remote
+    Clean,
+    /// Changed locally since the last sync
+    Modified,
+    /// Changed, added or removed on the remote since the last sync
+    RemoteUpdated,
+    /// Changed differently on both sides
+    Conflicted,
+    /// Only in the local store, never synced
+    New,
+    /// Removed locally since the last sync
+    Deleted,
+}
+
+impl EntryState {
+    /// Stable name for the state, as used in porcelain output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntryState::Clean => "clean",
+            EntryState::Modified => "modified",
+            EntryState::RemoteUpdated => "remote-updated",
+            EntryState::Conflicted => "conflicted",
+            EntryState::New => "new",
+            EntryState::Deleted => "deleted",
+        }
+    }
+
+    /// Work out the state from the local, last-synced and remote hashes
+    ///
+    /// Returns `None` when no side has the entry any more.
+    pub fn from_hashes(
+        local: Option<&str>,
+        base: Option<&str>,
+        remote: Option<&str>,
+    ) -> Option<Self> {
+        let state = match (local, base, remote) {
+            (None, _, None) => return None,
+            (Some(l), _, Some(r)) if l == r => EntryState::Clean,
+            (Some(_), None, None) => EntryState::New,
+            (None, None, Some(_)) => EntryState::RemoteUpdated,
+            (None, Some(b), Some(r)) if b == r => EntryState::Deleted,
+            (Some(l), Some(b), r) if l == b && r != Some(b) => EntryState::RemoteUpdated,
+            (Some(_), Some(b), Some(r)) if b == r => EntryState::Modified,
+            _ => EntryState::Conflicted,
+        };
+        Some(state)
+    }
+}
+
+/// Status of one store entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryStatus {
+    /// Repository id
+    pub repo: String,
+
+    /// How it compares with the remote
+    pub state: EntryState,
+
+    /// Current local content hash, if the entry exists locally
+    pub local: Option<String>,
+
+    /// What was recorded at the last sync
+    pub record: SyncRecord,
+}
+
+/// Get the content hash of each stored Justfile, by repository id
+///
+/// Untouched entries give the hash recorded with their tree object,
+/// without being read.
+pub fn local_hashes(justfiles: &[Justfile]) -> io::Result<BTreeMap<String, String>> {
+    let mut local = BTreeMap::new();
+    for justfile in justfiles {
+        local.insert(justfile.repo_name.clone(), current_hash(justfile)?);
+    }
+    Ok(local)
+}
+
+/// Compare stored Justfiles against the recorded sync state
+///
+/// Entries appear in repository id order, including ones that only exist
+/// in the sync state because they were deleted locally or added remotely.
+pub fn entry_statuses(justfiles: &[Justfile], state: &SyncState) -> io::Result<Vec<EntryStatus>> {
+    Ok(compare_hashes(&local_hashes(justfiles)?, state))
+}
+
+/// Compare local content hashes, by repository id, against a sync state
+pub fn compare_hashes(local: &BTreeMap<String, String>, state: &SyncState) -> Vec<EntryStatus> {
+    let repos: BTreeSet<&String> = local.keys().chain(state.keys()).collect();
+    let mut statuses = Vec::new();
+    for repo in repos {
+        let local = local.get(repo).cloned();
+        let record = state.get(repo).cloned().unwrap_or_default();
+        let Some(entry_state) = EntryState::from_hashes(
+            local.as_deref(),
+            record.base.as_deref(),
+            record.remote.as_deref(),
+        ) else {
+            continue;
+        };
+        statuses.push(EntryStatus {
+            repo: repo.clone(),
+            state: entry_state,
+            local,
+            record,
+        });
+    }
+    statuses
+}
+
+/// Compare the stored Justfiles a remote provides against its sync state
+///
+/// Entries another remote provides are left out, unless this remote comes
+/// before it in priority order and has the entry too. Then this remote
+/// takes over, comparing against the content last synced with the other,
+/// so unchanged entries are pulled and local changes are merged. Entries
+/// that were never synced belong to the first remote that isn't read-only.
+pub fn remote_statuses(
+    cache_dir: &Path,
+    justfiles: &[Justfile],
+    remote: &str,
+    state: &SyncState,
+) -> io::Result<Vec<EntryStatus>> {
+    let remotes = read_remotes(cache_dir)?;
+    let mut states = BTreeMap::new();
+    for other in remotes.iter().filter(|r| r.name != remote) {
+        states.insert(other.name.clone(), read_sync_state(cache_dir, &other.name)?);
+    }
+    states.insert(remote.to_string(), state.clone());
+    provided_statuses(&remotes, &local_hashes(justfiles)?, &states, remote)
+}
+
+/// Like [`remote_statuses`], but from given local content hashes and sync
+/// states of every remote rather than what the store holds
+///
+/// This lets a dry run carry what syncing with one remote would change on
+/// to the next.
+pub fn provided_statuses(
+    remotes: &[Remote],
+    local: &BTreeMap<String, String>,
+    states: &BTreeMap<String, SyncState>,
+    remote: &str,
+) -> io::Result<Vec<EntryStatus>> {
+    let rank = |name: &str| remotes.iter().position(|r| r.name == name);
+    let this = rank(remote).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("No remote named {remote}"))
+    })?;
+    let default = remotes.iter().position(|r| !r.read_only);
+    let no_state = SyncState::new();
+    let state_of = |name: &str| states.get(name).unwrap_or(&no_state);
+
+    // An entry comes from the first remote it was last synced with
+    let mut origins = BTreeMap::new();
+    for other in remotes {
+        for (repo, record) in state_of(&other.name) {
+            if record.base.is_some() {
+                origins.entry(repo.as_str()).or_insert(other.name.as_str());
+            }
+        }
+    }
+
+    let mut state = state_of(remote).clone();
+    let mut provided = BTreeSet::new();
+    for (repo, origin) in &origins {
+        let on_remote = state.get(*repo).is_some_and(|r| r.remote.is_some());
+        if *origin == remote {
+            provided.insert(repo.to_string());
+        } else if on_remote && rank(origin) > Some(this) {
+            let base = state_of(origin).get(*repo).and_then(|r| r.base.clone());
+            state.entry(repo.to_string()).or_default().base = base;
+            provided.insert(repo.to_string());
+        }
+    }
+
+    Ok(compare_hashes(local, &state)
+        .into_iter()
+        .filter(|s| match origins.get(s.repo.as_str()) {
+            Some(_) => provided.contains(&s.repo),
+            None => default == Some(this) || s.record.remote.is_some(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::content_hash;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_states_from_hashes() {
+        use EntryState::*;
+        let cases = [
+            (Some("a"), Some("a"), Some("a"), Some(Clean)),
+            (Some("b"), Some("a"), Some("a"), Some(Modified)),
+            (Some("a"), Some("a"), Some("b"), Some(RemoteUpdated)),
+            (Some("a"), Some("a"), None, Some(RemoteUpdated)),
+            (None, None, Some("a"), Some(RemoteUpdated)),
+            (Some("b"), Some("a"), Some("c"), Some(Conflicted)),
+            (Some("b"), Some("a"), Some("b"), Some(Clean)),
+            (Some("b"), Some("a"), None, Some(Conflicted)),
+            (None, Some("a"), Some("b"), Some(Conflicted)),
+            (Some("a"), None, None, Some(New)),
+            (Some("a"), None, Some("b"), Some(Conflicted)),
+            (None, Some("a"), Some("a"), Some(Deleted)),
+            (None, Some("a"), None, None),
+        ];
+        for (local, base, remote, expected) in cases {
+            assert_eq!(
+                EntryState::from_hashes(local, base, remote),
+                expected,
+                "{local:?} {base:?} {remote:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sync_state_and_statuses() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+        let dir = cache_dir.join("user/repo");
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("Justfile"), "build:\n")?;
+        let justfile = Justfile::new("user/repo".to_string(), dir.join("Justfile"));
+        let hash = content_hash(&justfile.path)?;
+
+        assert!(read_sync_state(cache_dir, "origin")?.is_empty());
+
+        let mut state = SyncState::new();
+        state.insert(
+            "user/repo".to_string(),
+            SyncRecord {
+                base: Some(hash.clone()),
+                remote: Some(hash.clone()),
+            },
+        );
+        state.insert(
+            "user/gone".to_string(),
+            SyncRecord {
+                base: Some("old".to_string()),
+                remote: Some("old".to_string()),
+            },
+        );
+        state.insert("user/empty".to_string(), SyncRecord::default());
+        write_sync_state(cache_dir, "origin", &state)?;
+
+        let read = read_sync_state(cache_dir, "origin")?;
+        assert_eq!(read.len(), 2);
+
+        let mut listed = read.clone();
+        let listing = BTreeMap::from([("user/repo".to_string(), hash.clone())]);
+        record_remote_hashes(&mut listed, &listing);
+        assert_eq!(listed["user/gone"].remote, None);
+        assert_eq!(listed["user/repo"].remote, Some(hash.clone()));
+
+        let statuses = entry_statuses(&[justfile], &read)?;
+        let states: Vec<_> = statuses
+            .iter()
+            .map(|s| (s.repo.as_str(), s.state))
+            .collect();
+        assert_eq!(
+            states,
+            [
+                ("user/gone", EntryState::Deleted),
+                ("user/repo", EntryState::Clean)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remote_statuses_follow_priority() -> io::Result<()> {
+        use crate::remotes::{Remote, add_remote};
+
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+        for name in ["personal", "team"] {
+            add_remote(
+                cache_dir,
+                Remote {
+                    name: name.to_string(),
+                    kind: "dir".to_string(),
+                    location: format!("/srv/{name}"),
+                    read_only: false,
+                },
+            )?;
+        }
+
+        let mut justfiles = Vec::new();
+        for repo in ["team/shared", "user/new"] {
+            let dir = cache_dir.join(repo);
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join("Justfile"), "build:\n")?;
+            justfiles.push(Justfile::new(repo.to_string(), dir.join("Justfile")));
+        }
+        let hash = content_hash(&justfiles[0].path)?;
+        let synced = |base: Option<&str>, remote: &str| SyncRecord {
+            base: base.map(str::to_string),
+            remote: Some(remote.to_string()),
+        };
+
+        let team_state = SyncState::from([
+            ("team/shared".to_string(), synced(Some(&hash), &hash)),
+            ("team/only".to_string(), synced(None, "other")),
+        ]);
+        write_sync_state(cache_dir, "team", &team_state)?;
+        assert_eq!(entry_origins(cache_dir)?["team/shared"], "team");
+
+        let states = |statuses: Vec<EntryStatus>| -> Vec<(String, EntryState)> {
+            statuses.into_iter().map(|s| (s.repo, s.state)).collect()
+        };
+        assert_eq!(
+            states(remote_statuses(cache_dir, &justfiles, "team", &team_state)?),
+            [
+                ("team/only".to_string(), EntryState::RemoteUpdated),
+                ("team/shared".to_string(), EntryState::Clean)
+            ]
+        );
+
+        // The personal remote comes first, so takes over an entry it has too
+        let personal_state =
+            SyncState::from([("team/shared".to_string(), synced(None, "personal"))]);
+        assert_eq!(
+            states(remote_statuses(
+                cache_dir,
+                &justfiles,
+                "personal",
+                &personal_state
+            )?),
+            [
+                ("team/shared".to_string(), EntryState::RemoteUpdated),
+                ("user/new".to_string(), EntryState::New)
+            ]
+        );
+
+        let mut personal_state = personal_state;
+        personal_state.get_mut("team/shared").unwrap().base = Some("personal".to_string());
+        write_sync_state(cache_dir, "personal", &personal_state)?;
+        release_entries(cache_dir, "personal", &personal_state)?;
+        assert_eq!(entry_origins(cache_dir)?["team/shared"], "personal");
+        assert_eq!(
+            read_sync_state(cache_dir, "team")?["team/shared"].base,
+            None
+        );
+        assert_eq!(
+            states(remote_statuses(cache_dir, &justfiles, "team", &team_state)?),
+            [("team/only".to_string(), EntryState::RemoteUpdated)]
+        );
+
+        remove_sync_state(cache_dir, "personal")?;
+        assert!(!entry_origins(cache_dir)?.contains_key("team/shared"));
+
+        Ok(())
+    }
+}
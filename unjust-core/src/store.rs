@@ -0,0 +1,514 @@
+//! Store entry management for unjust
+//!
+//! Entries live in the cache directory under their repository id
+//! (`username/repo`). Removed entries are moved to a trash area so they can
+//! be brought back with `restore_removed`.
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::changes::{Change, discard_change, record_change};
+use crate::compose::{COMPOSED_FILE, LAYER_FILE};
+use crate::includes::{copy_justfile_files, justfile_files, normalise};
+use crate::justfile::{Justfile, find_justfiles_in_dir, is_justfile_name, justfile_in_dir};
+use crate::lock::StoreLock;
+use crate::manifest::{MANIFEST_FILE, record_content_change};
+use crate::objects::collect_garbage;
+use crate::pins::PINNED_DIR;
+use crate::revisions::REVISIONS_DIR;
+
+/// Name of the trash directory inside the cache directory
+const TRASH_DIR: &str = ".trash";
+
+/// Separator between a repository id and a subpath within the repository,
+/// as in `username/repo//packages/api`
+pub const SUBPATH_SEPARATOR: &str = "//";
+
+/// Directory inside an entry that holds its subpath-scoped entries
+pub(crate) const SUBPATH_DIR: &str = ".subpaths";
+
+/// Split an entry id into its repository id and optional subpath
+pub fn split_repo_id(repo_name: &str) -> (&str, Option<&str>) {
+    match repo_name.split_once(SUBPATH_SEPARATOR) {
+        Some((repo, subpath)) => (repo, Some(subpath)),
+        None => (repo_name, None),
+    }
+}
+
+/// Check that a relative path stays inside its parent and isn't hidden
+fn is_plain_relative(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path).components().all(|c| match c {
+            Component::Normal(part) => !part.to_string_lossy().starts_with('.'),
+            _ => false,
+        })
+}
+
+/// Resolve the directory for an entry id, rejecting ids that would escape
+/// the cache or point into a hidden directory
+///
+/// Subpath-scoped ids (`username/repo//packages/api`) live inside the
+/// repository's entry directory.
+pub fn entry_dir(cache_dir: &Path, repo_name: &str) -> io::Result<PathBuf> {
+    let (repo, subpath) = split_repo_id(repo_name);
+    let valid = is_plain_relative(repo) && subpath.is_none_or(is_plain_relative);
+
+    if !valid {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid repository id: {repo_name}"),
+        ));
+    }
+
+    let dir = cache_dir.join(repo);
+    Ok(match subpath {
+        Some(subpath) => dir.join(SUBPATH_DIR).join(subpath),
+        None => dir,
+    })
+}
+
+fn not_found(repo_name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("Justfile not found for repo: {repo_name}"),
+    )
+}
+
+/// Remove now-empty parent directories of `dir`, stopping at `root`
+fn prune_empty_parents(root: &Path, dir: &Path) {
+    let mut current = dir.parent();
+    while let Some(parent) = current {
+        if parent == root || fs::remove_dir(parent).is_err() {
+            break;
+        }
+        current = parent.parent();
+    }
+}
+
+/// Move an entry's own files from `source` to `target`
+///
+/// That's its Justfile and everything it includes, along with its manifest,
+/// revisions and pinned checkouts. Subpath entries and entries nested in
+/// its directory stay where they are. Files generated from the entry are
+/// dropped, and `source` is removed up to `root` once nothing is left in it.
+fn move_entry(root: &Path, source: &Path, target: &Path) -> io::Result<()> {
+    let justfile = justfile_in_dir(source)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No Justfile in {}", source.display()),
+        )
+    })?;
+    // An entry with broken includes can still be moved, Justfile first
+    let mut files = justfile_files(&justfile).unwrap_or_else(|_| vec![justfile]);
+    files.extend(
+        [MANIFEST_FILE, REVISIONS_DIR, PINNED_DIR]
+            .map(|name| source.join(name))
+            .into_iter()
+            .filter(|path| path.exists()),
+    );
+
+    let base = normalise(source);
+    for file in files {
+        let file = normalise(&file);
+        let relative = file.strip_prefix(&base).unwrap_or(&file);
+        let destination = target.join(relative);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&file, &destination)?;
+        prune_empty_parents(&base, &file);
+    }
+
+    for generated in [COMPOSED_FILE, LAYER_FILE] {
+        let _ = fs::remove_file(source.join(generated));
+    }
+    if fs::remove_dir(source).is_ok() {
+        prune_empty_parents(root, source);
+    }
+    Ok(())
+}
+
+/// Move an entry to the trash and record the removal for the next sync
+///
+/// Returns the path the entry was moved to.
+pub fn remove_justfile(cache_dir: &Path, repo_name: &str) -> io::Result<PathBuf> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let source = entry_dir(cache_dir, repo_name)?;
+    if justfile_in_dir(&source)?.is_none() {
+        return Err(not_found(repo_name));
+    }
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let target = cache_dir
+        .join(TRASH_DIR)
+        .join(repo_name)
+        .join(stamp.to_string());
+
+    move_entry(cache_dir, &source, &target)?;
+
+    record_change(
+        cache_dir,
+        &Change::Removed {
+            repo: repo_name.to_string(),
+        },
+    )?;
+
+    Ok(target)
+}
+
+/// Delete every removed entry for good, along with the objects only their
+/// revisions referred to
+///
+/// Returns how many objects were deleted.
+pub fn empty_trash(cache_dir: &Path) -> io::Result<usize> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    match fs::remove_dir_all(cache_dir.join(TRASH_DIR)) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    collect_garbage(cache_dir)
+}
+
+/// Bring back the most recently removed version of an entry
+///
+/// Returns the restored entry directory.
+pub fn restore_removed(cache_dir: &Path, repo_name: &str) -> io::Result<PathBuf> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let target = entry_dir(cache_dir, repo_name)?;
+    if justfile_in_dir(&target)?.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Justfile already exists for repo: {repo_name}"),
+        ));
+    }
+
+    let trash_dir = cache_dir.join(TRASH_DIR).join(repo_name);
+    let latest = fs::read_dir(&trash_dir)
+        .map_err(|_| not_found(repo_name))?
+        .flatten()
+        .filter_map(|e| {
+            let stamp = e.file_name().to_str()?.parse::<u128>().ok()?;
+            Some((stamp, e.path()))
+        })
+        .max_by_key(|(stamp, _)| *stamp)
+        .map(|(_, path)| path)
+        .ok_or_else(|| not_found(repo_name))?;
+
+    move_entry(cache_dir, &latest, &target)?;
+
+    // An unsynced removal is cancelled; a synced one is undone by the next
+    // sync pushing the entry again as new
+    discard_change(
+        cache_dir,
+        &Change::Removed {
+            repo: repo_name.to_string(),
+        },
+    )?;
+
+    Ok(target)
+}
+
+/// Import an existing Justfile into the store under a repository id
+///
+/// The file keeps its original name (`justfile`, `.justfile`, ...). Fails if
+/// the store already has a Justfile for the repository, unless `overwrite`
+/// is set, in which case the old one is replaced whatever its name. Files
+/// it imports or loads as modules are copied along with it, and must live
+/// in its directory. Returns the path of the stored Justfile.
+pub fn adopt_justfile(
+    cache_dir: &Path,
+    repo_name: &str,
+    source: &Path,
+    overwrite: bool,
+) -> io::Result<PathBuf> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let target_dir = entry_dir(cache_dir, repo_name)?;
+    let name = source
+        .file_name()
+        .filter(|n| n.to_str().is_some_and(is_justfile_name))
+        .unwrap_or("Justfile".as_ref());
+    let target = target_dir.join(name);
+
+    let existing = find_justfiles_in_dir(&target_dir)?;
+    let change = if existing.is_empty() {
+        Change::Added {
+            repo: repo_name.to_string(),
+        }
+    } else {
+        Change::Edited {
+            repo: repo_name.to_string(),
+        }
+    };
+    if !existing.is_empty() {
+        if !overwrite {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Justfile already exists for repo: {repo_name}"),
+            ));
+        }
+        // Only one Justfile per entry, so drop any stored under other names
+        for path in existing {
+            fs::remove_file(path)?;
+        }
+    }
+
+    fs::create_dir_all(&target_dir)?;
+    let copied = copy_justfile_files(source, &target_dir)?;
+    if copied != target {
+        fs::rename(&copied, &target)?;
+    }
+    record_content_change(&Justfile::new(repo_name.to_string(), target.clone()))?;
+    record_change(cache_dir, &change)?;
+
+    Ok(target)
+}
+
+/// Rename an entry to a different repository id
+///
+/// This is used when a repository is transferred or renamed upstream.
+/// Returns the new entry directory.
+pub fn rename_justfile(cache_dir: &Path, from: &str, to: &str) -> io::Result<PathBuf> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let source = entry_dir(cache_dir, from)?;
+    let target = entry_dir(cache_dir, to)?;
+
+    if justfile_in_dir(&source)?.is_none() {
+        return Err(not_found(from));
+    }
+    if justfile_in_dir(&target)?.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Repository already exists in the store: {to}"),
+        ));
+    }
+    if target.starts_with(&source) || source.starts_with(&target) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Cannot rename {from} to {to}: one is nested inside the other"),
+        ));
+    }
+
+    move_entry(cache_dir, &source, &target)?;
+
+    record_change(
+        cache_dir,
+        &Change::Renamed {
+            from: from.to_string(),
+            to: to.to_string(),
+        },
+    )?;
+
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changes::pending_changes;
+    use crate::manifest::read_manifest;
+    use crate::objects::{object_id, object_path};
+    use tempfile::TempDir;
+
+    fn setup_store(repos: &[&str]) -> io::Result<TempDir> {
+        let temp_dir = TempDir::new()?;
+        for repo in repos {
+            let repo_dir = temp_dir.path().join(repo);
+            fs::create_dir_all(&repo_dir)?;
+            fs::write(repo_dir.join("Justfile"), format!("# {repo}"))?;
+        }
+        Ok(temp_dir)
+    }
+
+    #[test]
+    fn test_entry_dir_rejects_escapes() {
+        let cache_dir = Path::new("/cache");
+
+        assert!(entry_dir(cache_dir, "user/repo").is_ok());
+        assert!(entry_dir(cache_dir, "").is_err());
+        assert!(entry_dir(cache_dir, "../repo").is_err());
+        assert!(entry_dir(cache_dir, "/abs/repo").is_err());
+        assert!(entry_dir(cache_dir, ".trash/repo").is_err());
+        assert!(entry_dir(cache_dir, "user/repo//").is_err());
+        assert!(entry_dir(cache_dir, "user/repo//../other").is_err());
+    }
+
+    #[test]
+    fn test_subpath_entry_dir() -> io::Result<()> {
+        let cache_dir = Path::new("/cache");
+
+        assert_eq!(split_repo_id("user/repo"), ("user/repo", None));
+        assert_eq!(
+            split_repo_id("user/repo//packages/api"),
+            ("user/repo", Some("packages/api"))
+        );
+        assert_eq!(
+            entry_dir(cache_dir, "user/repo//packages/api")?,
+            cache_dir
+                .join("user/repo")
+                .join(SUBPATH_DIR)
+                .join("packages/api")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_and_restore() -> io::Result<()> {
+        let temp_dir = setup_store(&["user/repo"])?;
+        let cache_dir = temp_dir.path();
+
+        let trashed = remove_justfile(cache_dir, "user/repo")?;
+        assert!(trashed.join("Justfile").exists());
+        assert!(!cache_dir.join("user").exists());
+        assert_eq!(
+            pending_changes(cache_dir)?,
+            vec![Change::Removed {
+                repo: "user/repo".to_string()
+            }]
+        );
+
+        let restored = restore_removed(cache_dir, "user/repo")?;
+        assert_eq!(
+            fs::read_to_string(restored.join("Justfile"))?,
+            "# user/repo"
+        );
+        assert!(!cache_dir.join(TRASH_DIR).exists());
+        assert!(pending_changes(cache_dir)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_trash_deletes_unused_objects() -> io::Result<()> {
+        let temp_dir = setup_store(&["user/keep", "user/gone", "user/copy"])?;
+        let cache_dir = temp_dir.path();
+        fs::write(cache_dir.join("user/copy/Justfile"), "# user/keep")?;
+        for repo in ["user/keep", "user/gone", "user/copy"] {
+            let path = cache_dir.join(repo).join("Justfile");
+            record_content_change(&Justfile::new(repo.to_string(), path))?;
+        }
+        let kept = object_path(cache_dir, &object_id(b"# user/keep"));
+        let gone = object_path(cache_dir, &object_id(b"# user/gone"));
+        assert!(kept.exists() && gone.exists());
+
+        remove_justfile(cache_dir, "user/gone")?;
+        remove_justfile(cache_dir, "user/copy")?;
+        // Trashed entries keep their objects until the trash is emptied
+        assert_eq!(collect_garbage(cache_dir)?, 0);
+        assert!(gone.exists());
+
+        assert!(empty_trash(cache_dir)? > 0);
+        assert!(!cache_dir.join(TRASH_DIR).exists());
+        assert!(!gone.exists());
+        assert!(kept.exists());
+        let manifest = read_manifest(&cache_dir.join("user/keep"))?;
+        assert!(object_path(cache_dir, &manifest.object_id.unwrap()).exists());
+        assert!(restore_removed(cache_dir, "user/gone").is_err());
+        assert_eq!(empty_trash(cache_dir)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_and_rename_leave_nested_entries() -> io::Result<()> {
+        let temp_dir = setup_store(&["user/repo", "user/repo/tools"])?;
+        let cache_dir = temp_dir.path();
+        let subpath = entry_dir(cache_dir, "user/repo//pkg")?;
+        fs::create_dir_all(&subpath)?;
+        fs::write(subpath.join("Justfile"), "# pkg")?;
+        let entry = cache_dir.join("user/repo");
+        fs::create_dir_all(entry.join(REVISIONS_DIR))?;
+        fs::write(entry.join(MANIFEST_FILE), "")?;
+
+        let trashed = remove_justfile(cache_dir, "user/repo")?;
+        assert!(trashed.join("Justfile").exists());
+        assert!(trashed.join(MANIFEST_FILE).exists());
+        assert!(trashed.join(REVISIONS_DIR).is_dir());
+        assert!(!trashed.join("tools").exists());
+        assert!(!trashed.join(SUBPATH_DIR).exists());
+        assert!(justfile_in_dir(&entry)?.is_none());
+        assert!(!entry.join(MANIFEST_FILE).exists());
+        assert!(entry.join("tools/Justfile").exists());
+        assert!(subpath.join("Justfile").exists());
+        assert_eq!(pending_changes(cache_dir)?.len(), 1);
+
+        restore_removed(cache_dir, "user/repo")?;
+        assert_eq!(fs::read_to_string(entry.join("Justfile"))?, "# user/repo");
+        assert!(entry.join(REVISIONS_DIR).is_dir());
+        assert!(!cache_dir.join(TRASH_DIR).exists());
+
+        let renamed = rename_justfile(cache_dir, "user/repo", "other/repo")?;
+        assert!(renamed.join("Justfile").exists());
+        assert!(renamed.join(MANIFEST_FILE).exists());
+        assert!(!renamed.join("tools").exists());
+        assert!(entry.join("tools/Justfile").exists());
+        assert!(subpath.join("Justfile").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_missing() -> io::Result<()> {
+        let temp_dir = setup_store(&[])?;
+        let err = remove_justfile(temp_dir.path(), "user/repo").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn test_adopt() -> io::Result<()> {
+        let temp_dir = setup_store(&["taken/repo"])?;
+        let cache_dir = temp_dir.path();
+        let source = cache_dir.join("local-justfile");
+        fs::write(&source, "# adopted")?;
+
+        let stored = adopt_justfile(cache_dir, "user/repo", &source, false)?;
+        assert_eq!(fs::read_to_string(stored)?, "# adopted");
+
+        let err = adopt_justfile(cache_dir, "taken/repo", &source, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        let stored = adopt_justfile(cache_dir, "taken/repo", &source, true)?;
+        assert_eq!(fs::read_to_string(stored)?, "# adopted");
+
+        // Recognised names are preserved and replace other variants
+        let hidden = cache_dir.join(".justfile");
+        fs::write(&hidden, "# hidden")?;
+        let stored = adopt_justfile(cache_dir, "taken/repo", &hidden, true)?;
+        assert_eq!(stored, cache_dir.join("taken/repo/.justfile"));
+        assert_eq!(
+            find_justfiles_in_dir(&cache_dir.join("taken/repo"))?,
+            vec![stored]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename() -> io::Result<()> {
+        let temp_dir = setup_store(&["old/repo", "taken/repo"])?;
+        let cache_dir = temp_dir.path();
+
+        let err = rename_justfile(cache_dir, "old/repo", "taken/repo").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        let renamed = rename_justfile(cache_dir, "old/repo", "new/repo")?;
+        assert_eq!(fs::read_to_string(renamed.join("Justfile"))?, "# old/repo");
+        assert!(!cache_dir.join("old").exists());
+        assert_eq!(
+            pending_changes(cache_dir)?,
+            vec![Change::Renamed {
+                from: "old/repo".to_string(),
+                to: "new/repo".to_string()
+            }]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+//! Tags for organising store entries
+//!
+//! Tags live in each entry's manifest, so they travel with the entry when it
+//! is synced, renamed or removed.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::justfile::{Justfile, justfile_in_dir};
+use crate::lock::StoreLock;
+use crate::manifest::update_manifest;
+use crate::store::entry_dir;
+
+/// Check that a tag is usable: non-empty, without whitespace or commas
+fn validate_tag(tag: &str) -> io::Result<()> {
+    if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid tag: {tag:?}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Get the directory of an entry that has a Justfile
+fn existing_entry(cache_dir: &Path, repo_name: &str) -> io::Result<PathBuf> {
+    let dir = entry_dir(cache_dir, repo_name)?;
+    if justfile_in_dir(&dir)?.is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Justfile not found for repo: {repo_name}"),
+        ));
+    }
+    Ok(dir)
+}
+
+/// Add a tag to a store entry
+///
+/// Returns `false` if the entry already had the tag.
+pub fn tag_entry(cache_dir: &Path, repo_name: &str, tag: &str) -> io::Result<bool> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    validate_tag(tag)?;
+    let dir = existing_entry(cache_dir, repo_name)?;
+
+    let mut added = false;
+    update_manifest(&dir, |manifest| {
+        if !manifest.tags.iter().any(|t| t == tag) {
+            manifest.tags.push(tag.to_string());
+            added = true;
+        }
+    })?;
+    Ok(added)
+}
+
+/// Remove a tag from a store entry
+///
+/// Returns `false` if the entry didn't have the tag.
+pub fn untag_entry(cache_dir: &Path, repo_name: &str, tag: &str) -> io::Result<bool> {
+    let _lock = StoreLock::acquire(cache_dir)?;
+    let dir = existing_entry(cache_dir, repo_name)?;
+
+    let mut removed = false;
+    update_manifest(&dir, |manifest| {
+        let count = manifest.tags.len();
+        manifest.tags.retain(|t| t != tag);
+        removed = manifest.tags.len() != count;
+    })?;
+    Ok(removed)
+}
+
+/// Check whether a stored Justfile's entry has a tag
+pub fn has_tag(justfile: &Justfile, tag: &str) -> io::Result<bool> {
+    Ok(justfile.manifest()?.tags.iter().any(|t| t == tag))
+}
+
+/// Group stored Justfiles by tag, with tags and entries in sorted order
+pub fn tagged_entries(justfiles: &[Justfile]) -> io::Result<BTreeMap<String, Vec<String>>> {
+    let mut tags: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for justfile in justfiles {
+        for tag in justfile.manifest()?.tags {
+            tags.entry(tag)
+                .or_default()
+                .push(justfile.repo_name.clone());
+        }
+    }
+    for repos in tags.values_mut() {
+        repos.sort();
+    }
+    Ok(tags)
+}
+
+/// Pick the stored Justfile to use as a template
+///
+/// `name` can be a repository id, or a tag: any Justfile carrying it will
+/// do, and when several do the first by repository id is used.
+pub fn find_template(cache_dir: &Path, justfiles: &[Justfile], name: &str) -> io::Result<Justfile> {
+    if let Some(path) = justfile_in_dir(&entry_dir(cache_dir, name)?)? {
+        return Ok(Justfile::new(name.to_string(), path));
+    }
+
+    let mut tagged = Vec::new();
+    for justfile in justfiles {
+        if has_tag(justfile, name)? {
+            tagged.push(justfile);
+        }
+    }
+    tagged
+        .into_iter()
+        .min_by(|a, b| a.repo_name.cmp(&b.repo_name))
+        .cloned()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No Justfile or tag found for template: {name}"),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn store_entry(cache_dir: &Path, repo: &str) -> io::Result<Justfile> {
+        let dir = cache_dir.join(repo);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("Justfile"), "build:\n")?;
+        Ok(Justfile::new(repo.to_string(), dir.join("Justfile")))
+    }
+
+    #[test]
+    fn test_tag_and_untag() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+        store_entry(cache_dir, "user/repo")?;
+
+        assert!(tag_entry(cache_dir, "user/repo", "rust")?);
+        assert!(!tag_entry(cache_dir, "user/repo", "rust")?);
+        assert!(tag_entry(cache_dir, "user/repo", "cli")?);
+        assert_eq!(
+            crate::manifest::read_manifest(&cache_dir.join("user/repo"))?.tags,
+            ["rust", "cli"]
+        );
+
+        assert!(untag_entry(cache_dir, "user/repo", "rust")?);
+        assert!(!untag_entry(cache_dir, "user/repo", "rust")?);
+
+        assert!(tag_entry(cache_dir, "user/repo", "two words").is_err());
+        let err = tag_entry(cache_dir, "user/missing", "rust").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_template() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache_dir = temp_dir.path();
+        let justfiles = vec![
+            store_entry(cache_dir, "b/python")?,
+            store_entry(cache_dir, "a/rust")?,
+        ];
+
+        let found = find_template(cache_dir, &justfiles, "b/python")?;
+        assert_eq!(found.repo_name, "b/python");
+
+        // Any Justfile with the tag will do, picked by repository id
+        tag_entry(cache_dir, "a/rust", "template")?;
+        tag_entry(cache_dir, "a/rust", "lang")?;
+        tag_entry(cache_dir, "b/python", "lang")?;
+        let found = find_template(cache_dir, &justfiles, "template")?;
+        assert_eq!(found.repo_name, "a/rust");
+        let found = find_template(cache_dir, &justfiles, "lang")?;
+        assert_eq!(found.repo_name, "a/rust");
+
+        let err = find_template(cache_dir, &justfiles, "nothing").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        let tags = tagged_entries(&justfiles)?;
+        assert_eq!(tags["lang"], ["a/rust", "b/python"]);
+
+        Ok(())
+    }
+}
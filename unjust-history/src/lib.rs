@@ -0,0 +1,129 @@
+use console::style;
+use facet::Facet;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use unjust_core::{UsageRecord, ensure_cache_dir, format_age, now, read_history};
+
+/// Arguments for the "history" command
+#[derive(Facet, Debug)]
+pub struct HistoryArgs {
+    /// Only show uses of this repo
+    #[facet(positional, default)]
+    pub repo: Option<String>,
+
+    /// Only show runs that failed
+    #[facet(named)]
+    pub failed: bool,
+
+    /// Summarise runs per repo and recipe instead of listing them
+    #[facet(named)]
+    pub stats: bool,
+
+    /// Show at most this many entries
+    #[facet(named, short = 'n', default)]
+    pub limit: Option<usize>,
+}
+
+/// Format a duration in milliseconds for display
+fn format_duration(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{ms}ms")
+    } else {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    }
+}
+
+/// Print how often each repo and recipe was used, most used first
+fn print_stats(records: &[UsageRecord], limit: usize) {
+    let mut counts: BTreeMap<(&str, &str), (usize, usize)> = BTreeMap::new();
+    for record in records {
+        let key = (
+            record.repo.as_str(),
+            record.recipe.as_deref().unwrap_or("-"),
+        );
+        let (runs, failures) = counts.entry(key).or_default();
+        *runs += 1;
+        if record.failed() {
+            *failures += 1;
+        }
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by_key(|(_, (runs, _))| Reverse(*runs));
+    for ((repo, recipe), (runs, failures)) in counts.into_iter().take(limit) {
+        println!(
+            "{} {}: {} runs, {} failed",
+            style(repo).green(),
+            recipe,
+            runs,
+            failures
+        );
+    }
+}
+
+/// Handle the "history" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_history_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let history_args = match facet_args::from_slice::<HistoryArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let history = match ensure_cache_dir().and_then(|cache_dir| read_history(&cache_dir)) {
+        Ok(history) => history,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let records: Vec<UsageRecord> = history
+        .into_iter()
+        .filter(|r| {
+            history_args
+                .repo
+                .as_ref()
+                .is_none_or(|repo| &r.repo == repo)
+        })
+        .filter(|r| !history_args.failed || r.failed())
+        .collect();
+
+    if records.is_empty() {
+        println!(
+            "No usage recorded. Use '{}' to run a recipe.",
+            style("unjust use -r <recipe>").green()
+        );
+        return 0;
+    }
+
+    let limit = history_args.limit.unwrap_or(usize::MAX);
+    if history_args.stats {
+        print_stats(&records, limit);
+        return 0;
+    }
+
+    let now = now();
+    for record in records.iter().rev().take(limit) {
+        let status = if record.failed() {
+            style(format!("exit {}", record.exit_code)).red()
+        } else {
+            style("ok".to_string()).green()
+        };
+        println!(
+            "{:>9}  {} {}  {}  {}  {}",
+            format_age(record.time, now),
+            style(&record.repo).green(),
+            record.recipe.as_deref().unwrap_or("-"),
+            status,
+            format_duration(record.duration_ms),
+            record.cwd.display()
+        );
+    }
+
+    0
+}
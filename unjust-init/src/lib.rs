@@ -1,5 +1,6 @@
 use console::style;
 use facet::Facet;
+use unjust_core::{find_template, get_cache_dir, list_justfiles};
 
 /// Arguments for the "init" command
 #[derive(Facet, Debug)]
@@ -8,8 +9,8 @@ pub struct InitArgs {
     #[facet(positional, default)]
     pub name: Option<String>,
 
-    /// Template to use (existing Justfile name)
-    #[facet(named, short = 't')]
+    /// Template to use: a stored Justfile's repo id, or a tag carried by one
+    #[facet(named, short = 't', default)]
     pub template: Option<String>,
 }
 
@@ -61,8 +62,26 @@ pub fn handle_init_command(args: &[&str]) -> i32 {
 
     println!("Would initialize new Justfile with name: {}", name);
 
-    if let Some(ref template) = init_args.template {
-        println!("Would use template: {}", template);
+    let template = match (get_cache_dir(), init_args.template.as_deref()) {
+        (Some(cache_dir), Some(name)) => list_justfiles()
+            .and_then(|justfiles| find_template(&cache_dir, &justfiles, name))
+            .map(Some),
+        _ => Ok(None),
+    };
+    let template = match template {
+        Ok(template) => template,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    if let Some(template) = template {
+        println!(
+            "Would use template: {} ({})",
+            template.repo_name,
+            template.path.display()
+        );
     } else {
         println!("Would create a basic Justfile");
 
@@ -1,7 +1,8 @@
 use console::style;
 use facet::Facet;
 use facet_args::from_slice;
-use unjust_core::list_justfiles;
+use std::cmp::Reverse;
+use unjust_core::{Justfile, ensure_cache_dir, has_tag, list_justfiles, recipe_layers};
 
 /// Arguments for the "list" command
 #[derive(Facet, Debug)]
@@ -9,6 +10,42 @@ pub struct ListArgs {
     /// Show full paths
     #[facet(named, short = 'p')]
     pub paths: bool,
+
+    /// Show each Justfile's recipes and the layer they come from
+    #[facet(named, short = 'r')]
+    pub recipes: bool,
+
+    /// Only show Justfiles with this tag
+    #[facet(named, default)]
+    pub tag: Option<String>,
+
+    /// Order to list in: name (the default) or recent, most recently used first
+    #[facet(named, default)]
+    pub sort: Option<String>,
+}
+
+/// Order Justfiles by when they were last used, most recent first
+///
+/// Justfiles that were never used keep their order at the end.
+fn sort_by_recent(justfiles: Vec<Justfile>) -> std::io::Result<Vec<Justfile>> {
+    let mut keyed = Vec::with_capacity(justfiles.len());
+    for justfile in justfiles {
+        let last_used = justfile.manifest()?.last_used;
+        keyed.push((last_used, justfile));
+    }
+    keyed.sort_by_key(|(last_used, _)| Reverse(*last_used));
+    Ok(keyed.into_iter().map(|(_, justfile)| justfile).collect())
+}
+
+/// Keep only the Justfiles carrying a tag
+fn filter_by_tag(justfiles: Vec<Justfile>, tag: &str) -> std::io::Result<Vec<Justfile>> {
+    let mut tagged = Vec::new();
+    for justfile in justfiles {
+        if has_tag(&justfile, tag)? {
+            tagged.push(justfile);
+        }
+    }
+    Ok(tagged)
 }
 
 /// Handle the "list" command
@@ -24,8 +61,38 @@ pub fn handle_list_command(args: &[&str]) -> i32 {
         }
     };
 
-    match list_justfiles() {
+    let recent = match list_args.sort.as_deref() {
+        None | Some("name") => false,
+        Some("recent") => true,
+        Some(sort) => {
+            eprintln!(
+                "{} Unknown sort order: {} (expected name or recent)",
+                style("Error:").red().bold(),
+                sort
+            );
+            return 1;
+        }
+    };
+
+    let justfiles = list_justfiles()
+        .and_then(|justfiles| match list_args.tag.as_deref() {
+            Some(tag) => filter_by_tag(justfiles, tag),
+            None => Ok(justfiles),
+        })
+        .and_then(|justfiles| {
+            if recent {
+                sort_by_recent(justfiles)
+            } else {
+                Ok(justfiles)
+            }
+        });
+
+    match justfiles {
         Ok(justfiles) => {
+            if let (true, Some(tag)) = (justfiles.is_empty(), list_args.tag.as_deref()) {
+                println!("No Justfiles tagged {}", style(tag).green());
+                return 0;
+            }
             if justfiles.is_empty() {
                 println!(
                     "No Justfiles found. Use '{}' to add one.",
@@ -36,15 +103,40 @@ pub fn handle_list_command(args: &[&str]) -> i32 {
 
             println!("{} Available Justfiles:", style("Success:").green().bold());
             for (i, justfile) in justfiles.iter().enumerate() {
+                // Entries that came from a remote say which one
+                let remote = justfile
+                    .remote
+                    .as_ref()
+                    .map(|name| format!(" [{name}]"))
+                    .unwrap_or_default();
                 if list_args.paths {
                     println!(
-                        "{}. {} ({})",
+                        "{}. {}{} ({})",
                         i + 1,
                         style(&justfile.repo_name).green(),
+                        style(remote).dim(),
                         justfile.path.display()
                     );
                 } else {
-                    println!("{}. {}", i + 1, style(&justfile.repo_name).green());
+                    println!(
+                        "{}. {}{}",
+                        i + 1,
+                        style(&justfile.repo_name).green(),
+                        style(remote).dim()
+                    );
+                }
+
+                if list_args.recipes {
+                    let layers = ensure_cache_dir()
+                        .and_then(|cache_dir| recipe_layers(&cache_dir, justfile));
+                    match layers {
+                        Ok(recipes) => {
+                            for (recipe, layer) in recipes {
+                                println!("    {} ({})", recipe, style(layer).dim());
+                            }
+                        }
+                        Err(e) => eprintln!("    {} {}", style("Warning:").yellow().bold(), e),
+                    }
                 }
             }
             0
@@ -0,0 +1,195 @@
+use console::style;
+use facet::Facet;
+use std::io;
+use unjust_core::{
+    Justfile, StoreLock, content_hash, diff_revision, ensure_cache_dir, find_justfile, format_age,
+    now, resolve_repo_for_dir, restore_revision, revisions,
+};
+
+/// Arguments for the "log" command
+#[derive(Facet, Debug)]
+pub struct LogArgs {
+    /// Repo identifier (username/repo), detected if omitted
+    #[facet(positional, default)]
+    pub repo: Option<String>,
+}
+
+/// Arguments for the "diff" command
+#[derive(Facet, Debug)]
+pub struct DiffArgs {
+    /// Repo identifier (username/repo), detected if omitted
+    #[facet(positional, default)]
+    pub repo: Option<String>,
+
+    /// Revision to compare against (defaults to the last change)
+    #[facet(positional, default)]
+    pub rev: Option<u32>,
+}
+
+/// Arguments for the "restore" command
+#[derive(Facet, Debug)]
+pub struct RestoreArgs {
+    /// Repo identifier (username/repo)
+    #[facet(positional)]
+    pub repo: String,
+
+    /// Revision to restore
+    #[facet(positional)]
+    pub rev: u32,
+}
+
+/// Find the stored Justfile for a repo, or the current directory's repo
+fn stored_justfile(repo: Option<String>) -> io::Result<Justfile> {
+    let repo = match repo {
+        Some(repo) => repo,
+        None => resolve_repo_for_dir(&std::env::current_dir()?, false)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "Repository not specified and could not be detected",
+            )
+        })?,
+    };
+
+    find_justfile(&repo, false)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Justfile not found for repo: {repo}"),
+        )
+    })
+}
+
+/// Handle the "log" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_log_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let log_args = match facet_args::from_slice::<LogArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    // Edits made directly in the store aren't recorded here, as looking at
+    // the history shouldn't change it. They show as the working copy instead
+    let result = stored_justfile(log_args.repo).and_then(|justfile| {
+        let entry = justfile
+            .path
+            .parent()
+            .unwrap_or(&justfile.path)
+            .to_path_buf();
+        let revisions = revisions(&entry)?;
+        let hash = content_hash(&justfile.path)?;
+        let edited = revisions.last().is_none_or(|r| r.hash != hash);
+        Ok((justfile, revisions, edited))
+    });
+
+    match result {
+        Ok((justfile, revisions, edited)) => {
+            println!(
+                "{} Revisions of {}:",
+                style("Success:").green().bold(),
+                style(&justfile.repo_name).green()
+            );
+            let now = now();
+            if edited {
+                println!(
+                    "{:>4}  {:>9}  Working copy, not yet recorded",
+                    style("*").yellow(),
+                    ""
+                );
+            }
+            for revision in revisions.iter().rev() {
+                println!(
+                    "{:>4}  {:>9}  {}",
+                    style(revision.number).yellow(),
+                    format_age(revision.time, now),
+                    revision.message
+                );
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
+
+/// Handle the "diff" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_diff_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let diff_args = match facet_args::from_slice::<DiffArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let diff = stored_justfile(diff_args.repo)
+        .and_then(|justfile| diff_revision(&justfile, diff_args.rev));
+
+    match diff {
+        Ok(diff) if diff.is_empty() => {
+            println!("No changes");
+            0
+        }
+        Ok(diff) => {
+            for line in diff.lines() {
+                let styled = match line.chars().next() {
+                    Some('+') => style(line).green(),
+                    Some('-') => style(line).red(),
+                    Some('@') => style(line).cyan(),
+                    _ => style(line),
+                };
+                println!("{}", styled);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
+
+/// Handle the "restore" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_restore_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let restore_args = match facet_args::from_slice::<RestoreArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let restored = ensure_cache_dir().and_then(|cache_dir| {
+        let _lock = StoreLock::acquire(&cache_dir)?;
+        let justfile = stored_justfile(Some(restore_args.repo.clone()))?;
+        restore_revision(&justfile, restore_args.rev)
+    });
+
+    match restored {
+        Ok(path) => {
+            println!(
+                "{} Restored {} to revision {}: {}",
+                style("Success:").green().bold(),
+                style(&restore_args.repo).green(),
+                restore_args.rev,
+                path.display()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
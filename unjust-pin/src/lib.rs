@@ -0,0 +1,178 @@
+use console::style;
+use facet::Facet;
+use std::io;
+use std::path::PathBuf;
+use unjust_core::{
+    Pin, ensure_cache_dir, pin_entry, read_pins, remove_pin, resolve_repo_for_dir, worktree_root,
+};
+
+/// Arguments for the "pin" command
+#[derive(Facet, Debug)]
+pub struct PinArgs {
+    /// Repo identifier (username/repo), detected if omitted
+    #[facet(positional, default)]
+    pub repo: Option<String>,
+
+    /// Revision to pin to (defaults to the current content)
+    #[facet(named, default)]
+    pub rev: Option<u32>,
+
+    /// Remove the pin instead
+    #[facet(named)]
+    pub remove: bool,
+}
+
+/// Arguments for the "update" command
+#[derive(Facet, Debug)]
+pub struct UpdateArgs {
+    /// Only update the pin of this repo
+    #[facet(positional, default)]
+    pub repo: Option<String>,
+}
+
+/// Get the checkout pins apply to: the root of the current git working tree,
+/// or the current directory outside of git
+fn checkout_dir() -> io::Result<PathBuf> {
+    let current_dir = std::env::current_dir()?;
+    Ok(worktree_root(&current_dir)?.unwrap_or(current_dir))
+}
+
+/// Handle the "pin" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_pin_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let pin_args = match facet_args::from_slice::<PinArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let detected = match pin_args.repo {
+        Some(repo) => Ok(Some(repo)),
+        None => std::env::current_dir().and_then(|dir| resolve_repo_for_dir(&dir, false)),
+    };
+    let repo = match detected {
+        Ok(Some(repo)) => repo,
+        Ok(None) => {
+            eprintln!(
+                "{} Repository not specified and could not be detected",
+                style("Error:").red().bold()
+            );
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    if pin_args.remove {
+        let removed = ensure_cache_dir()
+            .and_then(|cache_dir| remove_pin(&cache_dir, &checkout_dir()?, &repo));
+        return match removed {
+            Ok(Some(pin)) => {
+                println!(
+                    "{} Unpinned {} for {}",
+                    style("Success:").green().bold(),
+                    style(&pin.repo).green(),
+                    pin.checkout.display()
+                );
+                0
+            }
+            Ok(None) => {
+                println!("{} is not pinned here", style(&repo).green());
+                0
+            }
+            Err(e) => {
+                eprintln!("{} {}", style("Error:").red().bold(), e);
+                1
+            }
+        };
+    }
+
+    let pinned = ensure_cache_dir()
+        .and_then(|cache_dir| pin_entry(&cache_dir, &checkout_dir()?, &repo, pin_args.rev));
+    match pinned {
+        Ok(pin) => {
+            println!(
+                "{} Pinned {} to revision {} for {}",
+                style("Success:").green().bold(),
+                style(&pin.repo).green(),
+                pin.revision,
+                pin.checkout.display()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
+
+/// Move the current checkout's pins on to the latest content, returning
+/// each pin with the revision it was moved from
+fn update_pins(repo: Option<&str>) -> io::Result<Vec<(u32, Pin)>> {
+    let cache_dir = ensure_cache_dir()?;
+    let checkout = checkout_dir()?;
+    let mut updated = Vec::new();
+    for pin in read_pins(&cache_dir)? {
+        if pin.checkout != checkout || repo.is_some_and(|repo| pin.repo != repo) {
+            continue;
+        }
+        let moved = pin_entry(&cache_dir, &checkout, &pin.repo, None)?;
+        updated.push((pin.revision, moved));
+    }
+    Ok(updated)
+}
+
+/// Handle the "update" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_update_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let update_args = match facet_args::from_slice::<UpdateArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    match update_pins(update_args.repo.as_deref()) {
+        Ok(updated) if updated.is_empty() => {
+            println!(
+                "Nothing is pinned here. Use '{}' to pin a Justfile.",
+                style("unjust pin <repo>").green()
+            );
+            0
+        }
+        Ok(updated) => {
+            for (from, pin) in updated {
+                if from == pin.revision {
+                    println!(
+                        "{} is already at its latest revision ({})",
+                        style(&pin.repo).green(),
+                        pin.revision
+                    );
+                } else {
+                    println!(
+                        "{} Updated {} from revision {} to {}",
+                        style("Success:").green().bold(),
+                        style(&pin.repo).green(),
+                        from,
+                        pin.revision
+                    );
+                }
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
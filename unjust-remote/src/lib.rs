@@ -0,0 +1,276 @@
+use console::style;
+use facet::Facet;
+use unjust_core::{
+    Remote, add_remote, ensure_cache_dir, read_remotes, remove_remote, set_remote_priority,
+};
+use unjust_storage::BACKEND_KINDS;
+
+/// Arguments for the "remote" command
+#[derive(Facet, Debug)]
+pub struct RemoteArgs {
+    /// Action to take: add, remove, priority or list
+    #[facet(positional, default)]
+    pub action: Option<String>,
+
+    /// Name of the remote
+    #[facet(positional, default)]
+    pub name: Option<String>,
+
+    /// Kind of storage: git, dir or http (or the new position, for priority)
+    #[facet(positional, default)]
+    pub kind: Option<String>,
+
+    /// Where the storage is: a git URL, a directory or an HTTP URL
+    #[facet(positional, default)]
+    pub location: Option<String>,
+}
+
+/// List the configured remotes, in priority order
+fn list_remotes() -> i32 {
+    let remotes = match ensure_cache_dir().and_then(|cache_dir| read_remotes(&cache_dir)) {
+        Ok(remotes) => remotes,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    if remotes.is_empty() {
+        println!(
+            "No remotes. Use '{}' to add one.",
+            style("unjust remote add <name> <kind> <location>").green()
+        );
+        return 0;
+    }
+
+    for (i, remote) in remotes.iter().enumerate() {
+        let access = if remote.read_only { ", read-only" } else { "" };
+        println!(
+            "{}. {} ({}{}) {}",
+            i + 1,
+            style(&remote.name).green(),
+            remote.kind,
+            access,
+            remote.location
+        );
+    }
+    0
+}
+
+/// Handle the "remote" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_remote_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let remote_args = match facet_args::from_slice::<RemoteArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let action = match remote_args.action.as_deref() {
+        None | Some("list") => return list_remotes(),
+        Some(action @ ("add" | "remove" | "priority")) => action,
+        Some(action) => {
+            eprintln!(
+                "{} Unknown remote action: {} (expected add, remove, priority or list)",
+                style("Error:").red().bold(),
+                action
+            );
+            return 1;
+        }
+    };
+
+    let Some(name) = remote_args.name else {
+        eprintln!("{} No remote name given", style("Error:").red().bold());
+        return 1;
+    };
+
+    if action == "priority" {
+        let Some(priority) = remote_args.kind.and_then(|p| p.parse().ok()) else {
+            eprintln!(
+                "{} Give the remote's new position, counting from 1 for the first to be used",
+                style("Error:").red().bold()
+            );
+            return 1;
+        };
+        return match ensure_cache_dir()
+            .and_then(|cache_dir| set_remote_priority(&cache_dir, &name, priority))
+        {
+            Ok(()) => list_remotes(),
+            Err(e) => {
+                eprintln!("{} {}", style("Error:").red().bold(), e);
+                1
+            }
+        };
+    }
+
+    if action == "remove" {
+        return match ensure_cache_dir().and_then(|cache_dir| remove_remote(&cache_dir, &name)) {
+            Ok(remote) => {
+                println!(
+                    "{} Removed remote {}",
+                    style("Success:").green().bold(),
+                    style(&remote.name).green()
+                );
+                0
+            }
+            Err(e) => {
+                eprintln!("{} {}", style("Error:").red().bold(), e);
+                1
+            }
+        };
+    }
+
+    add(name, remote_args.kind, remote_args.location, false)
+}
+
+/// Add a remote, checking its kind
+///
+/// Returns the exit code (0 for success, 1 for error)
+fn add(name: String, kind: Option<String>, location: Option<String>, read_only: bool) -> i32 {
+    let (Some(kind), Some(location)) = (kind, location) else {
+        eprintln!(
+            "{} Adding a remote needs a kind ({}) and a location",
+            style("Error:").red().bold(),
+            BACKEND_KINDS.join(", ")
+        );
+        return 1;
+    };
+    if !BACKEND_KINDS.contains(&kind.as_str()) {
+        eprintln!(
+            "{} Unknown storage kind: {} (expected one of {})",
+            style("Error:").red().bold(),
+            kind,
+            BACKEND_KINDS.join(", ")
+        );
+        return 1;
+    }
+
+    // Directories are kept absolute, so sync works from anywhere
+    let location = match kind.as_str() {
+        "dir" => match std::path::absolute(&location) {
+            Ok(path) => path.to_string_lossy().into_owned(),
+            Err(e) => {
+                eprintln!("{} {}", style("Error:").red().bold(), e);
+                return 1;
+            }
+        },
+        _ => location,
+    };
+
+    let remote = Remote {
+        name,
+        kind,
+        location,
+        read_only,
+    };
+    match ensure_cache_dir().and_then(|cache_dir| add_remote(&cache_dir, remote.clone())) {
+        Ok(()) if read_only => {
+            println!(
+                "{} Subscribed to {} at {}. Run '{}' to fetch its Justfiles",
+                style("Success:").green().bold(),
+                style(&remote.name).green(),
+                remote.location,
+                style("unjust sync").green()
+            );
+            0
+        }
+        Ok(()) => {
+            println!(
+                "{} Added {} remote {} at {}",
+                style("Success:").green().bold(),
+                remote.kind,
+                style(&remote.name).green(),
+                remote.location
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
+
+/// Arguments for the "subscribe" command
+#[derive(Facet, Debug)]
+pub struct SubscribeArgs {
+    /// Name for the remote, or a GitHub `username/repo` holding a collection
+    #[facet(positional, default)]
+    pub remote: Option<String>,
+
+    /// Kind of storage: git, dir or http
+    #[facet(positional, default)]
+    pub kind: Option<String>,
+
+    /// Where the storage is: a git URL, a directory or an HTTP URL
+    #[facet(positional, default)]
+    pub location: Option<String>,
+}
+
+/// Handle the "subscribe" command
+///
+/// Subscribing adds a read-only remote at the end of the priority order, so
+/// your own remotes' copies of an entry win over the subscription's.
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_subscribe_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let subscribe_args = match facet_args::from_slice::<SubscribeArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let Some(remote) = subscribe_args.remote else {
+        return list_subscriptions();
+    };
+
+    // A GitHub repository is named after its owner
+    if let Some((owner, repo)) = remote.split_once('/')
+        && subscribe_args.kind.is_none()
+    {
+        let location = format!("https://github.com/{owner}/{repo}.git");
+        return add(
+            owner.to_string(),
+            Some("git".to_string()),
+            Some(location),
+            true,
+        );
+    }
+    add(remote, subscribe_args.kind, subscribe_args.location, true)
+}
+
+/// List the read-only remotes
+fn list_subscriptions() -> i32 {
+    let remotes = match ensure_cache_dir().and_then(|cache_dir| read_remotes(&cache_dir)) {
+        Ok(remotes) => remotes,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let subscriptions: Vec<_> = remotes.iter().filter(|r| r.read_only).collect();
+    if subscriptions.is_empty() {
+        println!(
+            "No subscriptions. Use '{}' to add one.",
+            style("unjust subscribe <username/repo>").green()
+        );
+        return 0;
+    }
+    for remote in subscriptions {
+        println!(
+            "{} ({}) {}",
+            style(&remote.name).green(),
+            remote.kind,
+            remote.location
+        );
+    }
+    0
+}
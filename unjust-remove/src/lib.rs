@@ -0,0 +1,104 @@
+use console::style;
+use facet::Facet;
+use unjust_core::{empty_trash, ensure_cache_dir, remove_justfile, restore_removed};
+
+/// Arguments for the "remove" command
+#[derive(Facet, Debug)]
+pub struct RemoveArgs {
+    /// Repo identifier (username/repo)
+    #[facet(positional, default)]
+    pub repo: Option<String>,
+
+    /// Restore the most recently removed Justfile instead
+    #[facet(named)]
+    pub undo: bool,
+
+    /// Delete removed Justfiles for good, and the objects only they used
+    #[facet(named)]
+    pub empty_trash: bool,
+}
+
+/// Handle the "remove" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_remove_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let remove_args = match facet_args::from_slice::<RemoveArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let cache_dir = match ensure_cache_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    if remove_args.empty_trash {
+        return match empty_trash(&cache_dir) {
+            Ok(deleted) => {
+                println!(
+                    "{} Emptied the trash, deleting {} unused objects",
+                    style("Success:").green().bold(),
+                    deleted
+                );
+                0
+            }
+            Err(e) => {
+                eprintln!("{} {}", style("Error:").red().bold(), e);
+                1
+            }
+        };
+    }
+
+    // Get the repo name
+    let repo = match remove_args.repo.as_deref() {
+        Some(repo) => repo,
+        None => {
+            eprintln!("{} Repository not specified", style("Error:").red().bold());
+            return 1;
+        }
+    };
+
+    if remove_args.undo {
+        return match restore_removed(&cache_dir, repo) {
+            Ok(_) => {
+                println!(
+                    "{} Restored Justfile for: {}",
+                    style("Success:").green().bold(),
+                    style(repo).green()
+                );
+                0
+            }
+            Err(e) => {
+                eprintln!("{} {}", style("Error:").red().bold(), e);
+                1
+            }
+        };
+    }
+
+    match remove_justfile(&cache_dir, repo) {
+        Ok(_) => {
+            println!(
+                "{} Removed Justfile for: {}",
+                style("Success:").green().bold(),
+                style(repo).green()
+            );
+            println!(
+                "Run '{}' to undo, or '{}' to propagate the removal",
+                style(format!("unjust remove --undo {repo}")).green(),
+                style("unjust sync").green()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
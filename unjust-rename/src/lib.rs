@@ -0,0 +1,64 @@
+use console::style;
+use facet::Facet;
+use unjust_core::{ensure_cache_dir, rename_justfile};
+
+/// Arguments for the "rename" command
+#[derive(Facet, Debug)]
+pub struct RenameArgs {
+    /// Current repo identifier (username/repo)
+    #[facet(positional, default)]
+    pub from: Option<String>,
+
+    /// New repo identifier (username/repo)
+    #[facet(positional, default)]
+    pub to: Option<String>,
+}
+
+/// Handle the "rename" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_rename_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let rename_args = match facet_args::from_slice::<RenameArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let (from, to) = match (rename_args.from.as_deref(), rename_args.to.as_deref()) {
+        (Some(from), Some(to)) => (from, to),
+        _ => {
+            eprintln!(
+                "{} Both the current and new repository must be specified",
+                style("Error:").red().bold()
+            );
+            return 1;
+        }
+    };
+
+    let cache_dir = match ensure_cache_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    match rename_justfile(&cache_dir, from, to) {
+        Ok(_) => {
+            println!(
+                "{} Renamed {} to {}",
+                style("Success:").green().bold(),
+                style(from).green(),
+                style(to).green()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
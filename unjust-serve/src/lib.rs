@@ -0,0 +1,80 @@
+use console::style;
+use facet::Facet;
+use std::net::TcpListener;
+use std::path::Path;
+use unjust_storage::serve;
+
+/// Port to listen on when none is given
+const DEFAULT_PORT: u16 = 7766;
+
+/// Arguments for the "serve" command
+#[derive(Facet, Debug)]
+pub struct ServeArgs {
+    /// Storage directory to serve
+    #[facet(positional, default)]
+    pub dir: Option<String>,
+
+    /// Address to listen on (defaults to 127.0.0.1)
+    #[facet(named, default)]
+    pub bind: Option<String>,
+
+    /// Port to listen on (0 picks a free one)
+    #[facet(named, short = 'p', default)]
+    pub port: Option<u16>,
+}
+
+/// Handle the "serve" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_serve_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let serve_args = match facet_args::from_slice::<ServeArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    // Serving the store itself would let clients change it behind its back
+    let Some(dir) = serve_args.dir else {
+        eprintln!(
+            "{} No storage directory given. Create one to serve, such as {}",
+            style("Error:").red().bold(),
+            style("unjust serve ~/justfiles-registry").green()
+        );
+        return 1;
+    };
+
+    let bind = serve_args.bind.as_deref().unwrap_or("127.0.0.1");
+    let port = serve_args.port.unwrap_or(DEFAULT_PORT);
+    let (listener, address) = match TcpListener::bind((bind, port)).and_then(|listener| {
+        let address = listener.local_addr()?;
+        Ok((listener, address))
+    }) {
+        Ok(bound) => bound,
+        Err(e) => {
+            eprintln!(
+                "{} Couldn't listen on {}:{}: {}",
+                style("Error:").red().bold(),
+                bind,
+                port,
+                e
+            );
+            return 1;
+        }
+    };
+
+    println!(
+        "Serving {} at {}",
+        style(&dir).green(),
+        style(format!("http://{address}")).bold()
+    );
+    match serve(listener, Path::new(&dir)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use console::{StyledObject, style};
+use facet::Facet;
+use std::collections::BTreeSet;
+use std::io;
+use unjust_core::{
+    EntryState, EntryStatus, Remote, SyncState, ensure_cache_dir, entry_statuses, list_justfiles,
+    pending_changes, read_remotes, read_sync_state, remote_statuses,
+};
+
+/// Arguments for the "status" command
+#[derive(Facet, Debug)]
+pub struct StatusArgs {
+    /// Only show the status of this repo
+    #[facet(positional, default)]
+    pub repo: Option<String>,
+
+    /// Print one `state<TAB>repo<TAB>remote` line per entry, for scripts
+    #[facet(named)]
+    pub porcelain: bool,
+}
+
+/// Describe a state for people, styled by how much attention it needs
+fn describe(state: EntryState) -> StyledObject<&'static str> {
+    match state {
+        EntryState::Clean => style("clean").dim(),
+        EntryState::Modified => style("locally modified").yellow(),
+        EntryState::RemoteUpdated => style("remotely updated").cyan(),
+        EntryState::Conflicted => style("conflicted").red().bold(),
+        EntryState::New => style("new").green(),
+        EntryState::Deleted => style("deleted").red(),
+    }
+}
+
+/// Whether an entry is changed locally from a read-only remote's copy,
+/// which sync keeps as it is
+fn is_override(remote: Option<&Remote>, status: &EntryStatus) -> bool {
+    remote.is_some_and(|r| r.read_only)
+        && matches!(status.state, EntryState::Modified | EntryState::Deleted)
+}
+
+/// Compare every stored Justfile against the sync state of the remote
+/// providing it, pairing each status with that remote
+///
+/// Entries no remote provides yet are compared against the first remote,
+/// which is where a sync would push them.
+fn statuses(repo: Option<&str>) -> io::Result<Vec<(Option<Remote>, EntryStatus)>> {
+    let cache_dir = ensure_cache_dir()?;
+    let justfiles = list_justfiles()?;
+    let remotes = read_remotes(&cache_dir)?;
+
+    let mut statuses = Vec::new();
+    if remotes.is_empty() {
+        for status in entry_statuses(&justfiles, &SyncState::new())? {
+            statuses.push((None, status));
+        }
+    }
+    let mut seen = BTreeSet::new();
+    for remote in remotes {
+        let state = read_sync_state(&cache_dir, &remote.name)?;
+        for status in remote_statuses(&cache_dir, &justfiles, &remote.name, &state)? {
+            if seen.insert(status.repo.clone()) {
+                statuses.push((Some(remote.clone()), status));
+            }
+        }
+    }
+
+    statuses.retain(|(_, s)| repo.is_none_or(|repo| s.repo == repo));
+    statuses.sort_by(|(_, a), (_, b)| a.repo.cmp(&b.repo));
+    Ok(statuses)
+}
+
+/// Handle the "status" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_status_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let status_args = match facet_args::from_slice::<StatusArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let statuses = match statuses(status_args.repo.as_deref()) {
+        Ok(statuses) => statuses,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    if status_args.porcelain {
+        for (remote, status) in &statuses {
+            println!(
+                "{}\t{}\t{}",
+                status.state.as_str(),
+                status.repo,
+                remote.as_ref().map_or("-", |r| r.name.as_str())
+            );
+        }
+        return 0;
+    }
+
+    if statuses.is_empty() {
+        println!("No Justfiles in the store.");
+        return 0;
+    }
+
+    let width = statuses
+        .iter()
+        .map(|(_, s)| s.repo.len())
+        .max()
+        .unwrap_or(0);
+    for (remote, status) in &statuses {
+        let description = if is_override(remote.as_ref(), status) {
+            style("personal override").magenta()
+        } else {
+            describe(status.state)
+        };
+        let remote = remote
+            .as_ref()
+            .map(|r| format!(" ({})", r.name))
+            .unwrap_or_default();
+        println!(
+            "{}  {}{}",
+            style(format!("{:width$}", status.repo)).green(),
+            description,
+            style(remote).dim()
+        );
+    }
+
+    let pending = statuses
+        .iter()
+        .filter(|(remote, s)| s.state != EntryState::Clean && !is_override(remote.as_ref(), s))
+        .count();
+    if pending == 0 {
+        println!("\nEverything is in sync.");
+    } else {
+        println!(
+            "\n{} of {} entries differ from the remote. Run '{}' to sync them.",
+            pending,
+            statuses.len(),
+            style("unjust sync").green()
+        );
+    }
+
+    // Operations made offline wait in the log until a sync catches the
+    // remotes up with them
+    let queued = ensure_cache_dir().and_then(|cache_dir| pending_changes(&cache_dir));
+    if let Ok(queued) = queued.as_deref()
+        && !queued.is_empty()
+    {
+        println!("\nOperations queued for the next sync:");
+        for change in queued {
+            println!("  {}", change);
+        }
+    }
+
+    0
+}
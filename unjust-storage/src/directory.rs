@@ -0,0 +1,267 @@
+//! Plain directory storage
+//!
+//! Entries are kept in any directory (a network mount, a synced folder, a
+//! USB stick) in the same layout as the cache. An index file at the top
+//! records each entry's content hash, so listing doesn't have to read every
+//! entry. Changes are made under a lock file, with new files staged first
+//! and renamed into place, so two machines syncing at once can't leave an
+//! entry or the index half written.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use unjust_core::{FileLock, content_hash, entry_dir, write_atomic};
+
+use crate::StorageBackend;
+use crate::entry::{
+    EntryContent, delete_entry, entry_files, list_entries, read_entry, remove_leftover_files,
+    write_entry,
+};
+
+/// Name of the index file at the top of the storage directory
+const INDEX_FILE: &str = ".unjust-index";
+
+/// Name of the lock file at the top of the storage directory
+const LOCK_FILE: &str = ".unjust-lock";
+
+/// Name of the directory new entries are staged in
+const STAGING_DIR: &str = ".unjust-staging";
+
+/// How long to wait for another machine to finish with the directory
+const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Content hashes of the entries in a storage directory, along with a
+/// revision counter bumped on every change
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Index {
+    revision: u64,
+    entries: BTreeMap<String, String>,
+}
+
+impl Index {
+    fn to_text(&self) -> String {
+        let mut text = format!("# revision {}\n", self.revision);
+        for (repo, hash) in &self.entries {
+            text.push_str(&format!("{repo}\t{hash}\n"));
+        }
+        text
+    }
+
+    fn from_text(text: &str) -> Self {
+        let mut index = Index::default();
+        for line in text.lines() {
+            if let Some(revision) = line.strip_prefix("# revision ") {
+                index.revision = revision.trim().parse().unwrap_or_default();
+            } else if let Some((repo, hash)) = line.split_once('\t') {
+                index.entries.insert(repo.to_string(), hash.to_string());
+            }
+        }
+        index
+    }
+}
+
+/// A name for staging files that no other process will pick
+fn unique_name(prefix: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("{prefix}-{}-{nanos}", std::process::id())
+}
+
+/// Storage in a plain directory
+#[derive(Debug)]
+pub struct DirectoryBackend {
+    root: PathBuf,
+}
+
+impl DirectoryBackend {
+    /// Open a storage directory, which has to exist already
+    ///
+    /// Creating it here could quietly fill the mount point of a drive that
+    /// isn't mounted.
+    pub fn open(root: &Path) -> io::Result<Self> {
+        if !root.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Storage directory not found: {}", root.display()),
+            ));
+        }
+        Ok(Self {
+            root: root.to_path_buf(),
+        })
+    }
+
+    fn lock(&self) -> io::Result<FileLock> {
+        FileLock::acquire(&self.root.join(LOCK_FILE), LOCK_TIMEOUT)
+    }
+
+    /// Read the index, building it from the entries if there isn't one yet
+    ///
+    /// A built index is only written out by the next change, so reading
+    /// never leaves files behind in the directory.
+    fn read_index(&self) -> io::Result<Index> {
+        match fs::read_to_string(self.root.join(INDEX_FILE)) {
+            Ok(text) => Ok(Index::from_text(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Index {
+                revision: 0,
+                entries: list_entries(&self.root)?,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write_index(&self, index: &Index) -> io::Result<()> {
+        write_atomic(&self.root.join(INDEX_FILE), index.to_text())
+    }
+
+    /// Apply a change to the index
+    fn update_index(&self, change: impl FnOnce(&mut BTreeMap<String, String>)) -> io::Result<()> {
+        let mut index = self.read_index()?;
+        change(&mut index.entries);
+        index.revision += 1;
+        self.write_index(&index)
+    }
+}
+
+impl StorageBackend for DirectoryBackend {
+    fn list(&self) -> io::Result<BTreeMap<String, String>> {
+        let _lock = self.lock()?;
+        Ok(self.read_index()?.entries)
+    }
+
+    fn fetch(&self, repo: &str) -> io::Result<EntryContent> {
+        let _lock = self.lock()?;
+        read_entry(&entry_dir(&self.root, repo)?)
+    }
+
+    fn put(&self, repo: &str, content: &EntryContent) -> io::Result<String> {
+        let dir = entry_dir(&self.root, repo)?;
+        let _lock = self.lock()?;
+
+        // Stage the whole entry first, so a failed write leaves the old one
+        let staging = self.root.join(STAGING_DIR).join(unique_name("entry"));
+        fs::create_dir_all(&staging)?;
+        let staged = write_entry(&staging, content).and_then(|justfile| content_hash(&justfile));
+        let hash = match staged {
+            Ok(hash) => hash,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(e);
+            }
+        };
+
+        // Staged files replace old ones in place before the rest are removed,
+        // so a crash part way leaves the entry with a Justfile
+        fs::create_dir_all(&dir)?;
+        let leftover = entry_files(&dir)?;
+        for name in content.files.keys() {
+            let target = dir.join(name);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(staging.join(name), target)?;
+        }
+        remove_leftover_files(&dir, leftover, content)?;
+        fs::remove_dir_all(&staging)?;
+
+        self.update_index(|entries| {
+            entries.insert(repo.to_string(), hash.clone());
+        })?;
+        Ok(hash)
+    }
+
+    fn delete(&self, repo: &str) -> io::Result<()> {
+        let _lock = self.lock()?;
+        delete_entry(&self.root, repo)?;
+        self.update_index(|entries| {
+            entries.remove(repo);
+        })
+    }
+
+    fn revision(&self) -> io::Result<Option<String>> {
+        let _lock = self.lock()?;
+        Ok(Some(self.read_index()?.revision.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use tempfile::TempDir;
+
+    fn entry(content: &str) -> EntryContent {
+        EntryContent {
+            files: BTreeMap::from([("Justfile".to_string(), content.as_bytes().to_vec())]),
+        }
+    }
+
+    #[test]
+    fn test_directory_backend_round_trip() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        assert!(DirectoryBackend::open(&root.join("missing")).is_err());
+
+        // Entries already in the directory are indexed on first use
+        fs::create_dir_all(root.join("user/existing"))?;
+        fs::write(root.join("user/existing/Justfile"), "old:\n")?;
+
+        let backend = DirectoryBackend::open(root)?;
+        assert_eq!(backend.list()?.len(), 1);
+        assert_eq!(backend.revision()?.as_deref(), Some("0"));
+        assert!(!root.join(INDEX_FILE).exists());
+
+        let mut with_module = entry("mod ci\nbuild:\n");
+        with_module
+            .files
+            .insert("ci.just".to_string(), b"test:\n".to_vec());
+        backend.put("user/repo", &with_module)?;
+        assert!(root.join("user/repo/ci.just").is_file());
+        assert!(root.join(INDEX_FILE).is_file());
+
+        let hash = backend.put("user/repo", &entry("build:\n"))?;
+        assert!(!root.join("user/repo/ci.just").exists());
+        assert_eq!(backend.list()?["user/repo"], hash);
+        assert_eq!(backend.fetch("user/repo")?, entry("build:\n"));
+        assert_eq!(backend.revision()?.as_deref(), Some("2"));
+
+        let replaced = backend.put("user/repo", &entry("test:\n"))?;
+        assert_ne!(replaced, hash);
+        assert_eq!(backend.fetch("user/repo")?, entry("test:\n"));
+
+        backend.delete("user/existing")?;
+        assert_eq!(backend.list()?.keys().collect::<Vec<_>>(), ["user/repo"]);
+        assert!(!root.join("user/existing").exists());
+        assert!(!root.join(STAGING_DIR).read_dir()?.any(|_| true));
+        assert!(!root.join(LOCK_FILE).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_puts_keep_the_index_whole() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let backend = Arc::new(DirectoryBackend::open(temp_dir.path())?);
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let backend = Arc::clone(&backend);
+                thread::spawn(move || backend.put(&format!("user/repo{i}"), &entry("build:\n")))
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+
+        let listing = backend.list()?;
+        assert_eq!(listing.len(), 8);
+        assert_eq!(backend.revision()?.as_deref(), Some("8"));
+
+        Ok(())
+    }
+}
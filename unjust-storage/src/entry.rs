@@ -0,0 +1,222 @@
+//! Moving store entries in and out of directories
+//!
+//! Backends that keep entries as files use the same layout as the cache, so
+//! these work on the cache and on remote copies alike.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use unjust_core::{
+    MANIFEST_FILE, content_hash, entry_dir, justfile_files, justfile_in_dir, justfiles_in,
+    write_atomic,
+};
+
+/// The files making up an entry: its Justfile, everything the Justfile
+/// includes, and its manifest
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryContent {
+    /// File contents keyed by path relative to the entry, using `/`
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+/// Join the components of a relative path with `/`
+fn relative_name(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Check that a file name from an entry stays inside the entry and out of
+/// the hidden files unjust keeps alongside it
+fn checked_path(entry_dir: &Path, name: &str) -> io::Result<PathBuf> {
+    let path = Path::new(name);
+    let valid = name == MANIFEST_FILE
+        || path.components().all(|c| match c {
+            Component::Normal(part) => !part.to_string_lossy().starts_with('.'),
+            _ => false,
+        });
+    if !valid || name.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid file name in entry: {name}"),
+        ));
+    }
+    Ok(entry_dir.join(path))
+}
+
+/// List an entry's files, if it has a Justfile
+pub(crate) fn entry_files(entry_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let Some(justfile) = justfile_in_dir(entry_dir)? else {
+        return Ok(Vec::new());
+    };
+    let mut files = justfile_files(&justfile)?;
+    let manifest = entry_dir.join(MANIFEST_FILE);
+    if manifest.exists() {
+        files.push(manifest);
+    }
+    Ok(files)
+}
+
+/// Remove an entry's files, and any directories that leaves empty
+pub(crate) fn remove_entry_files(entry_dir: &Path) -> io::Result<()> {
+    remove_files(entry_dir, entry_files(entry_dir)?)
+}
+
+/// Remove the files of an entry's old version that aren't part of `content`
+pub(crate) fn remove_leftover_files(
+    entry_dir: &Path,
+    mut leftover: Vec<PathBuf>,
+    content: &EntryContent,
+) -> io::Result<()> {
+    leftover.retain(|file| !content.files.contains_key(&relative_name(entry_dir, file)));
+    remove_files(entry_dir, leftover)
+}
+
+/// Remove files from an entry directory along with any directories they
+/// leave empty
+fn remove_files(entry_dir: &Path, files: Vec<PathBuf>) -> io::Result<()> {
+    for file in files {
+        fs::remove_file(&file)?;
+        let mut dir = file.parent();
+        while let Some(parent) = dir.filter(|d| *d != entry_dir) {
+            if fs::remove_dir(parent).is_err() {
+                break;
+            }
+            dir = parent.parent();
+        }
+    }
+    Ok(())
+}
+
+/// Read the entry kept in a directory
+pub fn read_entry(entry_dir: &Path) -> io::Result<EntryContent> {
+    let files = entry_files(entry_dir)?;
+    if files.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No Justfile in {}", entry_dir.display()),
+        ));
+    }
+
+    let mut content = EntryContent::default();
+    for file in files {
+        content
+            .files
+            .insert(relative_name(entry_dir, &file), fs::read(&file)?);
+    }
+    Ok(content)
+}
+
+/// Write an entry into a directory, replacing the entry already there
+///
+/// Returns the path of the written Justfile.
+pub fn write_entry(entry_dir: &Path, content: &EntryContent) -> io::Result<PathBuf> {
+    let targets = content
+        .files
+        .iter()
+        .map(|(name, bytes)| Ok((checked_path(entry_dir, name)?, bytes)))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    // New files replace old ones in place before the rest are removed, so
+    // the entry is never left without a Justfile
+    let leftover = entry_files(entry_dir)?;
+    for (target, bytes) in &targets {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(target, bytes)?;
+    }
+    remove_leftover_files(entry_dir, leftover, content)?;
+
+    justfile_in_dir(entry_dir)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Entry has no Justfile: {}", entry_dir.display()),
+        )
+    })
+}
+
+/// Remove an entry from a directory laid out like the cache
+///
+/// Directories left empty are removed, up to `root`.
+pub fn delete_entry(root: &Path, repo: &str) -> io::Result<()> {
+    let dir = entry_dir(root, repo)?;
+    remove_entry_files(&dir)?;
+
+    let mut current = Some(dir.as_path());
+    while let Some(dir) = current.filter(|d| *d != root) {
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+    Ok(())
+}
+
+/// List the entries in a directory laid out like the cache, with their
+/// content hashes
+pub fn list_entries(root: &Path) -> io::Result<BTreeMap<String, String>> {
+    justfiles_in(root)?
+        .into_iter()
+        .map(|justfile| Ok((justfile.repo_name, content_hash(&justfile.path)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_entry_round_trip() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        let source = root.join("user/repo");
+        fs::create_dir_all(source.join("ci"))?;
+        fs::write(source.join("justfile"), "import 'common.just'\nmod ci\n")?;
+        fs::write(source.join("common.just"), "build:\n")?;
+        fs::write(source.join("ci/mod.just"), "lint:\n")?;
+        fs::write(source.join(MANIFEST_FILE), "tags = [\"rust\"]\n")?;
+        fs::write(source.join("notes.txt"), "not part of the entry\n")?;
+
+        let content = read_entry(&source)?;
+        let names: Vec<&str> = content.files.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [MANIFEST_FILE, "ci/mod.just", "common.just", "justfile"]
+        );
+
+        let target = root.join("copy/repo");
+        fs::create_dir_all(&target)?;
+        fs::write(target.join("Justfile"), "old:\n")?;
+        let path = write_entry(&target, &content)?;
+        assert_eq!(path, target.join("justfile"));
+        assert_eq!(read_entry(&target)?, content);
+
+        let listing = list_entries(root)?;
+        assert_eq!(listing["user/repo"], listing["copy/repo"]);
+
+        delete_entry(root, "copy/repo")?;
+        assert!(!root.join("copy").exists());
+        assert!(source.join("notes.txt").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_entry_rejects_escaping_paths() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        for name in ["../Justfile", "/etc/passwd", ".revisions/1/Justfile", ""] {
+            let mut content = EntryContent::default();
+            content.files.insert(name.to_string(), b"x".to_vec());
+            let err = write_entry(temp_dir.path(), &content).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{name}");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+//! Git repository storage
+//!
+//! Entries are kept in a git repository in the same layout as the cache. A
+//! clone of it is kept in the cache, changes are committed there and pushed
+//! when the sync finishes.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use unjust_core::{content_hash, entry_dir};
+
+use crate::StorageBackend;
+use crate::entry::{EntryContent, delete_entry, list_entries, read_entry, write_entry};
+
+/// Storage in a git repository
+#[derive(Debug)]
+pub struct GitBackend {
+    url: String,
+    checkout: PathBuf,
+}
+
+/// Run git in `dir`, returning its trimmed stdout
+fn git(dir: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git").args(args).current_dir(dir).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl GitBackend {
+    /// Open the repository at `url`, cloning it into `checkout` or bringing
+    /// an existing clone up to date
+    pub fn open(url: &str, checkout: &Path) -> io::Result<Self> {
+        if checkout.join(".git").exists() {
+            git(checkout, &["fetch", "--quiet", "origin"])?;
+            // A repository that was empty when cloned has no upstream yet
+            if git(checkout, &["rev-parse", "--verify", "--quiet", "@{u}"]).is_ok() {
+                git(checkout, &["reset", "--quiet", "--hard", "@{u}"])?;
+            }
+        } else {
+            let parent = checkout.parent().unwrap_or(Path::new("."));
+            fs::create_dir_all(parent)?;
+            let target = checkout.to_string_lossy();
+            git(parent, &["clone", "--quiet", url, &target])?;
+        }
+
+        Ok(Self {
+            url: url.to_string(),
+            checkout: checkout.to_path_buf(),
+        })
+    }
+
+    /// Get the URL of the repository
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl StorageBackend for GitBackend {
+    fn list(&self) -> io::Result<BTreeMap<String, String>> {
+        list_entries(&self.checkout)
+    }
+
+    fn fetch(&self, repo: &str) -> io::Result<EntryContent> {
+        read_entry(&entry_dir(&self.checkout, repo)?)
+    }
+
+    fn put(&self, repo: &str, content: &EntryContent) -> io::Result<String> {
+        let dir = entry_dir(&self.checkout, repo)?;
+        fs::create_dir_all(&dir)?;
+        let justfile = write_entry(&dir, content)?;
+        content_hash(&justfile)
+    }
+
+    fn delete(&self, repo: &str) -> io::Result<()> {
+        delete_entry(&self.checkout, repo)
+    }
+
+    fn revision(&self) -> io::Result<Option<String>> {
+        Ok(git(
+            &self.checkout,
+            &["rev-parse", "--verify", "--quiet", "HEAD"],
+        )
+        .ok())
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        if git(&self.checkout, &["status", "--porcelain"])?.is_empty() {
+            return Ok(());
+        }
+
+        git(&self.checkout, &["add", "--all"])?;
+        // Fall back to a generic identity where git has none configured
+        let mut commit = vec!["commit", "--quiet", "-m", "Sync from unjust"];
+        if git(&self.checkout, &["config", "user.email"]).is_err() {
+            commit.splice(
+                0..0,
+                [
+                    "-c",
+                    "user.name=unjust",
+                    "-c",
+                    "user.email=unjust@localhost",
+                ],
+            );
+        }
+        git(&self.checkout, &commit)?;
+        git(
+            &self.checkout,
+            &["push", "--quiet", "--set-upstream", "origin", "HEAD"],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(content: &str) -> EntryContent {
+        EntryContent {
+            files: BTreeMap::from([("Justfile".to_string(), content.as_bytes().to_vec())]),
+        }
+    }
+
+    #[test]
+    fn test_git_backend_round_trip() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let remote = temp_dir.path().join("remote.git");
+        fs::create_dir_all(&remote)?;
+        git(&remote, &["init", "--quiet", "--bare"])?;
+        let url = remote.to_string_lossy();
+
+        let first = GitBackend::open(&url, &temp_dir.path().join("first"))?;
+        assert!(first.list()?.is_empty());
+        assert_eq!(first.revision()?, None);
+        let hash = first.put("user/repo", &entry("build:\n"))?;
+        first.put("user/other//sub", &entry("test:\n"))?;
+        first.finish()?;
+        let revision = first.revision()?;
+        assert!(revision.is_some());
+
+        let second = GitBackend::open(&url, &temp_dir.path().join("second"))?;
+        assert_eq!(second.revision()?, revision);
+        let listing = second.list()?;
+        assert_eq!(listing.len(), 2);
+        assert_eq!(listing["user/repo"], hash);
+        assert_eq!(second.fetch("user/repo")?, entry("build:\n"));
+
+        second.delete("user/repo")?;
+        second.finish()?;
+
+        let first = GitBackend::open(&url, &temp_dir.path().join("first"))?;
+        assert_eq!(
+            first.list()?.keys().collect::<Vec<_>>(),
+            ["user/other//sub"]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,650 @@
+//! HTTP storage
+//!
+//! A small protocol for keeping entries on a server, with paths relative to
+//! the remote's base URL:
+//!
+//! - `GET /index` lists the entries as `repo<TAB>hash` lines. The `ETag` is
+//!   the revision of the store as a whole.
+//! - `GET /entries/<repo>` gets an entry's files, with its content hash as
+//!   the `ETag`.
+//! - `PUT /entries/<repo>` stores an entry's files. With `If-Match: "<hash>"`
+//!   only an entry with that hash is replaced, and with `If-None-Match: *`
+//!   the entry is only created if there isn't one. Anything else gets
+//!   `412 Precondition Failed`. The response `ETag` is the new hash.
+//! - `DELETE /entries/<repo>` removes an entry, taking `If-Match` the same
+//!   way.
+//!
+//! Repository ids are percent-encoded into a single path segment. Entry
+//! bodies hold each file as a `<length> <name>` line followed by that many
+//! bytes. A `PUT` must give its `Content-Length` (otherwise `411 Length
+//! Required`), and bodies over 16 MiB get `413 Payload Too Large`. Other
+//! requests carry no body. Only plain `http://` URLs are supported, so put a
+//! TLS proxy in front of a server that isn't on a trusted network.
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use crate::StorageBackend;
+use crate::directory::DirectoryBackend;
+use crate::entry::EntryContent;
+
+/// Largest request body the server accepts
+const MAX_BODY: u64 = 16 * 1024 * 1024;
+
+/// How long a connection can stall reading or writing before it's dropped
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Most connections the server handles at once
+const MAX_CONNECTIONS: usize = 32;
+
+/// Encode an entry's files as a request or response body
+pub fn encode_entry(content: &EntryContent) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (name, bytes) in &content.files {
+        body.extend_from_slice(format!("{} {name}\n", bytes.len()).as_bytes());
+        body.extend_from_slice(bytes);
+    }
+    body
+}
+
+/// Decode an entry's files from a body written by [`encode_entry`]
+pub fn decode_entry(mut body: &[u8]) -> io::Result<EntryContent> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid entry body");
+    let mut content = EntryContent::default();
+    while !body.is_empty() {
+        let newline = body.iter().position(|&b| b == b'\n').ok_or_else(invalid)?;
+        let header = std::str::from_utf8(&body[..newline]).map_err(|_| invalid())?;
+        let (length, name) = header.split_once(' ').ok_or_else(invalid)?;
+        let length: usize = length.parse().map_err(|_| invalid())?;
+        let rest = &body[newline + 1..];
+        if rest.len() < length {
+            return Err(invalid());
+        }
+        content
+            .files
+            .insert(name.to_string(), rest[..length].to_vec());
+        body = &rest[length..];
+    }
+    Ok(content)
+}
+
+/// Percent-encode a repository id into a single path segment
+fn encode_segment(repo: &str) -> String {
+    repo.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Decode a percent-encoded path segment
+fn decode_segment(segment: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut rest = segment.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// An HTTP request or response
+#[derive(Debug, Default)]
+struct Message {
+    /// The request line or status line
+    start: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Message {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Read the start line and headers, leaving the body to be read
+    fn read_head(reader: &mut impl BufRead) -> io::Result<Self> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let mut message = Message {
+            start: line.trim_end().to_string(),
+            ..Message::default()
+        };
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                message
+                    .headers
+                    .push((key.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        Ok(message)
+    }
+
+    fn content_length(&self) -> io::Result<Option<u64>> {
+        self.header("Content-Length")
+            .map(|length| {
+                length.parse().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "Invalid Content-Length")
+                })
+            })
+            .transpose()
+    }
+
+    /// Read a body of the given length, only holding what actually arrives
+    fn read_body(&mut self, reader: &mut impl BufRead, length: u64) -> io::Result<()> {
+        reader.take(length).read_to_end(&mut self.body)?;
+        if self.body.len() as u64 != length {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Body is shorter than its Content-Length",
+            ));
+        }
+        Ok(())
+    }
+
+    fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut head = format!("{}\r\n", self.start);
+        for (key, value) in &self.headers {
+            head.push_str(&format!("{key}: {value}\r\n"));
+        }
+        head.push_str(&format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            self.body.len()
+        ));
+        writer.write_all(head.as_bytes())?;
+        writer.write_all(&self.body)?;
+        writer.flush()
+    }
+}
+
+/// Quote a hash for an `ETag` or `If-Match` header
+fn quoted(hash: &str) -> String {
+    format!("\"{hash}\"")
+}
+
+/// Storage on a server speaking the unjust HTTP protocol
+#[derive(Debug)]
+pub struct HttpBackend {
+    /// Host and port to connect to
+    address: String,
+
+    /// Path prefix of the remote, without a trailing slash
+    prefix: String,
+
+    /// Entry hashes as last seen on the server, used as preconditions
+    known: Mutex<BTreeMap<String, String>>,
+}
+
+impl HttpBackend {
+    /// Open a remote at an `http://host[:port][/path]` URL
+    pub fn open(url: &str) -> io::Result<Self> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Only http:// URLs are supported: {url}"),
+            )
+        })?;
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let address = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{host}:80")
+        };
+        Ok(Self {
+            address,
+            prefix: format!("/{path}").trim_end_matches('/').to_string(),
+            known: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        path: &str,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    ) -> io::Result<(u16, Message)> {
+        let mut stream = TcpStream::connect(&self.address)?;
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let mut request = Message {
+            start: format!("{method} {}{path} HTTP/1.1", self.prefix),
+            headers,
+            body,
+        };
+        request
+            .headers
+            .push(("Host".to_string(), self.address.clone()));
+        request.write(&mut stream)?;
+
+        let mut reader = BufReader::new(stream);
+        let mut response = Message::read_head(&mut reader)?;
+        match response.content_length()? {
+            Some(length) => response.read_body(&mut reader, length)?,
+            // Without a length the body runs until the server closes
+            None => {
+                reader.read_to_end(&mut response.body)?;
+            }
+        }
+        let status = response
+            .start
+            .split(' ')
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid response from {}: {}", self.address, response.start),
+                )
+            })?;
+
+        match status {
+            200..300 => Ok((status, response)),
+            404 => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Not found on the server: {path}"),
+            )),
+            412 => Err(io::Error::other(format!(
+                "{path} changed on the server since it was listed, sync again"
+            ))),
+            _ => Err(io::Error::other(format!(
+                "{method} {path} failed: {} {}",
+                response.start,
+                String::from_utf8_lossy(&response.body).trim()
+            ))),
+        }
+    }
+
+    /// The precondition for changing an entry, based on its last seen hash
+    fn precondition(&self, repo: &str) -> (String, String) {
+        match self
+            .known
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(repo)
+        {
+            Some(hash) => ("If-Match".to_string(), quoted(hash)),
+            None => ("If-None-Match".to_string(), "*".to_string()),
+        }
+    }
+}
+
+/// Get the unquoted `ETag` of a response
+fn etag(response: &Message) -> io::Result<String> {
+    response
+        .header("ETag")
+        .map(|tag| tag.trim_matches('"').to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Response has no ETag"))
+}
+
+impl StorageBackend for HttpBackend {
+    fn list(&self) -> io::Result<BTreeMap<String, String>> {
+        let (_, response) = self.request("GET", "/index", Vec::new(), Vec::new())?;
+        let listing: BTreeMap<String, String> = String::from_utf8_lossy(&response.body)
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(repo, hash)| (repo.to_string(), hash.to_string()))
+            .collect();
+        *self.known.lock().unwrap_or_else(PoisonError::into_inner) = listing.clone();
+        Ok(listing)
+    }
+
+    fn fetch(&self, repo: &str) -> io::Result<EntryContent> {
+        let path = format!("/entries/{}", encode_segment(repo));
+        let (_, response) = self.request("GET", &path, Vec::new(), Vec::new())?;
+        decode_entry(&response.body)
+    }
+
+    fn put(&self, repo: &str, content: &EntryContent) -> io::Result<String> {
+        let path = format!("/entries/{}", encode_segment(repo));
+        let headers = vec![self.precondition(repo)];
+        let (_, response) = self.request("PUT", &path, headers, encode_entry(content))?;
+        let hash = etag(&response)?;
+        self.known
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(repo.to_string(), hash.clone());
+        Ok(hash)
+    }
+
+    fn delete(&self, repo: &str) -> io::Result<()> {
+        let path = format!("/entries/{}", encode_segment(repo));
+        let headers = vec![self.precondition(repo)];
+        self.request("DELETE", &path, headers, Vec::new())?;
+        self.known
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(repo);
+        Ok(())
+    }
+
+    fn revision(&self) -> io::Result<Option<String>> {
+        let (_, response) = self.request("GET", "/index", Vec::new(), Vec::new())?;
+        Ok(etag(&response).ok())
+    }
+}
+
+/// A response status, `ETag` and body
+type Reply = (u16, Option<String>, Vec<u8>);
+
+fn error_reply(e: io::Error) -> Reply {
+    let status = match e.kind() {
+        io::ErrorKind::NotFound => 404,
+        io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => 400,
+        _ => 500,
+    };
+    (status, None, e.to_string().into_bytes())
+}
+
+/// Check a request's preconditions against an entry's current hash
+fn preconditions_hold(request: &Message, current: Option<&String>) -> bool {
+    let if_match = request.header("If-Match").map(|tag| tag.trim_matches('"'));
+    let if_none_match = request.header("If-None-Match");
+    if_match.is_none_or(|tag| current.is_some_and(|hash| hash == tag))
+        && if_none_match.is_none_or(|_| current.is_none())
+}
+
+/// Answer one request from a store directory
+fn respond(request: &Message, store: &Mutex<DirectoryBackend>) -> Reply {
+    let mut parts = request.start.split(' ');
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+
+    // Checking preconditions and changing an entry happen as one step
+    let store = store.lock().unwrap_or_else(PoisonError::into_inner);
+    let result = (|| -> io::Result<Reply> {
+        if path == "/index" {
+            if method != "GET" {
+                return Ok((405, None, Vec::new()));
+            }
+            let body: String = store
+                .list()?
+                .iter()
+                .map(|(repo, hash)| format!("{repo}\t{hash}\n"))
+                .collect();
+            return Ok((200, store.revision()?, body.into_bytes()));
+        }
+
+        let Some(repo) = path.strip_prefix("/entries/").and_then(decode_segment) else {
+            return Ok((404, None, Vec::new()));
+        };
+        let current = store.list()?.remove(&repo);
+        match method {
+            "GET" => match current {
+                Some(hash) => Ok((200, Some(hash), encode_entry(&store.fetch(&repo)?))),
+                None => Ok((404, None, Vec::new())),
+            },
+            "PUT" if !preconditions_hold(request, current.as_ref()) => Ok((412, None, Vec::new())),
+            "PUT" => {
+                let content = decode_entry(&request.body)?;
+                let hash = store.put(&repo, &content)?;
+                let status = if current.is_some() { 200 } else { 201 };
+                Ok((status, Some(hash), Vec::new()))
+            }
+            "DELETE" if current.is_none() => Ok((404, None, Vec::new())),
+            "DELETE" if !preconditions_hold(request, current.as_ref()) => {
+                Ok((412, None, Vec::new()))
+            }
+            "DELETE" => {
+                store.delete(&repo)?;
+                Ok((204, None, Vec::new()))
+            }
+            _ => Ok((405, None, Vec::new())),
+        }
+    })();
+
+    result.unwrap_or_else(error_reply)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        412 => "Precondition Failed",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+fn handle(stream: TcpStream, store: &Mutex<DirectoryBackend>) -> io::Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = Message::read_head(&mut reader)?;
+    let is_put = request.start.starts_with("PUT ");
+    let (status, tag, body) = match request.content_length() {
+        Err(e) => error_reply(e),
+        Ok(Some(length)) if length > MAX_BODY => (413, None, b"Request body is too large".to_vec()),
+        Ok(None) if is_put => (411, None, b"Content-Length is required".to_vec()),
+        // Requests other than PUT don't need a body
+        Ok(length) => match request.read_body(&mut reader, length.unwrap_or(0)) {
+            Ok(()) => respond(&request, store),
+            Err(e) => (
+                400,
+                None,
+                format!("Could not read the request body: {e}").into_bytes(),
+            ),
+        },
+    };
+    let mut response = Message {
+        start: format!("HTTP/1.1 {status} {}", reason(status)),
+        headers: Vec::new(),
+        body,
+    };
+    if let Some(tag) = tag {
+        response.headers.push(("ETag".to_string(), quoted(&tag)));
+    }
+    response.write(&mut &stream)
+}
+
+/// Count of the connections being handled, kept to [`MAX_CONNECTIONS`]
+#[derive(Default)]
+struct Slots {
+    active: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl Slots {
+    /// Wait for a free slot and take it, until the returned guard is dropped
+    fn acquire(self: &Arc<Self>) -> Slot {
+        let mut active = self.active.lock().unwrap_or_else(PoisonError::into_inner);
+        while *active >= MAX_CONNECTIONS {
+            active = self
+                .freed
+                .wait(active)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *active += 1;
+        Slot(Arc::clone(self))
+    }
+}
+
+/// A connection's slot, given back when dropped
+struct Slot(Arc<Slots>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        *self.0.active.lock().unwrap_or_else(PoisonError::into_inner) -= 1;
+        self.0.freed.notify_one();
+    }
+}
+
+/// Serve the entries in a store directory over the unjust HTTP protocol
+///
+/// Each connection is handled on its own thread, up to a limit, after which
+/// new connections wait to be accepted. This only returns if accepting a
+/// connection fails.
+pub fn serve(listener: TcpListener, root: &Path) -> io::Result<()> {
+    let store = Arc::new(Mutex::new(DirectoryBackend::open(root)?));
+    let slots = Arc::new(Slots::default());
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let slot = slots.acquire();
+        let store = Arc::clone(&store);
+        thread::spawn(move || {
+            let _slot = slot;
+            // A client that hangs up early only affects its own request
+            let _ = handle(stream, &store);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Shutdown;
+    use tempfile::TempDir;
+
+    fn entry(files: &[(&str, &str)]) -> EntryContent {
+        EntryContent {
+            files: files
+                .iter()
+                .map(|(name, content)| (name.to_string(), content.as_bytes().to_vec()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_entry_and_segment_encoding() -> io::Result<()> {
+        let content = entry(&[("Justfile", "build:\n"), ("ci/mod.just", "")]);
+        assert_eq!(decode_entry(&encode_entry(&content))?, content);
+        assert!(decode_entry(b"99 Justfile\nshort").is_err());
+
+        let repo = "user/repo//packages/api";
+        assert_eq!(encode_segment(repo), "user%2Frepo%2F%2Fpackages%2Fapi");
+        assert_eq!(decode_segment(&encode_segment(repo)).as_deref(), Some(repo));
+        assert_eq!(decode_segment("bad%2"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_http_backend_against_server() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path().to_path_buf();
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/", listener.local_addr()?);
+        thread::spawn(move || serve(listener, &root));
+
+        let first = HttpBackend::open(&url)?;
+        let second = HttpBackend::open(&url)?;
+        assert!(first.list()?.is_empty());
+        assert!(second.list()?.is_empty());
+
+        let hash = first.put("user/repo//sub", &entry(&[("Justfile", "build:\n")]))?;
+        assert_eq!(first.list()?["user/repo//sub"], hash);
+        assert_eq!(
+            first.fetch("user/repo//sub")?,
+            entry(&[("Justfile", "build:\n")])
+        );
+        assert!(
+            temp_dir
+                .path()
+                .join("user/repo/.subpaths/sub/Justfile")
+                .exists()
+        );
+
+        // The second client hasn't seen the entry, so can't overwrite it
+        let err = second
+            .put("user/repo//sub", &entry(&[("Justfile", "test:\n")]))
+            .unwrap_err();
+        assert!(err.to_string().contains("changed on the server"));
+        second.list()?;
+        second.put("user/repo//sub", &entry(&[("Justfile", "test:\n")]))?;
+
+        // Now the first client's view is out of date
+        assert!(first.delete("user/repo//sub").is_err());
+        first.list()?;
+        first.delete("user/repo//sub")?;
+        assert!(first.list()?.is_empty());
+        assert_eq!(
+            first.fetch("user/repo").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(first.revision()?.as_deref(), Some("3"));
+
+        assert!(HttpBackend::open("https://example.com").is_err());
+
+        Ok(())
+    }
+
+    /// Send a raw request and get the status line of the reply
+    fn raw_request(address: &str, request: &str) -> io::Result<String> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        stream.write_all(request.as_bytes())?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        Ok(line.trim_end().to_string())
+    }
+
+    #[test]
+    fn test_server_checks_request_bodies() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path().to_path_buf();
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?.to_string();
+        thread::spawn(move || serve(listener, &root));
+
+        // A GET without a body is answered straight away
+        let reply = raw_request(&address, "GET /index HTTP/1.1\r\nHost: x\r\n\r\n")?;
+        assert_eq!(reply, "HTTP/1.1 200 OK");
+
+        let reply = raw_request(
+            &address,
+            "PUT /entries/user%2Frepo HTTP/1.1\r\nContent-Length: 999999999999999\r\n\r\n",
+        )?;
+        assert_eq!(reply, "HTTP/1.1 413 Payload Too Large");
+        let reply = raw_request(&address, "PUT /entries/user%2Frepo HTTP/1.1\r\n\r\n")?;
+        assert_eq!(reply, "HTTP/1.1 411 Length Required");
+
+        // A body cut short still gets an answer
+        let mut stream = TcpStream::connect(&address)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        stream.write_all(b"PUT /entries/user%2Frepo HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc")?;
+        stream.shutdown(Shutdown::Write)?;
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply)?;
+        assert_eq!(reply.trim_end(), "HTTP/1.1 400 Bad Request");
+
+        // The server is still up
+        assert!(
+            HttpBackend::open(&format!("http://{address}"))?
+                .list()?
+                .is_empty()
+        );
+
+        Ok(())
+    }
+}
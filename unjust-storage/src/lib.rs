@@ -0,0 +1,81 @@
+#![warn(missing_docs)]
+#![forbid(unsafe_code)]
+//! Remote storage for the unjust tool
+//!
+//! Sync talks to remote storage through the [`StorageBackend`] trait, so it
+//! doesn't need to know where entries are kept. Each configured remote picks
+//! its backend by kind.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use unjust_core::Remote;
+
+mod directory;
+mod entry;
+mod git;
+mod http;
+mod merge;
+
+pub use directory::DirectoryBackend;
+pub use entry::{EntryContent, delete_entry, list_entries, read_entry, write_entry};
+pub use git::GitBackend;
+pub use http::{HttpBackend, decode_entry, encode_entry, serve};
+pub use merge::merge_entries;
+
+/// Kinds of remote storage there are backends for
+pub const BACKEND_KINDS: &[&str] = &["git", "dir", "http"];
+
+/// Name of the directory inside the cache where backends keep local state
+const BACKENDS_DIR: &str = ".backends";
+
+/// Storage that store entries can be synced with
+///
+/// Entries are identified by repository id and hashed with
+/// [`unjust_core::content_hash`], so hashes can be compared with the store.
+pub trait StorageBackend: Send + Sync {
+    /// List the entries in the storage with their content hashes
+    fn list(&self) -> io::Result<BTreeMap<String, String>>;
+
+    /// Get an entry's files
+    fn fetch(&self, repo: &str) -> io::Result<EntryContent>;
+
+    /// Store an entry's files, replacing any already there, and return the
+    /// stored entry's content hash
+    fn put(&self, repo: &str, content: &EntryContent) -> io::Result<String>;
+
+    /// Remove an entry
+    fn delete(&self, repo: &str) -> io::Result<()>;
+
+    /// Identify the current state of the storage as a whole, if it has a
+    /// notion of one (such as a commit)
+    fn revision(&self) -> io::Result<Option<String>>;
+
+    /// Publish the changes made by `put` and `delete`
+    ///
+    /// Backends that apply changes straight away don't need to do anything.
+    fn finish(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Open the backend for a configured remote
+pub fn open_backend(cache_dir: &Path, remote: &Remote) -> io::Result<Box<dyn StorageBackend>> {
+    let state_dir = cache_dir.join(BACKENDS_DIR).join(&remote.name);
+    match remote.kind.as_str() {
+        "git" => Ok(Box::new(GitBackend::open(&remote.location, &state_dir)?)),
+        "dir" => Ok(Box::new(DirectoryBackend::open(Path::new(
+            &remote.location,
+        ))?)),
+        "http" => Ok(Box::new(HttpBackend::open(&remote.location)?)),
+        kind => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Unknown storage kind for remote {}: {kind} (expected one of {})",
+                remote.name,
+                BACKEND_KINDS.join(", ")
+            ),
+        )),
+    }
+}
@@ -0,0 +1,187 @@
+//! Merging entries changed on both sides of a sync
+
+use std::collections::BTreeSet;
+
+use unjust_core::{MANIFEST_FILE, Manifest, merge_lines};
+
+use crate::entry::EntryContent;
+
+/// Merge the local and remote versions of an entry against the content
+/// they were both changed from
+///
+/// Files are merged line by line. The local manifest is kept, with its tags
+/// merged with the remote's. Returns the names of the files that couldn't be
+/// merged if there are any.
+pub fn merge_entries(
+    base: &EntryContent,
+    local: &EntryContent,
+    remote: &EntryContent,
+) -> Result<EntryContent, Vec<String>> {
+    let names: BTreeSet<&String> = base
+        .files
+        .keys()
+        .chain(local.files.keys())
+        .chain(remote.files.keys())
+        .collect();
+
+    let mut merged = EntryContent::default();
+    let mut conflicts = Vec::new();
+    for name in names {
+        let (b, l, r) = (
+            base.files.get(name),
+            local.files.get(name),
+            remote.files.get(name),
+        );
+        let result = if name == MANIFEST_FILE {
+            Some(merge_manifest(b, l, r))
+        } else if l == r || r == b {
+            Some(l.cloned())
+        } else if l == b {
+            Some(r.cloned())
+        } else {
+            merge_text(b, l, r).map(Some)
+        };
+
+        match result {
+            Some(Some(content)) => {
+                merged.files.insert(name.clone(), content);
+            }
+            Some(None) => {}
+            None => conflicts.push(name.clone()),
+        }
+    }
+
+    if conflicts.is_empty() {
+        Ok(merged)
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// Merge the manifests of both sides, keeping the local one's metadata
+///
+/// Tags are merged as sets against the base: a tag either side added is
+/// kept, and one either side removed is dropped.
+fn merge_manifest(
+    base: Option<&Vec<u8>>,
+    local: Option<&Vec<u8>>,
+    remote: Option<&Vec<u8>>,
+) -> Option<Vec<u8>> {
+    let parse = |bytes: &Vec<u8>| Manifest::from_toml(&String::from_utf8_lossy(bytes)).ok();
+    let (Some(local), Some(remote)) = (local, remote) else {
+        return local.or(remote).cloned();
+    };
+    let (Some(mut merged), Some(theirs)) = (parse(local), parse(remote)) else {
+        return Some(local.clone());
+    };
+
+    let base = base.and_then(parse).unwrap_or_default().tags;
+    let mut tags: Vec<String> = merged
+        .tags
+        .iter()
+        .filter(|tag| theirs.tags.contains(tag) || !base.contains(tag))
+        .cloned()
+        .collect();
+    tags.extend(
+        theirs
+            .tags
+            .into_iter()
+            .filter(|tag| !merged.tags.contains(tag) && !base.contains(tag)),
+    );
+    merged.tags = tags;
+    Some(merged.to_toml().into_bytes())
+}
+
+/// Merge a text file changed on both sides, if neither side removed it
+fn merge_text(
+    base: Option<&Vec<u8>>,
+    local: Option<&Vec<u8>>,
+    remote: Option<&Vec<u8>>,
+) -> Option<Vec<u8>> {
+    let text = |bytes: &Vec<u8>| String::from_utf8(bytes.clone()).ok();
+    let base = match base {
+        Some(base) => text(base)?,
+        None => String::new(),
+    };
+    let merged = merge_lines(&base, &text(local?)?, &text(remote?)?)?;
+    Some(merged.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(files: &[(&str, &str)]) -> EntryContent {
+        EntryContent {
+            files: files
+                .iter()
+                .map(|(name, content)| (name.to_string(), content.as_bytes().to_vec()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_merge_entries() {
+        let base = entry(&[
+            (
+                "Justfile",
+                "import 'common.just'\n\nbuild:\n    cargo build\n",
+            ),
+            ("common.just", "fmt:\n"),
+            ("old.just", "x:\n"),
+        ]);
+        let local = entry(&[
+            (
+                "Justfile",
+                "import 'common.just'\n\nbuild:\n    cargo build --release\n",
+            ),
+            ("common.just", "fmt:\n"),
+            (MANIFEST_FILE, "tags = [\"local\"]\n"),
+        ]);
+        let remote = entry(&[
+            (
+                "Justfile",
+                "# Shared\nimport 'common.just'\n\nbuild:\n    cargo build\n",
+            ),
+            ("common.just", "fmt:\n    cargo fmt\n"),
+            ("old.just", "x:\n"),
+            (MANIFEST_FILE, "tags = [\"remote\"]\n"),
+        ]);
+
+        let merged = merge_entries(&base, &local, &remote).unwrap();
+        assert_eq!(
+            merged,
+            entry(&[
+                (
+                    "Justfile",
+                    "# Shared\nimport 'common.just'\n\nbuild:\n    cargo build --release\n"
+                ),
+                ("common.just", "fmt:\n    cargo fmt\n"),
+                (MANIFEST_FILE, "tags = [\"local\", \"remote\"]\n"),
+            ])
+        );
+
+        let clashing = entry(&[
+            ("Justfile", "build:\n    make\n"),
+            ("common.just", "fmt:\n"),
+        ]);
+        assert_eq!(
+            merge_entries(&base, &local, &clashing),
+            Err(vec!["Justfile".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_merge_manifest_tags() {
+        let manifest = |tags: &str| entry(&[(MANIFEST_FILE, tags)]);
+        let base = manifest("tags = [\"a\", \"b\"]\nlast_used = 1\n");
+        let local = manifest("tags = [\"a\", \"c\"]\nlast_used = 2\n");
+        let remote = manifest("tags = [\"b\", \"d\"]\nlast_used = 3\n");
+
+        // Each side's additions are kept and each side's removals dropped
+        assert_eq!(
+            merge_entries(&base, &local, &remote),
+            Ok(manifest("last_used = 2\ntags = [\"c\", \"d\"]\n"))
+        );
+    }
+}
@@ -1,16 +1,778 @@
-use console::style;
+use console::{Term, style};
 use facet::Facet;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use unjust_core::{
+    Change, Justfile, PlannedSync, Remote, StoreLock, SyncAction, SyncRecord, SyncState,
+    content_hash, ensure_cache_dir, entry_dir, entry_statuses, justfile_in_dir, list_justfiles,
+    local_hashes, pending_changes, plan_sync, provided_statuses, queue_local_changes,
+    read_manifest, read_remotes, read_sync_state, record_content_change, record_remote_hashes,
+    release_entries, remote_statuses, remove_justfile, revision_dir, revisions, settle_changes,
+    snapshot, update_manifest, write_sync_state,
+};
+use unjust_storage::{
+    EntryContent, StorageBackend, merge_entries, open_backend, read_entry, write_entry,
+};
 
 /// Arguments for the "sync" command
 #[derive(Facet, Debug)]
-pub struct SyncArgs<'a> {
+pub struct SyncArgs {
     /// Repo to sync (if specific)
-    #[facet(positional)]
-    pub repo: Option<&'a str>,
+    #[facet(positional, default)]
+    pub repo: Option<String>,
 
     /// Force push to remote repo
     #[facet(named)]
     pub force_push: bool,
+
+    /// Print what would be synced without changing anything
+    #[facet(named)]
+    pub dry_run: bool,
+
+    /// Only sync with this remote
+    #[facet(named, default)]
+    pub remote: Option<String>,
+
+    /// How many entries to sync at once
+    #[facet(named, short = 'j', default)]
+    pub jobs: Option<usize>,
+}
+
+/// How many entries are synced at once unless `--jobs` says otherwise
+const DEFAULT_JOBS: usize = 8;
+
+/// What syncing one entry did
+enum Outcome {
+    /// Both sides now have the entry with this content hash, or neither has
+    /// it any more
+    Synced(Option<String>),
+    /// The entry was left alone as these files changed on both sides
+    Conflict(Vec<String>),
+    /// Local changes were kept without touching the remote, as it's
+    /// read-only
+    Kept,
+}
+
+/// Get the remotes to sync with, in priority order
+fn remotes_to_sync(cache_dir: &Path, only: Option<&str>) -> io::Result<Vec<Remote>> {
+    let remotes = read_remotes(cache_dir)?;
+    let Some(name) = only else {
+        return Ok(remotes);
+    };
+    let remote = remotes
+        .into_iter()
+        .find(|r| r.name == name)
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("No remote named {name}"))
+        })?;
+    Ok(vec![remote])
+}
+
+/// Plan a sync with a remote of every entry it provides, or just one
+fn plan(
+    cache_dir: &Path,
+    remote: &Remote,
+    state: &SyncState,
+    repo: Option<&str>,
+    force_push: bool,
+) -> io::Result<Vec<PlannedSync>> {
+    let statuses = remote_statuses(cache_dir, &list_justfiles()?, &remote.name, state)?
+        .into_iter()
+        .filter(|s| repo.is_none_or(|repo| s.repo == repo))
+        .collect();
+    plan_sync(cache_dir, statuses, force_push, remote.read_only)
+}
+
+/// Get the stored Justfile for an entry
+fn stored_justfile(cache_dir: &Path, repo: &str) -> io::Result<Justfile> {
+    let path = justfile_in_dir(&entry_dir(cache_dir, repo)?)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Justfile not found for repo: {repo}"),
+        )
+    })?;
+    Ok(Justfile::new(repo.to_string(), path))
+}
+
+/// What's left to do for an entry once its local changes are made
+enum Step {
+    /// Nothing, the entry is done with
+    Done(Outcome),
+    /// Send the entry to the remote
+    Put(EntryContent),
+    /// Delete the entry from the remote
+    Delete,
+}
+
+/// Fetch what an entry's planned action needs from the remote
+fn fetch(backend: &dyn StorageBackend, planned: &PlannedSync) -> io::Result<Option<EntryContent>> {
+    match planned.action {
+        SyncAction::Pull | SyncAction::Merge => backend.fetch(&planned.status.repo).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Make the local changes for one entry's planned action, given what was
+/// fetched for it, returning what's left to do on the remote
+fn apply_local(
+    cache_dir: &Path,
+    remote: &Remote,
+    planned: &PlannedSync,
+    fetched: Option<EntryContent>,
+) -> io::Result<Step> {
+    let repo = planned.status.repo.as_str();
+    let dir = entry_dir(cache_dir, repo)?;
+    let fetched = || {
+        fetched.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Nothing was fetched for {repo}"),
+            )
+        })
+    };
+
+    // Snapshot what gets synced, so later merges have it to work from
+    let synced = |justfile: &Justfile, message: &str| -> io::Result<Outcome> {
+        snapshot(justfile, message)?;
+        record_content_change(justfile)?;
+        Ok(Outcome::Synced(Some(content_hash(&justfile.path)?)))
+    };
+
+    match planned.action {
+        SyncAction::UpToDate => {
+            // Clean entries are left be, unless a later merge would have no
+            // revision of this content to start from
+            let local = planned.status.local.as_deref();
+            if !revisions(&dir)?
+                .iter()
+                .any(|r| Some(r.hash.as_str()) == local)
+            {
+                snapshot(&stored_justfile(cache_dir, repo)?, "Synced")?;
+            }
+            Ok(Step::Done(Outcome::Synced(planned.status.local.clone())))
+        }
+        SyncAction::Pull => {
+            let content = fetched()?;
+            fs::create_dir_all(&dir)?;
+            // How often the entry is used here isn't the remote's business
+            let last_used = read_manifest(&dir)?.last_used;
+            let justfile = Justfile::new(repo.to_string(), write_entry(&dir, &content)?);
+            let outcome = synced(&justfile, &format!("Pulled from {}", remote.name))?;
+            update_manifest(&dir, |manifest| manifest.last_used = last_used)?;
+            Ok(Step::Done(outcome))
+        }
+        SyncAction::Push => {
+            let justfile = stored_justfile(cache_dir, repo)?;
+            snapshot(&justfile, &format!("Pushed to {}", remote.name))?;
+            Ok(Step::Put(read_entry(&dir)?))
+        }
+        SyncAction::Merge => {
+            let base_hash = planned.status.record.base.as_deref();
+            let base_revision = revisions(&dir)?
+                .into_iter()
+                .rev()
+                .find(|r| Some(r.hash.as_str()) == base_hash)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("Last synced revision of {repo} is missing"),
+                    )
+                })?;
+            let base = read_entry(&revision_dir(&dir, base_revision.number))?;
+            let merged = match merge_entries(&base, &read_entry(&dir)?, &fetched()?) {
+                Ok(merged) => merged,
+                Err(files) => return Ok(Step::Done(Outcome::Conflict(files))),
+            };
+
+            snapshot(&stored_justfile(cache_dir, repo)?, "Before merging")?;
+            let justfile = Justfile::new(repo.to_string(), write_entry(&dir, &merged)?);
+            synced(&justfile, &format!("Merged with {}", remote.name))?;
+            if remote.read_only {
+                // The merged copy stays an override of what the remote has
+                let hash = planned.status.record.remote.clone();
+                return Ok(Step::Done(Outcome::Synced(hash)));
+            }
+            Ok(Step::Put(read_entry(&dir)?))
+        }
+        SyncAction::DeleteLocal => {
+            // Removed entries go to the trash, so they can still be restored
+            remove_justfile(cache_dir, repo)?;
+            Ok(Step::Done(Outcome::Synced(None)))
+        }
+        SyncAction::DeleteRemote => Ok(Step::Delete),
+        SyncAction::Conflict => Ok(Step::Done(Outcome::Conflict(Vec::new()))),
+        SyncAction::Override => Ok(Step::Done(Outcome::Kept)),
+    }
+}
+
+/// Make the remote changes left for one entry
+fn apply_remote(backend: &dyn StorageBackend, repo: &str, step: Step) -> io::Result<Outcome> {
+    match step {
+        Step::Done(outcome) => Ok(outcome),
+        Step::Put(content) => Ok(Outcome::Synced(Some(backend.put(repo, &content)?))),
+        Step::Delete => {
+            backend.delete(repo)?;
+            Ok(Outcome::Synced(None))
+        }
+    }
+}
+
+/// A count of synced entries, kept up to date on one line of stderr while
+/// it's a terminal
+struct Progress {
+    term: Term,
+    label: String,
+    total: usize,
+    done: Mutex<usize>,
+}
+
+impl Progress {
+    fn new(label: &str, total: usize) -> Self {
+        Self {
+            term: Term::stderr(),
+            label: label.to_string(),
+            total,
+            done: Mutex::new(0),
+        }
+    }
+
+    /// Count another entry as synced
+    fn tick(&self) {
+        let mut done = self.done.lock().unwrap_or_else(PoisonError::into_inner);
+        *done += 1;
+        if self.term.is_term() {
+            let _ = self.term.clear_line();
+            let _ = self
+                .term
+                .write_str(&format!("{} {}/{}", self.label, *done, self.total));
+        }
+    }
+
+    /// Clear the progress line, leaving the terminal for the report
+    fn finish(&self) {
+        if self.term.is_term() {
+            let _ = self.term.clear_line();
+        }
+    }
+}
+
+/// Run `work` on each item with up to `jobs` threads, returning the results
+/// in the items' order
+fn in_parallel<T: Send, R: Send>(
+    items: Vec<T>,
+    jobs: usize,
+    work: impl Fn(usize, T) -> R + Sync,
+) -> Vec<R> {
+    let count = items.len();
+    let items = Mutex::new(items.into_iter().enumerate());
+    let results = Mutex::new((0..count).map(|_| None).collect::<Vec<_>>());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, count.max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let next = items.lock().unwrap_or_else(PoisonError::into_inner).next();
+                    let Some((index, item)) = next else {
+                        break;
+                    };
+                    let result = work(index, item);
+                    results.lock().unwrap_or_else(PoisonError::into_inner)[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner)
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Carry out a plan, returning the outcome for each entry in plan order
+///
+/// Fetches and remote changes run up to `jobs` entries at a time. Local
+/// changes are made one at a time in plan order in between, as an entry's
+/// directory can hold nested and subpath entries, and the store lock
+/// doesn't keep this process's threads apart.
+fn apply_all(
+    cache_dir: &Path,
+    remote: &Remote,
+    backend: &dyn StorageBackend,
+    plan: &[PlannedSync],
+    jobs: usize,
+) -> Vec<io::Result<Outcome>> {
+    let progress = Progress::new(&format!("Syncing with {}", remote.name), plan.len());
+
+    let fetched = in_parallel(plan.iter().collect(), jobs, |_, planned| {
+        fetch(backend, planned)
+    });
+    let steps: Vec<io::Result<Step>> = plan
+        .iter()
+        .zip(fetched)
+        .map(|(planned, fetched)| {
+            let step = fetched.and_then(|fetched| apply_local(cache_dir, remote, planned, fetched));
+            if !matches!(step, Ok(Step::Put(_) | Step::Delete)) {
+                progress.tick();
+            }
+            step
+        })
+        .collect();
+
+    let outcomes = in_parallel(steps, jobs, |index, step| {
+        let remote_step = matches!(step, Ok(Step::Put(_) | Step::Delete));
+        let outcome = step.and_then(|step| apply_remote(backend, &plan[index].status.repo, step));
+        if remote_step {
+            progress.tick();
+        }
+        outcome
+    });
+    progress.finish();
+
+    outcomes
+}
+
+/// Print a sync plan, one line per entry followed by a summary
+///
+/// With `files` set, the files each push would send are listed too.
+fn print_plan(cache_dir: &Path, plan: &[PlannedSync], files: bool) {
+    if plan.is_empty() {
+        println!("Nothing to sync.");
+        return;
+    }
+
+    let width = plan.iter().map(|p| p.status.repo.len()).max().unwrap_or(0);
+    for planned in plan {
+        let action = format!("{:13}", planned.action.as_str());
+        let action = match planned.action {
+            SyncAction::UpToDate => style(action).dim(),
+            SyncAction::Pull => style(action).cyan(),
+            SyncAction::Push => style(action).green(),
+            SyncAction::Merge => style(action).yellow(),
+            SyncAction::DeleteLocal | SyncAction::DeleteRemote => style(action).red(),
+            SyncAction::Conflict => style(action).red().bold(),
+            SyncAction::Override => style(action).magenta(),
+        };
+        println!(
+            "{} {:width$}  ({})",
+            action,
+            planned.status.repo,
+            planned.status.state.as_str()
+        );
+
+        let pushing = matches!(planned.action, SyncAction::Push | SyncAction::Merge);
+        if files && pushing {
+            let content = entry_dir(cache_dir, &planned.status.repo).and_then(|d| read_entry(&d));
+            for name in content.iter().flat_map(|c| c.files.keys()) {
+                println!("{:14}{}", "", style(name).dim());
+            }
+        }
+    }
+
+    let count = |action| plan.iter().filter(|p| p.action == action).count();
+    println!(
+        "\n{} to pull, {} to push, {} to merge, {} to delete, {} in conflict",
+        count(SyncAction::Pull),
+        count(SyncAction::Push),
+        count(SyncAction::Merge),
+        count(SyncAction::DeleteLocal) + count(SyncAction::DeleteRemote),
+        count(SyncAction::Conflict)
+    );
+    let overrides = count(SyncAction::Override);
+    if overrides > 0 {
+        println!("{overrides} kept as personal overrides of a read-only remote");
+    }
+}
+
+/// Print the operations queued in the store since the last sync
+fn print_pending_changes(cache_dir: &Path) -> io::Result<()> {
+    for change in pending_changes(cache_dir)? {
+        match change {
+            Change::Added { repo } => println!("Added since the last sync: {}", repo),
+            Change::Edited { repo } => println!("Edited since the last sync: {}", repo),
+            Change::Removed { repo } => println!("Removed since the last sync: {}", repo),
+            Change::Renamed { from, to } => {
+                println!("Renamed since the last sync: {} to {}", from, to)
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Make the changes a plan would make to the local content hashes and sync
+/// states of a dry run, so the next remote is compared with the store as
+/// the sync would leave it
+fn simulate(
+    plan: &[PlannedSync],
+    remote: &Remote,
+    local: &mut BTreeMap<String, String>,
+    states: &mut BTreeMap<String, SyncState>,
+) {
+    let state = states.entry(remote.name.clone()).or_default();
+    for planned in plan {
+        let repo = &planned.status.repo;
+        let remote_hash = planned.status.record.remote.clone();
+        let synced = match planned.action {
+            SyncAction::UpToDate | SyncAction::Push => planned.status.local.clone(),
+            SyncAction::Pull => {
+                if let Some(hash) = &remote_hash {
+                    local.insert(repo.clone(), hash.clone());
+                }
+                remote_hash
+            }
+            SyncAction::Merge => {
+                // What the merge comes to isn't known without fetching, so
+                // it stands in as content of its own
+                let merged = format!("merged:{}", remote_hash.as_deref().unwrap_or("-"));
+                local.insert(repo.clone(), merged.clone());
+                if remote.read_only {
+                    remote_hash
+                } else {
+                    Some(merged)
+                }
+            }
+            SyncAction::DeleteLocal | SyncAction::DeleteRemote => {
+                local.remove(repo);
+                state.remove(repo);
+                continue;
+            }
+            SyncAction::Conflict | SyncAction::Override => continue,
+        };
+        state.insert(
+            repo.clone(),
+            SyncRecord {
+                base: synced.clone(),
+                remote: synced,
+            },
+        );
+    }
+
+    // As with a real sync, other remotes stop providing what this one synced
+    let synced: Vec<String> = state
+        .iter()
+        .filter(|(_, r)| r.base.is_some())
+        .map(|(repo, _)| repo.clone())
+        .collect();
+    for (name, other) in states.iter_mut() {
+        if name == &remote.name {
+            continue;
+        }
+        for repo in &synced {
+            if let Some(record) = other.get_mut(repo) {
+                record.base = None;
+            }
+        }
+    }
+}
+
+/// Print the plan a sync would carry out
+///
+/// With several remotes, each is compared with the store as syncing with
+/// the ones before it would leave it. Git remotes are fetched into their
+/// local clone to be compared with, but nothing is changed on any remote.
+fn dry_run(cache_dir: &Path, sync_args: &SyncArgs) -> io::Result<()> {
+    let remotes = remotes_to_sync(cache_dir, sync_args.remote.as_deref())?;
+    if remotes.is_empty() {
+        println!(
+            "{} nothing will be changed. No remote is configured, so every entry is new",
+            style("Dry run,").yellow()
+        );
+        let statuses = entry_statuses(&list_justfiles()?, &SyncState::new())?
+            .into_iter()
+            .filter(|s| sync_args.repo.as_ref().is_none_or(|repo| &s.repo == repo))
+            .collect();
+        let plan = plan_sync(cache_dir, statuses, sync_args.force_push, false)?;
+        print_plan(cache_dir, &plan, true);
+        return print_pending_changes(cache_dir);
+    }
+
+    let all_remotes = read_remotes(cache_dir)?;
+    let mut local = local_hashes(&list_justfiles()?)?;
+    let mut states = BTreeMap::new();
+    for remote in &all_remotes {
+        states.insert(
+            remote.name.clone(),
+            read_sync_state(cache_dir, &remote.name)?,
+        );
+    }
+
+    for (i, remote) in remotes.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "{} nothing will be changed. Comparing with {}",
+            style("Dry run,").yellow(),
+            style(&remote.name).green()
+        );
+        let backend = open_backend(cache_dir, remote)?;
+        let state = states.entry(remote.name.clone()).or_default();
+        record_remote_hashes(state, &backend.list()?);
+        let statuses = provided_statuses(&all_remotes, &local, &states, &remote.name)?
+            .into_iter()
+            .filter(|s| sync_args.repo.as_ref().is_none_or(|repo| &s.repo == repo))
+            .collect();
+        let plan = plan_sync(cache_dir, statuses, sync_args.force_push, remote.read_only)?;
+        print_plan(cache_dir, &plan, true);
+        simulate(&plan, remote, &mut local, &mut states);
+    }
+    print_pending_changes(cache_dir)
+}
+
+/// Whether the remote still has what was last synced of an entry, so it
+/// can be changed there without losing anything
+fn unchanged_on_remote(state: &SyncState, repo: &str) -> bool {
+    state
+        .get(repo)
+        .is_some_and(|r| r.base.is_some() && r.base == r.remote)
+}
+
+/// Delete an entry removed from the store from the remote, if the remote
+/// hasn't moved on since the last sync
+fn replay_removal(
+    cache_dir: &Path,
+    backend: &dyn StorageBackend,
+    state: &mut SyncState,
+    repo: &str,
+) -> io::Result<bool> {
+    let present = justfile_in_dir(&entry_dir(cache_dir, repo)?)?.is_some();
+    if present || !unchanged_on_remote(state, repo) {
+        return Ok(false);
+    }
+    backend.delete(repo)?;
+    state.remove(repo);
+    Ok(true)
+}
+
+/// Replay the queued removals and renames against a remote in log order,
+/// returning the entries they failed for
+///
+/// A rename sends the entry under its new id before deleting the old one.
+/// Operations the store or the remote have moved on from since are left to
+/// the comparison that follows, which reports them as conflicts where both
+/// sides changed.
+fn replay_queued(
+    cache_dir: &Path,
+    remote: &Remote,
+    backend: &dyn StorageBackend,
+    state: &mut SyncState,
+    only: Option<&str>,
+) -> io::Result<Vec<String>> {
+    if remote.read_only {
+        return Ok(Vec::new());
+    }
+
+    let mut failed = Vec::new();
+    for change in pending_changes(cache_dir)? {
+        if only.is_some_and(|repo| !change.repos().contains(&repo)) {
+            continue;
+        }
+        let replayed = match &change {
+            Change::Removed { repo } => replay_removal(cache_dir, backend, state, repo),
+            Change::Renamed { from, to } if unchanged_on_remote(state, from) => {
+                let dir = entry_dir(cache_dir, to)?;
+                let new = state.get(to.as_str()).is_none_or(|r| r.remote.is_none());
+                let sent = match justfile_in_dir(&dir)? {
+                    Some(path) if new => {
+                        let justfile = Justfile::new(to.clone(), path);
+                        snapshot(&justfile, &format!("Pushed to {}", remote.name))
+                            .and_then(|_| backend.put(to, &read_entry(&dir)?))
+                            .map(|hash| {
+                                state.insert(
+                                    to.clone(),
+                                    SyncRecord {
+                                        base: Some(hash.clone()),
+                                        remote: Some(hash),
+                                    },
+                                );
+                            })
+                    }
+                    _ => Ok(()),
+                };
+                sent.and_then(|()| replay_removal(cache_dir, backend, state, from))
+            }
+            Change::Added { .. } | Change::Edited { .. } | Change::Renamed { .. } => Ok(false),
+        };
+        match replayed {
+            Ok(true) => println!("Replayed queued {change}"),
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!(
+                    "{} Could not replay queued {}: {}",
+                    style("Error:").red().bold(),
+                    change,
+                    e
+                );
+                failed.extend(change.repos().into_iter().map(String::from));
+            }
+        }
+    }
+    Ok(failed)
+}
+
+/// Sync the store with one remote, returning the entries that didn't sync
+fn sync_remote(cache_dir: &Path, remote: &Remote, sync_args: &SyncArgs) -> io::Result<Vec<String>> {
+    let access = if remote.read_only { ", read-only" } else { "" };
+    println!(
+        "Syncing with {} ({} {}{})",
+        style(&remote.name).green(),
+        remote.kind,
+        remote.location,
+        access
+    );
+    let backend = open_backend(cache_dir, remote)?;
+
+    let mut state = read_sync_state(cache_dir, &remote.name)?;
+    record_remote_hashes(&mut state, &backend.list()?);
+    let mut unsynced = replay_queued(
+        cache_dir,
+        remote,
+        backend.as_ref(),
+        &mut state,
+        sync_args.repo.as_deref(),
+    )?;
+    let mut plan = plan(
+        cache_dir,
+        remote,
+        &state,
+        sync_args.repo.as_deref(),
+        sync_args.force_push,
+    )?;
+
+    let jobs = sync_args.jobs.unwrap_or(DEFAULT_JOBS);
+    let outcomes = apply_all(cache_dir, remote, backend.as_ref(), &plan, jobs);
+
+    for (planned, outcome) in plan.iter_mut().zip(outcomes) {
+        let repo = planned.status.repo.clone();
+        match outcome {
+            Ok(Outcome::Synced(Some(hash))) => {
+                let record = state.entry(repo).or_default();
+                record.base = Some(hash.clone());
+                record.remote = Some(hash);
+            }
+            Ok(Outcome::Synced(None)) => {
+                state.remove(&repo);
+            }
+            Ok(Outcome::Kept) => {}
+            Ok(Outcome::Conflict(files)) => {
+                unsynced.push(repo.clone());
+                planned.action = SyncAction::Conflict;
+                if !files.is_empty() {
+                    eprintln!(
+                        "{} {} changed on both sides in: {}",
+                        style("Conflict:").red().bold(),
+                        repo,
+                        files.join(", ")
+                    );
+                }
+            }
+            Err(e) => {
+                eprintln!("{} {}: {}", style("Error:").red().bold(), repo, e);
+                unsynced.push(repo);
+            }
+        }
+    }
+
+    if !remote.read_only {
+        backend.finish()?;
+    }
+    write_sync_state(cache_dir, &remote.name, &state)?;
+    release_entries(cache_dir, &remote.name, &state)?;
+
+    print_plan(cache_dir, &plan, false);
+    if let Some(revision) = backend.revision()? {
+        println!("Remote is at revision {}", revision);
+    }
+    Ok(unsynced)
+}
+
+/// Report which of the operations queued before a sync the remotes have
+/// caught up with, dropping those from the log
+///
+/// `unsynced` maps each entry that didn't sync to the remote it failed with.
+fn settle_queued_changes(cache_dir: &Path, unsynced: &BTreeMap<String, String>) -> io::Result<()> {
+    let settled = settle_changes(cache_dir)?;
+    let queued = pending_changes(cache_dir)?;
+    if settled.is_empty() && queued.is_empty() {
+        return Ok(());
+    }
+
+    println!();
+    if !settled.is_empty() {
+        println!("Queued operations now on the remote: {}", settled.len());
+    }
+    for change in &queued {
+        let conflict = change
+            .repos()
+            .into_iter()
+            .find_map(|repo| unsynced.get(repo));
+        if let Some(remote) = conflict {
+            eprintln!(
+                "{} queued {} conflicts with {}",
+                style("Conflict:").red().bold(),
+                change,
+                remote
+            );
+        }
+    }
+    if !queued.is_empty() {
+        println!(
+            "Operations still queued for the next sync: {}",
+            queued.len()
+        );
+    }
+    Ok(())
+}
+
+/// Sync the store with each remote in priority order, returning whether
+/// every entry synced
+fn sync(cache_dir: &Path, sync_args: &SyncArgs) -> io::Result<bool> {
+    let remotes = remotes_to_sync(cache_dir, sync_args.remote.as_deref())?;
+    if remotes.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No remote configured. Use 'unjust remote add <name> <kind> <location>' to add one",
+        ));
+    }
+
+    // Nothing else changes the store while it's synced
+    let _lock = StoreLock::acquire(cache_dir)?;
+
+    // Log what changed by hand since the last sync, so it's tracked too
+    queue_local_changes(cache_dir, &list_justfiles()?)?;
+
+    let mut unsynced = BTreeMap::new();
+    let mut unreachable = false;
+    for (i, remote) in remotes.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        // An unreachable remote leaves its queued operations for next time
+        match sync_remote(cache_dir, remote, sync_args) {
+            Ok(repos) => {
+                for repo in repos {
+                    unsynced.entry(repo).or_insert_with(|| remote.name.clone());
+                }
+            }
+            Err(e) => {
+                unreachable = true;
+                eprintln!(
+                    "{} Could not sync with {}: {}",
+                    style("Error:").red().bold(),
+                    remote.name,
+                    e
+                );
+            }
+        }
+    }
+
+    settle_queued_changes(cache_dir, &unsynced)?;
+    Ok(unsynced.is_empty() && !unreachable)
 }
 
 /// Handle the "sync" command
@@ -26,16 +788,26 @@ pub fn handle_sync_command(args: &[&str]) -> i32 {
         }
     };
 
-    println!(
-        "{} Sync command not fully implemented yet",
-        style("Note:").yellow().bold()
-    );
+    let result = ensure_cache_dir().and_then(|cache_dir| {
+        if sync_args.dry_run {
+            dry_run(&cache_dir, &sync_args).map(|()| true)
+        } else {
+            sync(&cache_dir, &sync_args)
+        }
+    });
 
-    if let Some(repo) = sync_args.repo {
-        println!("Would sync repo: {}", repo);
-    } else {
-        println!("Would sync all repos");
+    match result {
+        Ok(true) => 0,
+        Ok(false) => {
+            eprintln!(
+                "{} Some entries were not synced, see above",
+                style("Warning:").yellow().bold()
+            );
+            1
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
     }
-
-    0
 }
@@ -0,0 +1,143 @@
+use console::style;
+use facet::Facet;
+use unjust_core::{
+    ensure_cache_dir, list_justfiles, resolve_repo_for_dir, tag_entry, tagged_entries, untag_entry,
+};
+
+/// Arguments for the "tag" command
+#[derive(Facet, Debug)]
+pub struct TagArgs {
+    /// Action to take: add, remove or list
+    #[facet(positional, default)]
+    pub action: Option<String>,
+
+    /// Tag to add or remove
+    #[facet(positional, default)]
+    pub tag: Option<String>,
+
+    /// Repo identifier (username/repo), detected if omitted
+    #[facet(positional, default)]
+    pub repo: Option<String>,
+}
+
+/// List every tag along with the Justfiles carrying it
+fn list_tags() -> i32 {
+    let tags = match list_justfiles().and_then(|justfiles| tagged_entries(&justfiles)) {
+        Ok(tags) => tags,
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    if tags.is_empty() {
+        println!(
+            "No tags. Use '{}' to add one.",
+            style("unjust tag add <tag> <repo>").green()
+        );
+        return 0;
+    }
+
+    for (tag, repos) in tags {
+        println!("{}: {}", style(tag).green(), repos.join(", "));
+    }
+    0
+}
+
+/// Handle the "tag" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_tag_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let tag_args = match facet_args::from_slice::<TagArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let adding = match tag_args.action.as_deref() {
+        None | Some("list") => return list_tags(),
+        Some("add") => true,
+        Some("remove") => false,
+        Some(action) => {
+            eprintln!(
+                "{} Unknown tag action: {} (expected add, remove or list)",
+                style("Error:").red().bold(),
+                action
+            );
+            return 1;
+        }
+    };
+
+    let Some(tag) = tag_args.tag.as_deref() else {
+        eprintln!("{} No tag given", style("Error:").red().bold());
+        return 1;
+    };
+
+    // Use the given repo, or the one for the current directory
+    let repo = match tag_args.repo {
+        Some(repo) => Some(repo),
+        None => match std::env::current_dir().and_then(|dir| resolve_repo_for_dir(&dir, false)) {
+            Ok(repo) => repo,
+            Err(e) => {
+                eprintln!("{} {}", style("Error:").red().bold(), e);
+                return 1;
+            }
+        },
+    };
+    let Some(repo) = repo else {
+        eprintln!(
+            "{} Repository not specified and could not be detected",
+            style("Error:").red().bold()
+        );
+        return 1;
+    };
+
+    let result = ensure_cache_dir().and_then(|cache_dir| {
+        if adding {
+            tag_entry(&cache_dir, &repo, tag)
+        } else {
+            untag_entry(&cache_dir, &repo, tag)
+        }
+    });
+
+    match result {
+        Ok(true) if adding => {
+            println!(
+                "{} Tagged {} with {}",
+                style("Success:").green().bold(),
+                style(&repo).green(),
+                style(tag).green()
+            );
+            0
+        }
+        Ok(true) => {
+            println!(
+                "{} Removed tag {} from {}",
+                style("Success:").green().bold(),
+                style(tag).green(),
+                style(&repo).green()
+            );
+            0
+        }
+        Ok(false) if adding => {
+            println!("{} is already tagged {}", style(&repo).green(), tag);
+            0
+        }
+        Ok(false) => {
+            eprintln!(
+                "{} {} is not tagged {}",
+                style("Error:").red().bold(),
+                repo,
+                tag
+            );
+            1
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            1
+        }
+    }
+}
@@ -1,22 +1,143 @@
 use console::style;
 use facet::Facet;
-use unjust_core::find_justfile;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+use unjust_core::{
+    Justfile, LinkMode, UsageRecord, add_exclude_entry, checkout_layers, compose_layers,
+    ensure_cache_dir, find_justfile, find_pin, justfile_files, link_justfile, materialised_in, now,
+    pin_outdated, record_usage, record_use, remove_exclude_entry, resolve_repo_for_dir,
+    split_repo_id, unlink_justfile, worktree_root,
+};
 use which::which;
 
 /// Arguments for the "use" command
 #[derive(Facet, Debug)]
-pub struct UseArgs<'a> {
-    /// Repo identifier (username/repo)
-    #[facet(positional)]
-    pub repo: Option<&'a str>,
+pub struct UseArgs {
+    /// Repo identifier (username/repo), detected if omitted
+    #[facet(positional, default)]
+    pub repo: Option<String>,
 
     /// Store upstream and current repo Justfiles separately
     #[facet(named)]
     pub separate_upstream_justfile: bool,
 
-    /// Force refresh from remote
+    /// Force refresh from remote, and replace a linked copy even if it has
+    /// local changes
     #[facet(named, short = 'f')]
     pub force: bool,
+
+    /// Symlink the Justfile into the current repo
+    #[facet(named)]
+    pub link: bool,
+
+    /// Copy the Justfile into the current repo instead of symlinking it
+    #[facet(named)]
+    pub copy: bool,
+
+    /// Recipe to run with the Justfile
+    #[facet(named, short = 'r', default)]
+    pub recipe: Option<String>,
+}
+
+/// Arguments for the "unlink" command
+#[derive(Facet, Debug)]
+pub struct UnlinkArgs {
+    /// Remove a linked copy even if it has local changes
+    #[facet(named, short = 'f')]
+    pub force: bool,
+}
+
+/// Get the root of the current git working tree, or the current directory
+/// outside of git
+fn checkout_dir() -> std::io::Result<PathBuf> {
+    let current_dir = std::env::current_dir()?;
+    Ok(worktree_root(&current_dir)?.unwrap_or(current_dir))
+}
+
+/// Get the directory a repo's Justfile should be materialised in: the
+/// checkout, or its subdirectory for subpath-scoped ids
+/// (`username/repo//packages/api`)
+fn materialise_dir(repo: &str) -> std::io::Result<PathBuf> {
+    let current_dir = std::env::current_dir()?;
+    Ok(
+        match (worktree_root(&current_dir)?, split_repo_id(repo).1) {
+            (Some(root), Some(subpath)) => root.join(subpath),
+            (Some(root), None) => root,
+            (None, _) => current_dir,
+        },
+    )
+}
+
+/// Find the directory holding the Justfile unjust placed for the current
+/// directory: the nearest one with a record, up to the checkout
+fn linked_dir(cache_dir: &Path) -> std::io::Result<PathBuf> {
+    let current_dir = std::env::current_dir()?;
+    let checkout = checkout_dir()?;
+    for dir in current_dir.ancestors() {
+        if materialised_in(cache_dir, dir)?.is_some() {
+            return Ok(dir.to_path_buf());
+        }
+        if dir == checkout {
+            break;
+        }
+    }
+    Ok(checkout)
+}
+
+/// Say which revision this checkout has pinned a stored Justfile to, if
+/// any, warning when the entry has moved on since
+fn report_pin(cache_dir: &Path, checkout: &Path, justfile: &Justfile) -> std::io::Result<()> {
+    let Some(pin) = find_pin(cache_dir, checkout, &justfile.repo_name)? else {
+        return Ok(());
+    };
+    if pin_outdated(cache_dir, &pin)? {
+        eprintln!(
+            "{} {} has changed since it was pinned at revision {}. Run '{}' to use the latest",
+            style("Warning:").yellow().bold(),
+            style(&pin.repo).green(),
+            pin.revision,
+            style("unjust update").green()
+        );
+    }
+    println!("Using revision {} pinned for this checkout", pin.revision);
+    Ok(())
+}
+
+/// Run a recipe from a Justfile in `dir`, returning just's exit code
+fn run_recipe(justfile: &Path, dir: &Path, recipe: &str) -> std::io::Result<i32> {
+    let status = Command::new("just")
+        .arg("--justfile")
+        .arg(justfile)
+        .arg("--working-directory")
+        .arg(dir)
+        .arg(recipe)
+        .status()?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Add a use to the history, warning rather than failing if it can't be
+/// written
+fn record_history(repo: &str, recipe: Option<String>, exit_code: i32, started: Instant) {
+    let time = now();
+    let recorded = ensure_cache_dir().and_then(|cache_dir| {
+        let record = UsageRecord {
+            time,
+            repo: repo.to_string(),
+            recipe,
+            exit_code,
+            duration_ms: started.elapsed().as_millis() as u64,
+            cwd: std::env::current_dir()?,
+        };
+        record_usage(&cache_dir, &record)
+    });
+    if let Err(e) = recorded {
+        eprintln!(
+            "{} Could not record usage history: {}",
+            style("Warning:").yellow().bold(),
+            e
+        );
+    }
 }
 
 /// Handle the "use" command
@@ -32,18 +153,100 @@ pub fn handle_use_command(args: &[&str]) -> i32 {
         }
     };
 
-    // Get the repo name
-    let repo = match use_args.repo {
+    // Get the repo name, falling back to bindings and git remotes
+    let detected = match use_args.repo {
+        Some(repo) => Some(repo),
+        None => match std::env::current_dir()
+            .and_then(|dir| resolve_repo_for_dir(&dir, use_args.separate_upstream_justfile))
+        {
+            Ok(detected) => detected,
+            Err(e) => {
+                eprintln!("{} {}", style("Error:").red().bold(), e);
+                return 1;
+            }
+        },
+    };
+    let repo = match detected.as_deref() {
         Some(repo) => repo,
         None => {
-            eprintln!("{} Repository not specified", style("Error:").red().bold());
+            eprintln!(
+                "{} Repository not specified and could not be detected",
+                style("Error:").red().bold()
+            );
+            eprintln!(
+                "Run '{}' to associate this directory with one",
+                style("unjust bind <repo>").green()
+            );
             return 1;
         }
     };
 
-    // Find the Justfile
-    match find_justfile(repo, use_args.separate_upstream_justfile) {
-        Ok(Some(justfile)) => {
+    // Find the Justfile, composing it with its bases if it declares any,
+    // at the revisions this checkout has pinned
+    let found = find_justfile(repo, use_args.separate_upstream_justfile).and_then(|found| {
+        let Some(justfile) = found else {
+            return Ok(None);
+        };
+        if let Err(e) = record_use(&justfile) {
+            eprintln!(
+                "{} Could not record last use: {}",
+                style("Warning:").yellow().bold(),
+                e
+            );
+        }
+        let cache_dir = ensure_cache_dir()?;
+        let checkout = checkout_dir()?;
+        report_pin(&cache_dir, &checkout, &justfile)?;
+        let layers = checkout_layers(&cache_dir, &checkout, &justfile)?;
+        let path = compose_layers(&layers)?;
+        // Only a lone Justfile can be symlinked or copied on its own
+        let standalone = layers.len() == 1 && justfile_files(&path)?.len() == 1;
+        Ok(Some((Justfile::new(justfile.repo_name, path), standalone)))
+    });
+
+    let started = Instant::now();
+    let used = matches!(found, Ok(Some(_)));
+    let exit_code = match found {
+        Ok(Some((justfile, standalone))) if use_args.link || use_args.copy => {
+            let mode = if !standalone {
+                LinkMode::Import
+            } else if use_args.copy {
+                LinkMode::Copy
+            } else {
+                LinkMode::Symlink
+            };
+            let placed = ensure_cache_dir().and_then(|cache_dir| {
+                let dir = materialise_dir(repo)?;
+                link_justfile(&cache_dir, &justfile, &dir, mode, use_args.force)
+            });
+
+            match placed {
+                Ok(target) => {
+                    println!(
+                        "{} Linked Justfile for {} to: {}",
+                        style("Success:").green().bold(),
+                        style(repo).green(),
+                        target.display()
+                    );
+                    if mode == LinkMode::Import {
+                        println!("It spans several files, so it imports the stored Justfile");
+                    }
+                    // Outside of git there's nothing to exclude it from
+                    if let Ok(true) = add_exclude_entry(&target) {
+                        println!("Added {} to .git/info/exclude", target.display());
+                    }
+                    0
+                }
+                Err(e) => {
+                    eprintln!("{} {}", style("Error:").red().bold(), e);
+                    if e.kind() == std::io::ErrorKind::InvalidData {
+                        eprintln!("Use '{}' to replace it anyway", style("--force").green());
+                    }
+                    1
+                }
+            }
+        }
+        Ok(Some((justfile, _))) => {
             // Check if just is installed
             if which("just").is_err() {
                 eprintln!(
@@ -59,12 +262,24 @@ pub fn handle_use_command(args: &[&str]) -> i32 {
                 justfile.path.display()
             );
 
-            // In a real implementation, we would actually execute just here
-            println!(
-                "Would execute just with Justfile: {}",
-                justfile.path.display()
-            );
-            0
+            match use_args.recipe.as_deref() {
+                Some(recipe) => match std::env::current_dir()
+                    .and_then(|dir| run_recipe(&justfile.path, &dir, recipe))
+                {
+                    Ok(code) => code,
+                    Err(e) => {
+                        eprintln!("{} {}", style("Error:").red().bold(), e);
+                        1
+                    }
+                },
+                None => {
+                    println!(
+                        "Would execute just with Justfile: {}",
+                        justfile.path.display()
+                    );
+                    0
+                }
+            }
         }
         Ok(None) => {
             eprintln!(
@@ -83,5 +298,48 @@ pub fn handle_use_command(args: &[&str]) -> i32 {
             eprintln!("{} {}", style("Error:").red().bold(), e);
             1
         }
+    };
+
+    if used {
+        record_history(repo, use_args.recipe, exit_code, started);
+    }
+    exit_code
+}
+
+/// Handle the "unlink" command
+///
+/// Returns the exit code (0 for success, 1 for error)
+pub fn handle_unlink_command(args: &[&str]) -> i32 {
+    // Parse arguments
+    let unlink_args = match facet_args::from_slice::<UnlinkArgs>(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{} {}", style("Error parsing arguments:").red().bold(), e);
+            return 1;
+        }
+    };
+
+    let removed = ensure_cache_dir().and_then(|cache_dir| {
+        let dir = linked_dir(&cache_dir)?;
+        unlink_justfile(&cache_dir, &dir, unlink_args.force)
+    });
+
+    match removed {
+        Ok(target) => {
+            let _ = remove_exclude_entry(&target);
+            println!(
+                "{} Removed linked Justfile: {}",
+                style("Success:").green().bold(),
+                target.display()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{} {}", style("Error:").red().bold(), e);
+            if e.kind() == std::io::ErrorKind::InvalidData {
+                eprintln!("Use '{}' to remove it anyway", style("--force").green());
+            }
+            1
+        }
     }
 }